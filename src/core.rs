@@ -1,8 +1,26 @@
 use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
 use crate::smart_speaker::actors::core_actor::CoreActor;
+use crate::smart_speaker::models::mic_model::list_audio_devices;
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+use crate::smart_speaker::models::speak_model::list_voices;
+use crate::smart_speaker::models::vision_model::Capture;
+use crate::smart_speaker::models::frame_model::{FrameHeader, SharedFrame};
+use crate::utils::calibration_util::{calibration_key, calibration_path, BoardSpec, CameraCalibration};
+use crate::utils::camera_util::{list_cameras, CameraSettings};
 use crate::utils::config_util::Config;
+use crate::utils::doctor_util::{check_audio_file, DoctorReport};
+use crate::utils::fake_pupil_util::{load_gaze_csv, FakePupilFrameSource, FakePupilGazeSource, FakePupilServer};
+use crate::utils::marker_util;
+use crate::utils::replay_util::{DialogueReplay, DialogueTranscript};
+use crate::utils::vision_util;
 use crate::utils::vision_util::VisionType;
 
+/// Shortest time between two views of `calibrate-camera`.
+const CALIBRATION_VIEW_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Smart speaker trait
 pub(crate) trait SmartSpeaker {
     fn start(&mut self);
@@ -62,7 +80,10 @@ impl SmartSpeaker for VisionSmartSpeaker {
 }
 
 /// Run smart speaker
-pub(crate) fn run_smart_speaker(config: Config) {
+pub(crate) fn run_smart_speaker(config: Config) -> Result<()> {
+    if !config.text_input && !config.audio_file_path.is_empty() {
+        check_audio_file(&config.audio_file_path).map_err(|e| anyhow!("--audio-file: {}", e))?;
+    }
     if config.vision {
         match config.vision_type {
             VisionType::None => {
@@ -74,6 +95,9 @@ pub(crate) fn run_smart_speaker(config: Config) {
             VisionType::BuiltInCamera => {
                 VisionSmartSpeaker::new(config).start();
             }
+            VisionType::Recording => {
+                VisionSmartSpeaker::new(config).start();
+            }
         }
     } else {
         match config.vision_type {
@@ -87,6 +111,181 @@ pub(crate) fn run_smart_speaker(config: Config) {
             VisionType::BuiltInCamera => {
                 NonVisionSmartSpeaker::new(config).start();
             }
+            VisionType::Recording => {
+                NonVisionSmartSpeaker::new(config).start();
+            }
         }
     };
+    Ok(())
+}
+
+/// Run fake pupil capture server until the process is killed
+pub(crate) fn run_fake_pupil(config: Config) -> Result<()> {
+    let endpoint = if config.zmq_in_endpoint.is_empty() {
+        "127.0.0.1:50020".to_string()
+    } else {
+        config.zmq_in_endpoint.clone()
+    };
+    let frames = if config.recording_path.is_empty() {
+        FakePupilFrameSource::Synthetic { width: 1280, height: 720 }
+    } else {
+        let mut capture = Capture::new();
+        vision_util::set_recording_capture(&mut capture, &config.recording_path, config.recording_fps, true)?;
+        FakePupilFrameSource::Recording(capture)
+    };
+    let gaze = if config.gaze_recording_path.is_empty() {
+        FakePupilGazeSource::Synthetic
+    } else {
+        FakePupilGazeSource::Recording(load_gaze_csv(&config.gaze_recording_path)?)
+    };
+    // the pupil world camera frame rate is 60fps. recordings are paced by their own frame rate.
+    let server = FakePupilServer::start(&endpoint, frames, gaze, 60.)?;
+    println!("fake pupil capture is running at {}", server.endpoint());
+    loop {
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// Print the recording devices with their `--mic-index`
+pub(crate) fn run_list_mics() -> Result<()> {
+    for (i, device) in list_audio_devices()?.iter().enumerate() {
+        println!("{}: {}", i, device);
+    }
+    Ok(())
+}
+
+/// Print the cameras and the paths for `--camera-device`
+pub(crate) fn run_list_cameras() -> Result<()> {
+    let cameras = list_cameras()?;
+    if cameras.is_empty() {
+        return Err(anyhow!("no camera found"));
+    }
+    for camera in cameras {
+        println!("{}: {} {}", camera.index, camera.path, camera.name);
+        for path in camera.stable_paths {
+            println!("    {}", path);
+        }
+    }
+    Ok(())
+}
+
+/// Print the speech synthesizer voices of `--language`
+pub(crate) fn run_list_voices(config: Config) -> Result<()> {
+    let voices = list_voices(&config.language)?;
+    if voices.is_empty() {
+        return Err(anyhow!("no voice found for {}", config.language.to_str()));
+    }
+    for voice in voices {
+        println!("{} ({}, {:?})", voice.name(), voice.id(), voice.gender());
+    }
+    Ok(())
+}
+
+/// Print the recipes in `--recipe-path`
+pub(crate) fn run_list_recipes(config: Config) -> Result<()> {
+    let catalog = RecipeCatalog::load(&config.recipe_path)?;
+    for recipe in catalog.recipes.iter() {
+        let vision_steps = recipe.steps.iter().filter(|step| step.requires_vision()).count();
+        println!("{}: {} ({} steps, {} with vision) aliases: {:?}",
+                 recipe.id, recipe.name.get(&config.language), recipe.steps.len(), vision_steps, recipe.aliases);
+    }
+    Ok(())
+}
+
+/// Load and validate a recipe file or directory
+pub(crate) fn run_validate_recipe(config: Config, path: Option<String>) -> Result<()> {
+    let path = path.unwrap_or(config.recipe_path.clone());
+    let catalog = RecipeCatalog::load(&path)?;
+    for recipe in catalog.recipes.iter() {
+        println!("ok: {} ({} steps)", recipe.id, recipe.steps.len());
+    }
+    Ok(())
+}
+
+/// Replay a recorded session or a transcript against the context actor and report differing turns
+pub(crate) fn run_replay(config: Config, path: &str) -> Result<()> {
+    let transcript = if path.ends_with(".jsonl") {
+        DialogueTranscript::from_session(path, config.vision, &config.language)?
+    } else {
+        DialogueTranscript::load(path)?
+    };
+    let mut replay = DialogueReplay::from_transcript(&transcript, RecipeCatalog::load(&config.recipe_path)?);
+    let mut mismatches = 0;
+    for (i, turn) in transcript.turns.iter().enumerate() {
+        let actual = replay.feed(&turn.input);
+        if actual == turn.expect {
+            println!("{}: {:?} -> {:?}", i, turn.input, actual);
+        } else {
+            mismatches += 1;
+            println!("{}: {:?} differs\n  expected: {:?}\n  actual:   {:?}", i, turn.input, turn.expect, actual);
+        }
+    }
+    if mismatches > 0 {
+        return Err(anyhow!("{} of {} turns differ", mismatches, transcript.turns.len()));
+    }
+    println!("{} turns replayed", transcript.turns.len());
+    Ok(())
+}
+
+/// Write printable aruco markers
+pub(crate) fn run_generate_markers(config: Config, amount: u32, output_dir: &str) -> Result<()> {
+    for path in marker_util::generate_aruco(config.aruco_dictionary, amount, 200, output_dir)? {
+        println!("{}", path.display());
+    }
+    Ok(())
+}
+
+/// Collect `views` views of the calibration board from the camera of the config, calibrate it
+/// and write the calibration to `--calibration-dir`. The board should be held at different angles and distances.
+pub(crate) fn run_calibrate_camera(config: Config, views: u32) -> Result<()> {
+    let key = calibration_key(&config).ok_or(anyhow!("--vision-type is required"))?;
+    let mut capture = Capture::new();
+    match config.vision_type {
+        VisionType::None => {}
+        VisionType::Pupil => vision_util::set_pupil_capture(&mut capture, config.zmq_in_endpoint.clone())?,
+        VisionType::BuiltInCamera => vision_util::set_camera_capture(&mut capture, &CameraSettings::from_config(&config))?,
+        VisionType::Recording => vision_util::set_recording_capture(&mut capture, &config.recording_path, config.recording_fps, false)?,
+    }
+    let source = capture.source.as_mut().ok_or(anyhow!("no capture source"))?;
+    let spec = BoardSpec::from_config(&config);
+    println!("show the {}x{} {} to the camera from different angles and distances.", spec.columns, spec.rows, spec.board);
+    let mut board_views = vec![];
+    let mut image_size = (0, 0);
+    let mut last_view: Option<Instant> = None;
+    while board_views.len() < views as usize {
+        let frame = match source.get_frame() {
+            Ok(frame) => frame,
+            Err(e) => {
+                println!("stopped capturing: {}", e);
+                break;
+            }
+        };
+        // a view per second so that the board moves between the views
+        if last_view.is_some_and(|last| last.elapsed() < CALIBRATION_VIEW_INTERVAL) {
+            continue;
+        }
+        let header = FrameHeader::new(0, 0, source.get_pixel_format(), source.get_vision_type(), 0, None);
+        let frame = SharedFrame::from_mat(header, &frame)?;
+        if let Some(view) = spec.find(&frame.view_bgr()?)? {
+            image_size = (frame.header().width, frame.header().height);
+            board_views.push(view);
+            last_view = Some(Instant::now());
+            println!("view {}/{}", board_views.len(), views);
+        }
+    }
+    let calibration = CameraCalibration::calibrate(&key, image_size, &board_views)?;
+    let path = calibration_path(&config.calibration_dir, &key);
+    calibration.save(&path)?;
+    println!("reprojection error {:.3}px from {} views. written to {}", calibration.rms_error, calibration.views, path.display());
+    Ok(())
+}
+
+/// Check devices, models and endpoints of the config. Fails if any check fails.
+pub(crate) fn run_doctor(config: Config) -> Result<()> {
+    let report = DoctorReport::run(&config);
+    println!("{}", report);
+    if !report.is_ok() {
+        return Err(anyhow!("doctor found problems"));
+    }
+    Ok(())
 }
@@ -0,0 +1,22 @@
+#[cfg(test)]
+mod core_tests {
+    use crate::utils::config_util::Config;
+    use super::super::core::*;
+
+    fn config() -> Config {
+        let mut config = Config::new();
+        config.recipe_path = format!("{}/resources/recipes", env!("CARGO_MANIFEST_DIR"));
+        config
+    }
+
+    #[test]
+    fn validate_recipe_test() {
+        run_validate_recipe(config(), None).unwrap();
+        assert!(run_validate_recipe(config(), Some("/nonexistent/recipe.json".to_string())).is_err());
+    }
+
+    #[test]
+    fn replay_transcript_test() {
+        run_replay(config(), &format!("{}/resources/transcripts/potato_salad_cancel.json", env!("CARGO_MANIFEST_DIR"))).unwrap();
+    }
+}
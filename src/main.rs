@@ -19,25 +19,110 @@ mod query;
         Ok(command) => {
             match command {
                 Command::Run => {
-                    core::run_smart_speaker(cli.parse_config()?);
+                    core::run_smart_speaker(cli.parse_config()?)?;
+                }
+                Command::FakePupil => {
+                    core::run_fake_pupil(cli.parse_config()?)?;
+                }
+                Command::ListMics => {
+                    core::run_list_mics()?;
+                }
+                Command::ListCameras => {
+                    core::run_list_cameras()?;
+                }
+                Command::ListVoices => {
+                    core::run_list_voices(cli.parse_config()?)?;
+                }
+                Command::ListRecipes => {
+                    core::run_list_recipes(cli.parse_config()?)?;
+                }
+                Command::ValidateRecipe(path) => {
+                    core::run_validate_recipe(cli.parse_config()?, path)?;
+                }
+                Command::Replay(path) => {
+                    core::run_replay(cli.parse_config()?, &path)?;
+                }
+                Command::GenerateMarkers(amount, output_dir) => {
+                    core::run_generate_markers(cli.parse_config()?, amount, &output_dir)?;
+                }
+                Command::CalibrateCamera(views) => {
+                    core::run_calibrate_camera(cli.parse_config()?, views)?;
+                }
+                Command::Doctor => {
+                    core::run_doctor(cli.parse_config()?)?;
                 }
                 Command::Help => {
                     println!("available commands:");
                     println!("run: run smart speaker");
+                    println!("fake-pupil: run a fake pupil capture server at --zmq-in-endpoint. frames from --recording-path and gaze from --gaze-recording-path if given, otherwise synthetic.");
+                    println!("list-mics: list recording devices and their --mic-index.");
+                    println!("list-cameras: list video devices (linux), their index and stable /dev/v4l/by-id paths for --camera-device.");
+                    println!("list-voices: list speech synthesizer voices of --language.");
+                    println!("list-recipes: list recipes in --recipe-path.");
+                    println!("validate-recipe [path]: check a recipe file or directory. default: --recipe-path");
+                    println!("replay <file>: replay a --record-session file (.jsonl) or a transcript (.json) and report differing turns. uses --recipe-path, --language and --vision.");
+                    println!("generate-markers [amount] [dir]: write aruco marker images of --aruco-dictionary. default: 10 markers to ./markers");
+                    println!("calibrate-camera [views]: calibrate the camera of --vision-type with --calibration-board and write it to --calibration-dir. default: 20 views");
+                    println!("doctor: check mic, camera, pupil remote, tts voice, model files and log directory for the given options. exits non-zero on failure.");
                     println!("help: show this help");
                     println!("available options:");
+                    println!("--config: toml or json file of options. keys are the option names without --, e.g. vad-engine = \"energy\".");
+                    println!("          precedence: default < config file < VAS_* environment variables (VAS_VAD_ENGINE) < command line.");
                     println!("--pv-api-key: pico voice api key");
-                    println!("--pv-model-path: pico voice rhn model path. relative path from executable file.");
+                    println!("--pv-ppn-model-path: pico voice ppn (wake word) model path. default: model.ppn");
+                    println!("--pv-rhn-model-path: pico voice rhn (intent) model path. default: model.rhn");
+                    println!("--pv-porcupine-params-path: porcupine parameter file. default: bundled file of --language");
+                    println!("--pv-rhino-params-path: rhino parameter file. default: bundled file of --language");
+                    println!("--speech-engine: wake word / intent engine. picovoice");
+                    println!("--vad-engine: voice activity engine. picovoice / energy (offline, no api key)");
+                    println!("--vad-threshold: voice probability to start listening. default: 0.5");
+                    println!("--vad-energy-margin-db: energy above the noise floor to count as voice. default: 12");
+                    println!("--vad-hangover-ms: keep speech after the voice stops. default: 300");
+                    println!("--vad-min-speech-ms: voice needed to start speech. default: 100");
                     println!("--mic-index: mic index. 0 / 1 / 2 / ...");
-                    println!("--vision-type: vision type. none / pupil / camera");
+                    println!("--audio-file: use 16kHz wav file instead of mic. the run shuts down a few seconds after the end of the file.");
+                    println!("--audio-file-fast: stream --audio-file as fast as possible instead of real time.");
+                    println!("--text-input: type commands (cook <menu> / next / repeat / cancel / quit) instead of speaking. no mic or pico voice key needed.");
+                    println!("--speech-output: where the machine speech goes. tts / console / transcript");
+                    println!("--speech-output-path: directory for --speech-output transcript. each utterance is written as a text file. no audio is written.");
+                    println!("--speech-output-fast: finish console / transcript speech right away instead of after its speaking time.");
+                    println!("--camera-index: camera device index for --vision-type camera. default: 0");
+                    println!("--camera-device: camera index or device path, e.g. /dev/v4l/by-id/usb-...-video-index0. see list-cameras. default: 0");
+                    println!("--camera-backend: opencv capture backend. any / v4l2 / gstreamer / ffmpeg / avfoundation / dshow / msmf. default: any");
+                    println!("--camera-width: requested frame width. default: driver default");
+                    println!("--camera-height: requested frame height. default: driver default");
+                    println!("--camera-fps: requested frame rate. the camera is read at the frame rate it reports. default: driver default");
+                    println!("--camera-fourcc: requested pixel format, e.g. MJPG / YUYV. default: driver default");
+                    println!("--calibration-dir: camera calibrations of calibrate-camera, one file per device. vision uses the file of the camera if it exists. default: calibration");
+                    println!("--calibration-board: calibration pattern. chessboard / charuco (--aruco-dictionary). default: chessboard");
+                    println!("--calibration-board-size: inner corners of a chessboard, squares of a charuco board. default: 9x6");
+                    println!("--calibration-square-mm: side of a board square. default: 25");
+                    println!("--calibration-marker-mm: side of a charuco marker. default: 18");
+                    println!("--aruco-dictionary: aruco dictionary of the markers. 4x4_50 / 4x4_100 / 5x5_50 / ... / 7x7_1000. default: 4x4_50");
+                    println!("--aruco-marker-mm: edge length of the printed markers. default: 38");
+                    println!("--aruco-board: json board file: dictionary, marker_mm and markers [{{id, size_mm, position_mm: [x, y], tag: ingredient or tool}}]. see resources/aruco_board.example.json");
+                    println!("--vision-type: vision type. none / pupil / camera / recording");
+                    println!("--recording-path: video file or image directory to replay. required for --vision-type recording.");
+                    println!("--recording-fps: replay frame rate. default: frame rate of the video, 30 for image directory.");
+                    println!("--recording-loop: replay the recording from the beginning after the end.");
+                    println!("--gaze-recording-path: gaze csv to replay with fake-pupil. (pupil player gaze_positions.csv)");
+                    println!("--gaze-max-skew-ms: largest time between a frame and the gaze used for it. default: 250");
+                    println!("--gaze-min-confidence: drop pupil gaze below this confidence. default: 0.8");
+                    println!("--gaze-smoothing: gaze smoothing. none / ema / one-euro. default: none");
+                    println!("--gaze-fixation: fixation detection. idt (dispersion) / ivt (velocity). default: idt");
+                    println!("--gaze-fixation-dispersion: idt width + height of a fixation in normalized units. default: 0.05");
+                    println!("--gaze-fixation-velocity: ivt gaze speed of a fixation in normalized units per second. default: 0.5");
+                    println!("--gaze-fixation-min-ms: shortest fixation. default: 100");
                     println!("--debug: debug mode. true / false");
                     println!("--vision: vision mode. true / false. if this option is enabled, --vision-type option is required.");
-                    println!("--stream-endpoint: stream endpoint");
+                    println!("--recipe-path: recipe directory or a single recipe json file. default: resources/recipes");
+                    println!("--record-session: write every message to the given json lines file.");
+                    println!("--record-media: how --record-session writes frames and audio. elide (size only) / reference (sidecar raw files). default: elide");
                 }
             }
         }
-        _ => {
-            println!("no matched query found. type help for available commands.");
+        Err(e) => {
+            println!("{}", e);
         }
     }
     Ok(())
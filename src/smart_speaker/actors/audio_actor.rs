@@ -1,9 +1,9 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::models::mic_model::AudioListener;
 use crate::smart_speaker::controllers::mic_controller;
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::audio_util::FRAME_DURATION;
+use crate::utils::camera_util::is_end_of_stream;
 use crate::utils::message_util::*;
 
 pub(crate) struct AudioActor {
@@ -11,7 +11,11 @@ pub(crate) struct AudioActor {
     core: AudioListener,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
     stream: Vec<i16>,
+    /// The wav file was played to the end. Nothing is read anymore.
+    ended: bool,
 }
 
 impl AudioActor {
@@ -21,7 +25,10 @@ impl AudioActor {
             core,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::AudioActor),
+            ticker: Ticker::new(FRAME_DURATION),
             stream: vec![],
+            ended: false,
         }
     }
 
@@ -30,27 +37,39 @@ impl AudioActor {
         write_log_message(&self.sender, SmartSpeakerActors::AudioActor, SmartSpeakerLogMessageType::Info(self.core.info()));
         let _ = self.core.start();
         while self.alive {
-            match mic_controller::listen_mic(&mut self.core) {
-                Ok(stream) => {
-                    self.stream = stream;
-                    let mut pending = true;
-                    while pending {
-                        if let Ok(message) = self.receiver.try_recv() {
-                            self.handle_message(message);
-                        } else {
-                            pending = false;
-                        }
+            self.heartbeat.beat(&self.sender);
+            if !self.ended && self.ticker.tick() {
+                // the mic blocks until the frame is recorded. a wav file that is not realtime is paced by the ticker.
+                match mic_controller::listen_mic(&mut self.core) {
+                    Ok(stream) => {
+                        self.stream = stream;
+                    },
+                    Err(e) if is_end_of_stream(&e) => {
+                        self.end_of_stream();
+                    },
+                    _ => {
+                        dbg!("failed to read mic");
                     }
-                },
-                _ => {
-                    dbg!("failed to read mic");
                 }
             }
-            thread::sleep(Duration::from_millis(33));
+            let deadline = if self.ended { self.heartbeat.deadline() } else { self.ticker.deadline() };
+            if let Some(message) = receive_until(&self.receiver, deadline) {
+                self.handle_message(message);
+            }
         }
         let _ = self.core.stop();
     }
 
+    /// A run driven by a wav file is over when the file is. The core actor is asked to shut everything down.
+    fn end_of_stream(&mut self) {
+        self.ended = true;
+        self.stream = vec![];
+        write_log_message(&self.sender, SmartSpeakerActors::AudioActor, SmartSpeakerLogMessageType::Info("audio file ended. shutting down".to_string()));
+        if let Err(e) = self.sender.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})) {
+            println!("Error: {}", e);
+        }
+    }
+
     fn handle_message(&mut self, message: SmartSpeakerMessage) {
         match message {
             SmartSpeakerMessage::RequestShutdown(ShutdownMessage {}) => {
@@ -2,8 +2,9 @@
 mod audio_actor_tests {
     use std::sync::mpsc;
     use std::thread;
-    use crate::smart_speaker::models::mic_model::AudioListener;
+    use crate::smart_speaker::models::mic_model::{AudioListener, AUDIO_FILE_TAIL};
     use crate::smart_speaker::models::message_model::*;
+    use crate::utils::audio_util::{write_wav, FRAME_LENGTH, SAMPLE_RATE};
     use super::super::audio_actor::*;
 
     #[test]
@@ -36,4 +37,57 @@ mod audio_actor_tests {
         actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
         assert!(core_rx.try_recv().is_err());
     }
+
+    #[test]
+    fn audio_actor_wav_file_test() {
+        let path = std::env::temp_dir().join("vas_audio_actor_test.wav");
+        let path = path.to_str().unwrap();
+        write_wav(path, SAMPLE_RATE, &vec![100i16; FRAME_LENGTH * 4]).unwrap();
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let mut audio_actor = AudioActor::new(AudioListener::from_wav_file(path, false).unwrap(), actor_rx, core_tx.clone());
+        thread::spawn(move || {
+            audio_actor.run();
+        });
+        actor_tx.send(SmartSpeakerMessage::RequestAudioStream(AudioStreamMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::AudioActor,
+            stream: vec![],
+        })).expect("TODO: panic message");
+        loop {
+            match core_rx.recv_timeout(std::time::Duration::from_secs(1)).expect("no audio stream") {
+                SmartSpeakerMessage::RequestAudioStream(AudioStreamMessage { send_from, send_to: _, stream }) => {
+                    assert_eq!(send_from, SmartSpeakerActors::AudioActor);
+                    assert_eq!(stream.len(), FRAME_LENGTH);
+                    break;
+                },
+                _ => {}
+            }
+        }
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn audio_actor_wav_file_end_test() {
+        let path = std::env::temp_dir().join("vas_audio_actor_end_test.wav");
+        let path = path.to_str().unwrap();
+        write_wav(path, SAMPLE_RATE, &vec![100i16; FRAME_LENGTH * 4]).unwrap();
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let mut audio_actor = AudioActor::new(AudioListener::from_wav_file(path, false).unwrap(), actor_rx, core_tx.clone());
+        thread::spawn(move || {
+            audio_actor.run();
+        });
+        // the file and its silent tail are paced by the frame ticker
+        let timeout = AUDIO_FILE_TAIL + std::time::Duration::from_secs(5);
+        loop {
+            match core_rx.recv_timeout(timeout).expect("no shutdown request") {
+                SmartSpeakerMessage::RequestShutdown(_) => break,
+                _ => {}
+            }
+        }
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
+        let _ = std::fs::remove_file(path);
+    }
 }
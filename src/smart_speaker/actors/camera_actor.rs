@@ -1,32 +1,55 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use opencv::prelude::*;
 use opencv::core::Mat;
 use crate::smart_speaker::controllers::camera_controller;
+use crate::smart_speaker::models::core_model::HEARTBEAT_INTERVAL;
+use crate::smart_speaker::models::frame_model::{FrameHeader, SharedFrame};
 use crate::smart_speaker::models::vision_model::Capture;
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::camera_util::is_end_of_stream;
 use crate::utils::message_util::*;
 use crate::utils::vision_util::VisionType;
 
+const PUPIL_CAPTURE_INTERVAL: Duration = Duration::from_millis(14);
+/// A failed capture is retried after this long, so a source that fails at once is not polled in a busy loop.
+const CAPTURE_RETRY_INTERVAL: Duration = HEARTBEAT_INTERVAL;
+
 pub(crate) struct CameraActor {
     alive: bool,
     core: Capture,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
-    frame_data_bytes: Vec<u8>,
-    height: i32,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
+    frame: Option<SharedFrame>,
+    frame_count: u64,
+    /// Set after a failed capture. The source is not read again before it.
+    retry_at: Option<Instant>,
+    /// The recording played to the end. The last frame is still served.
+    ended: bool,
 }
 
 impl CameraActor {
     pub(crate) fn new(core: Capture, receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>) -> Self {
+        let capture_interval = match core.get_source_type() {
+            // The pupil world camera frame rate is 60fps.
+            Some(VisionType::Pupil) => PUPIL_CAPTURE_INTERVAL,
+            // cameras are read at their frame rate. recordings pace themselves.
+            Some(_) => core.get_frame_interval().unwrap_or(Duration::ZERO),
+            None => HEARTBEAT_INTERVAL,
+        };
         Self {
             alive: true,
             core,
             receiver,
             sender,
-            frame_data_bytes: vec![],
-            height: 0,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::CameraActor),
+            ticker: Ticker::new(capture_interval),
+            frame: None,
+            frame_count: 0,
+            retry_at: None,
+            ended: false,
         }
     }
 
@@ -34,34 +57,72 @@ impl CameraActor {
         write_log_message(&self.sender, SmartSpeakerActors::CameraActor, SmartSpeakerLogMessageType::Info("CameraActor started".to_string()));
         write_log_message(&self.sender, SmartSpeakerActors::CameraActor, SmartSpeakerLogMessageType::Info(self.core.info()));
         while self.alive {
-            match &mut self.core.source {
-                None => {}
-                Some(source) => {
-                    match camera_controller::capture_frame(source) {
-                        Ok(frame) => {
-                            self.handle_frame(frame);
-                            if let Ok(message) = self.receiver.try_recv() {
-                                self.handle_message(message);
-                            }
-                        },
-                        _ => {}
-                    }
-                }
+            self.heartbeat.beat(&self.sender);
+            if self.can_capture() && self.ticker.tick() {
+                self.capture();
+            }
+            if let Some(message) = receive_until(&self.receiver, self.deadline()) {
+                self.handle_message(message);
             }
-            if let Some(vision_type) = self.core.get_source_type() {
-                if vision_type == VisionType::Pupil {
-                    // The pupil world camera frame rate is 60fps.
-                    thread::sleep(Duration::from_millis(14));
+        }
+    }
+
+    fn can_capture(&self) -> bool {
+        !self.ended && self.retry_at.map_or(true, |at| Instant::now() >= at)
+    }
+
+    /// Next capture, or the next heartbeat while the capture is paused.
+    fn deadline(&self) -> Instant {
+        let heartbeat = self.heartbeat.deadline();
+        if self.ended {
+            return heartbeat;
+        }
+        match self.retry_at {
+            Some(at) => at.max(self.ticker.deadline()).min(heartbeat),
+            None => self.ticker.deadline().min(heartbeat),
+        }
+    }
+
+    fn capture(&mut self) {
+        let source = match &mut self.core.source {
+            Some(source) => source,
+            None => return,
+        };
+        match camera_controller::capture_frame(source) {
+            Ok(frame) => {
+                self.retry_at = None;
+                self.frame_count += 1;
+                let header = FrameHeader::new(frame.cols(), frame.rows(),
+                                              source.get_pixel_format(),
+                                              source.get_vision_type(),
+                                              self.frame_count,
+                                              source.get_source_timestamp());
+                self.handle_frame(header, frame);
+            }
+            Err(e) if is_end_of_stream(&e) => {
+                self.ended = true;
+                write_log_message(&self.sender, SmartSpeakerActors::CameraActor, SmartSpeakerLogMessageType::Info(
+                    format!("recording ended after {} frames", self.frame_count)));
+            }
+            Err(e) => {
+                if self.retry_at.is_none() {
+                    write_log_message(&self.sender, SmartSpeakerActors::CameraActor, SmartSpeakerLogMessageType::Warn(format!("capture failed: {}", e)));
                 }
-            } else {
-                thread::sleep(Duration::from_millis(33));
+                self.retry_at = Some(Instant::now() + CAPTURE_RETRY_INTERVAL);
             }
         }
     }
 
-    fn handle_frame(&mut self, frame: Mat) {
-        self.frame_data_bytes = frame.data_bytes().unwrap().to_vec();
-        self.height = frame.size().unwrap().height;
+    fn handle_frame(&mut self, header: FrameHeader, frame: Mat) {
+        // the previous frame is freed as soon as no consumer holds it any more.
+        match SharedFrame::from_mat(header, &frame) {
+            Ok(frame) => {
+                self.frame = Some(frame);
+            }
+            Err(e) => {
+                write_log_message(&self.sender, SmartSpeakerActors::CameraActor, SmartSpeakerLogMessageType::Error(format!("dropped frame: {}", e)));
+            }
+        }
     }
 
     fn handle_message(&mut self, message: SmartSpeakerMessage) {
@@ -69,17 +130,12 @@ impl CameraActor {
             SmartSpeakerMessage::RequestShutdown(_) => {
                 self.alive = false;
             },
-            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
-                                                        send_from,
-                                                        send_to: _,
-                                                        frame_data_bytes: _,
-                                                        height: _, }) => {
+            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to: _, frame: _ }) => {
                 camera_frame_message(
                     &self.sender,
                     SmartSpeakerActors::CameraActor,
                     send_from,
-                    self.frame_data_bytes.clone(),
-                    self.height.clone());
+                    self.frame.clone());
             },
             _ => {}
         }
@@ -1,20 +1,26 @@
 #[cfg(test)]
 mod camera_actor_tests {
-    use std::sync::mpsc;
+    use std::sync::{mpsc, Arc};
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::thread;
-    use opencv::prelude::*;
+    use anyhow::{anyhow, Result};
     use opencv::core::Mat;
+    use opencv::prelude::*;
     use crate::smart_speaker::actors::camera_actor::CameraActor;
-    use crate::smart_speaker::models::vision_model::Capture;
+    use crate::smart_speaker::models::frame_model::PixelFormat;
+    use crate::smart_speaker::models::vision_model::{Capture, CaptureSource};
     use crate::smart_speaker::models::message_model::*;
-    use crate::utils::vision_util::set_camera_capture;
+    use crate::utils::camera_util::{CameraSettings, EndOfStream};
+    use crate::utils::config_util::Config;
+    use crate::utils::fake_pupil_util::*;
+    use crate::utils::vision_util::{set_camera_capture, set_pupil_capture, VisionType};
 
     #[test]
     fn camera_actor_test() {
         let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
         let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
         let mut capture_source = Capture::new();
-        set_camera_capture(&mut capture_source).expect("TODO: panic message");
+        set_camera_capture(&mut capture_source, &CameraSettings::from_config(&Config::new())).expect("TODO: panic message");
         let mut camera_actor = CameraActor::new(capture_source, actor_rx, core_tx.clone());
         thread::spawn(move || {
             camera_actor.run();
@@ -23,18 +29,16 @@ mod camera_actor_tests {
         actor_tx.send(SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
             send_from: SmartSpeakerActors::CoreActor,
             send_to: SmartSpeakerActors::CameraActor,
-            frame_data_bytes: vec![],
-            height: 0,
+            frame: None,
         })).expect("TODO: panic message");
         thread::sleep(std::time::Duration::from_millis(33));
         let message = core_rx.try_recv().expect("TODO: panic message");
         match message {
-            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to,
-                                                        frame_data_bytes, height }) => {
+            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to, frame }) => {
                 assert_eq!(send_from, SmartSpeakerActors::CameraActor);
                 assert_eq!(send_to, SmartSpeakerActors::CoreActor);
-                let mat = Mat::from_slice(&frame_data_bytes).unwrap();
-                let mat = mat.reshape(3, height).unwrap();
+                let frame = frame.unwrap();
+                let mat = frame.view().unwrap();
                 assert_eq!(mat.size().unwrap().width >= 640, true);
                 assert_eq!(mat.size().unwrap().height >= 480, true);
             },
@@ -46,4 +50,98 @@ mod camera_actor_tests {
         actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
         assert!(core_rx.try_recv().is_err());
     }
+
+    #[test]
+    fn camera_actor_fake_pupil_test() {
+        let server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Synthetic,
+            60.).unwrap();
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let mut capture_source = Capture::new();
+        set_pupil_capture(&mut capture_source, server.endpoint()).expect("TODO: panic message");
+        let mut camera_actor = CameraActor::new(capture_source, actor_rx, core_tx.clone());
+        thread::spawn(move || {
+            camera_actor.run();
+        });
+        actor_tx.send(SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::CameraActor,
+            frame: None,
+        })).expect("TODO: panic message");
+        loop {
+            match core_rx.recv_timeout(std::time::Duration::from_secs(2)).expect("no camera frame") {
+                SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to: _, frame }) => {
+                    assert_eq!(send_from, SmartSpeakerActors::CameraActor);
+                    let frame = frame.unwrap();
+                    let header = frame.header();
+                    assert_eq!((header.width, header.height, header.channels), (320, 240, 3));
+                    assert_eq!(header.format, PixelFormat::Bgr);
+                    assert_eq!(header.source, VisionType::Pupil);
+                    assert!(header.sequence >= 1);
+                    assert!(header.source_timestamp.is_some());
+                    assert_eq!(frame.data().len(), 320 * 240 * 3);
+                    break;
+                },
+                _ => {}
+            }
+        }
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
+    }
+
+    /// Recording without a frame interval that fails every read and counts the reads.
+    struct FailingSource {
+        reads: Arc<AtomicUsize>,
+        end_of_stream: bool,
+    }
+
+    impl CaptureSource for FailingSource {
+        fn get_vision_type(&self) -> VisionType {
+            VisionType::Recording
+        }
+        fn get_frame(&mut self) -> Result<Mat> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            match self.end_of_stream {
+                true => Err(EndOfStream.into()),
+                false => Err(anyhow!("failed to read image")),
+            }
+        }
+    }
+
+    #[test]
+    fn camera_actor_blocks_after_failed_capture_test() {
+        for end_of_stream in [true, false] {
+            let reads = Arc::new(AtomicUsize::new(0));
+            let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+            let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+            let mut capture_source = Capture::new();
+            capture_source.update_source(Box::new(FailingSource { reads: reads.clone(), end_of_stream }));
+            let mut camera_actor = CameraActor::new(capture_source, actor_rx, core_tx);
+            let handle = thread::spawn(move || {
+                camera_actor.run();
+            });
+            thread::sleep(std::time::Duration::from_millis(300));
+            // the source is not polled again, neither at the end of the recording nor within the retry interval
+            assert_eq!(reads.load(Ordering::SeqCst), 1);
+            // the actor still answers while it does not capture
+            actor_tx.send(SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
+                send_from: SmartSpeakerActors::CoreActor,
+                send_to: SmartSpeakerActors::CameraActor,
+                frame: None,
+            })).unwrap();
+            loop {
+                match core_rx.recv_timeout(std::time::Duration::from_secs(2)).expect("no camera frame") {
+                    SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from: _, send_to: _, frame }) => {
+                        assert!(frame.is_none());
+                        break;
+                    },
+                    _ => {}
+                }
+            }
+            actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).unwrap();
+            handle.join().unwrap();
+        }
+    }
 }
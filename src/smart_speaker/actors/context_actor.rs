@@ -1,51 +1,55 @@
 use std::ptr::write;
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::models::core_model::{WaitingInteraction, SmartSpeakerState};
 use crate::smart_speaker::models::intent_model::IntentAction;
 use crate::smart_speaker::models::speak_model::MachineSpeechBoilerplate;
 use crate::smart_speaker::models::task_model::{SmartSpeakerTaskResult, Task, cooking_task::CookingTask, vision_viewing_task::VisionViewingTask, SmartSpeakerTaskResultCode};
 use crate::smart_speaker::models::message_model::*;
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+use crate::smart_speaker::models::vision_model::VisionAction;
 use crate::utils::message_util::*;
 
+/// Unsure vision results in a row answered with a clarification. The next one fails the vision request.
+const MAX_VISION_UNSURE: usize = 2;
+
 pub(crate) struct ContextActor {
     alive: bool,
     vision: bool,
+    recipes: RecipeCatalog,
     current_task: Option<Box<dyn Task>>,
     next_state: Option<SmartSpeakerState>,
+    vision_unsure_count: usize,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
 }
 
 impl ContextActor {
-    pub(crate) fn new(receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>, vision: bool) -> Self {
+    pub(crate) fn new(receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>, vision: bool, recipes: RecipeCatalog) -> Self {
         ContextActor {
             alive: true,
             vision,
+            recipes,
             current_task: None,
             next_state: None,
+            vision_unsure_count: 0,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::ContextActor),
         }
     }
 
     pub(crate) fn run(&mut self) {
         write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Info("ContextActor started".to_string()));
         while self.alive {
-            let mut pending = true;
-            while pending {
-                if let Ok(message) = self.receiver.try_recv() {
-                    self.handle_message(message);
-                } else {
-                    pending = false;
-                }
+            self.heartbeat.beat(&self.sender);
+            if let Some(message) = receive_until(&self.receiver, self.heartbeat.deadline()) {
+                self.handle_message(message);
             }
-            thread::sleep(Duration::from_millis(33));
         }
     }
 
-    fn handle_message(&mut self, message: SmartSpeakerMessage) {
+    pub(crate) fn handle_message(&mut self, message: SmartSpeakerMessage) {
         match message {
             SmartSpeakerMessage::RequestShutdown(ShutdownMessage {}) => {
                 self.alive = false;
@@ -59,6 +63,9 @@ impl ContextActor {
             SmartSpeakerMessage::TextToSpeechFinished(StringMessage { send_from: _, send_to: _, message: _ }) => {
                 self.handle_next_state();
             }
+            SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage { send_from: _, send_to: _, actor, cause: _, degraded }) => {
+                self.handle_actor_restarted(actor, degraded);
+            }
             _ => {
                 write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Error("unhandled message".to_string()));
             }
@@ -78,7 +85,16 @@ impl ContextActor {
             }
             IntentAction::CookingTask => {
                 write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Info("start cooking task".to_string()));
-                self.current_task = Some(Box::new(CookingTask::new(content, self.vision).unwrap()))
+                match CookingTask::new(content, self.vision, &self.recipes) {
+                    Ok(task) => {
+                        self.current_task = Some(Box::new(task))
+                    }
+                    Err(e) => {
+                        write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Error(format!("failed to start cooking task: {}", e)));
+                        self.request_text_to_speech_boilerplate(MachineSpeechBoilerplate::IntentFailed as usize);
+                        self.request_state_update(SmartSpeakerState::Idle);
+                    }
+                }
             }
             _ => {
                 self.request_text_to_speech_boilerplate(MachineSpeechBoilerplate::Undefined as usize);
@@ -106,7 +122,7 @@ impl ContextActor {
                     }
                 }
             },
-            ProcessResult::Failure => {
+            ProcessResult::Failure | ProcessResult::Unsure => {
                 write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Debug("intent failed".to_string()));
                 match &mut self.current_task {
                     None => {
@@ -123,6 +139,22 @@ impl ContextActor {
     }
 
     fn handle_vision(&mut self, result: ProcessResult, contents: Vec<VisionContent>) {
+        let result = match result {
+            ProcessResult::Unsure if self.vision_unsure_count >= MAX_VISION_UNSURE => {
+                write_log_message(&self.sender, SmartSpeakerActors::ContextActor, SmartSpeakerLogMessageType::Warn(
+                    format!("still unsure after {} clarifications", self.vision_unsure_count)));
+                self.vision_unsure_count = 0;
+                ProcessResult::Failure
+            }
+            ProcessResult::Unsure => {
+                self.vision_unsure_count += 1;
+                ProcessResult::Unsure
+            }
+            _ => {
+                self.vision_unsure_count = 0;
+                result
+            }
+        };
         match result {
             ProcessResult::Success => {
                 for content in contents {
@@ -142,7 +174,28 @@ impl ContextActor {
                     self.handle_task_result(result);
                 }
             },
+            ProcessResult::Unsure => {
+                // ask for a clarification and look again for the same actions after the speech
+                let actions = contents.into_iter().map(|content| content.action).collect::<Vec<VisionAction>>();
+                self.set_next_state(SmartSpeakerState::WaitingForInteraction(WaitingInteraction::Vision(actions)));
+                self.request_text_to_speech_boilerplate(MachineSpeechBoilerplate::VisionUnsure as usize);
+            },
+        }
+    }
+
+    fn handle_actor_restarted(&mut self, actor: SmartSpeakerActors, degraded: bool) {
+        if !degraded {
+            self.request_text_to_speech_boilerplate(MachineSpeechBoilerplate::ActorRestarted as usize);
+            return;
+        }
+        match actor {
+            SmartSpeakerActors::CameraActor | SmartSpeakerActors::VisionActor | SmartSpeakerActors::GazeActor => {
+                // new tasks are started without vision
+                self.vision = false;
+            }
+            _ => {}
         }
+        self.request_text_to_speech_boilerplate(MachineSpeechBoilerplate::ActorDegraded as usize);
     }
 
     fn handle_task_result(&mut self, result: SmartSpeakerTaskResult) {
@@ -0,0 +1,46 @@
+#[cfg(test)]
+mod context_actor_tests {
+    use std::sync::mpsc;
+    use crate::smart_speaker::models::intent_model::IntentAction;
+    use crate::smart_speaker::models::message_model::*;
+    use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+    use crate::smart_speaker::models::speak_model::MachineSpeechBoilerplate;
+    use super::super::context_actor::*;
+
+    fn spoken_boilerplates(core_rx: &mpsc::Receiver<SmartSpeakerMessage>) -> Vec<usize> {
+        core_rx.try_iter().filter_map(|message| match message {
+            SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage { message: TextToSpeechMessageType::Boilerplate(index), .. }) => Some(index),
+            _ => None,
+        }).collect()
+    }
+
+    fn restarted(actor: SmartSpeakerActors, degraded: bool) -> SmartSpeakerMessage {
+        SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            actor,
+            cause: "panicked: test".to_string(),
+            degraded,
+        })
+    }
+
+    #[test]
+    fn context_actor_restarted_test() {
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (_, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let recipes = RecipeCatalog::load(&format!("{}/resources/recipes", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let mut context_actor = ContextActor::new(actor_rx, core_tx, true, recipes);
+        context_actor.handle_message(restarted(SmartSpeakerActors::AudioActor, false));
+        assert_eq!(spoken_boilerplates(&core_rx), vec![MachineSpeechBoilerplate::ActorRestarted as usize]);
+        context_actor.handle_message(restarted(SmartSpeakerActors::CameraActor, true));
+        assert_eq!(spoken_boilerplates(&core_rx), vec![MachineSpeechBoilerplate::ActorDegraded as usize]);
+        // without the camera, vision tasks are not started any more
+        context_actor.handle_message(SmartSpeakerMessage::IntentFinalized(IntentFinalizedMessage {
+            send_from: SmartSpeakerActors::InputActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            result: ProcessResult::Success,
+            content: IntentContent::new(IntentAction::WhatYouSee, vec![]),
+        }));
+        assert_eq!(spoken_boilerplates(&core_rx), vec![MachineSpeechBoilerplate::Undefined as usize]);
+    }
+}
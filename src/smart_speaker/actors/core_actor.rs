@@ -1,11 +1,12 @@
 use std::collections::HashMap;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use crate::smart_speaker::actors::audio_actor::AudioActor;
 use crate::smart_speaker::actors::camera_actor::CameraActor;
 use crate::smart_speaker::actors::context_actor::ContextActor;
 use crate::smart_speaker::actors::gaze_actor::GazeActor;
+use crate::smart_speaker::actors::input_actor::{spawn_stdin_reader, InputActor};
 use crate::smart_speaker::actors::logger_actor::LoggerActor;
 use crate::smart_speaker::actors::speech_to_intent_actor::SpeechToIntentActor;
 use crate::smart_speaker::actors::machine_speech_actor::MachineSpeechActor;
@@ -13,15 +14,20 @@ use crate::smart_speaker::actors::machine_speech_actor::MachineSpeechActor;
 use crate::smart_speaker::actors::vision_actor::VisionActor;
 use crate::smart_speaker::actors::voice_activity_detect_actor::VoiceActivityDetectActor;
 use crate::smart_speaker::actors::wake_word_actor::WakeWordActor;
-use crate::smart_speaker::models::core_model::{WaitingInteraction, SmartSpeakerState};
+use crate::smart_speaker::models::core_model::{WaitingInteraction, SmartSpeakerState, RestartPolicy, HEARTBEAT_INTERVAL, HEARTBEAT_TIMEOUT, UI_LOOP_INTERVAL};
 use crate::smart_speaker::models::debug_model::DebugData;
-use crate::smart_speaker::models::gaze_model::Gaze;
+use crate::smart_speaker::models::gaze_model::{Gaze, GazeFilter};
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+use crate::smart_speaker::models::session_model::SessionRecorder;
 use crate::smart_speaker::models::mic_model::{AudioListener, SpeechToIntent, VoiceActivityDetector, WakeWordDetector};
 use crate::smart_speaker::models::speak_model::{MachineSpeech, MachineSpeechBoilerplate};
 use crate::smart_speaker::models::vision_model::Capture;
 use crate::utils::config_util::Config;
+use crate::smart_speaker::models::marker_model::MarkerBoard;
+use crate::utils::calibration_util::load_calibration;
+use crate::utils::camera_util::CameraSettings;
 use crate::smart_speaker::models::message_model::*;
-use crate::utils::message_util::write_log_message;
+use crate::utils::message_util::{actor_restarted_message, receive_until, write_log_message};
 use crate::utils::vision_util;
 use crate::utils::vision_util::VisionType;
 #[cfg(target_os = "macos")]
@@ -30,21 +36,142 @@ use cocoa_foundation::base::id;
 use cocoa_foundation::foundation::{NSRunLoop, NSDate, NSDefaultRunLoopMode};
 #[cfg(target_os = "macos")]
 use objc::{msg_send, sel, sel_impl, class};
+#[cfg(target_os = "macos")]
+use crate::smart_speaker::models::speak_model::SpeechOutputType;
+
+/// Thread of a running actor, watched by the supervisor.
+struct SupervisedActor {
+    handle: thread::JoinHandle<()>,
+    /// None until the actor sends its first heartbeat. Actors without heartbeats are only watched for panics.
+    last_heartbeat: Option<Instant>,
+}
 
-struct CoreActorManager {
+pub(crate) struct CoreActorManager {
     alive: bool,
-    senders: HashMap<SmartSpeakerActors, mpsc::Sender<SmartSpeakerMessage>>
+    senders: HashMap<SmartSpeakerActors, mpsc::Sender<SmartSpeakerMessage>>,
+    supervised: HashMap<SmartSpeakerActors, SupervisedActor>,
+    restarts: HashMap<SmartSpeakerActors, u32>,
+    heartbeat_timeout: Duration,
+    recipes: RecipeCatalog,
 }
 
 impl CoreActorManager {
-    fn new() -> Self {
+    /// An actor that sent heartbeats before and is silent for `heartbeat_timeout` is considered hung.
+    pub(crate) fn new(recipes: RecipeCatalog, heartbeat_timeout: Duration) -> Self {
         Self {
             alive: true,
-            senders: HashMap::new()
+            senders: HashMap::new(),
+            supervised: HashMap::new(),
+            restarts: HashMap::new(),
+            heartbeat_timeout,
+            recipes,
+        }
+    }
+
+    pub(crate) fn heartbeat(&mut self, actor: SmartSpeakerActors) {
+        if let Some(supervised) = self.supervised.get_mut(&actor) {
+            supervised.last_heartbeat = Some(Instant::now());
         }
     }
 
-    fn add_sender(&mut self, actor: SmartSpeakerActors, sender: mpsc::Sender<SmartSpeakerMessage>) {
+    /// Watch the thread of `actor` for panics and missing heartbeats.
+    pub(crate) fn watch(&mut self, actor: SmartSpeakerActors, handle: thread::JoinHandle<()>) {
+        self.supervised.insert(actor, SupervisedActor {
+            handle,
+            last_heartbeat: None,
+        });
+    }
+
+    /// Actors that panicked or stopped sending heartbeats, with the cause. Actors that returned normally are forgotten.
+    pub(crate) fn find_failed_actors(&mut self) -> Vec<(SmartSpeakerActors, String)> {
+        let mut failed = vec![];
+        let finished = self.supervised.iter()
+            .filter(|(_, supervised)| supervised.handle.is_finished())
+            .map(|(actor, _)| actor.clone())
+            .collect::<Vec<SmartSpeakerActors>>();
+        for actor in finished {
+            let supervised = self.supervised.remove(&actor).unwrap();
+            if let Err(payload) = supervised.handle.join() {
+                let cause = if let Some(message) = payload.downcast_ref::<&str>() {
+                    message.to_string()
+                } else if let Some(message) = payload.downcast_ref::<String>() {
+                    message.clone()
+                } else {
+                    "unknown panic".to_string()
+                };
+                failed.push((actor, format!("panicked: {}", cause)));
+            }
+        }
+        let hung = self.supervised.iter()
+            .filter(|(_, supervised)| supervised.last_heartbeat.is_some_and(|t| t.elapsed() > self.heartbeat_timeout))
+            .map(|(actor, _)| actor.clone())
+            .collect::<Vec<SmartSpeakerActors>>();
+        for actor in hung {
+            // a thread can not be killed. `supervise` asks it to shut down, but a thread that is stuck for good
+            // is abandoned: its handle is dropped here and it is never joined.
+            self.supervised.remove(&actor);
+            failed.push((actor, format!("no heartbeat for {:?}", self.heartbeat_timeout)));
+        }
+        failed
+    }
+
+    /// Apply the restart policy of every failed actor. The context actor is told about restarts and degraded actors.
+    fn supervise(&mut self, config: &Config, sender: mpsc::Sender<SmartSpeakerMessage>) {
+        self.supervise_with(sender, |manager, actor, sender| manager.spawn_actor(config, actor, sender));
+    }
+
+    /// `supervise` with the actors started by `spawn`.
+    pub(crate) fn supervise_with<F>(&mut self, sender: mpsc::Sender<SmartSpeakerMessage>, mut spawn: F)
+        where F: FnMut(&mut Self, SmartSpeakerActors, mpsc::Sender<SmartSpeakerMessage>) {
+        for (actor, cause) in self.find_failed_actors() {
+            let restarts = self.restarts.get(&actor).cloned().unwrap_or(0);
+            let policy = match RestartPolicy::of(&actor) {
+                RestartPolicy::Restart(max_restarts) if restarts >= max_restarts => RestartPolicy::Degrade,
+                policy => policy,
+            };
+            self.report(format!("{} {}. {:?}", actor, cause, policy));
+            match policy {
+                RestartPolicy::Restart(_) => {
+                    self.stop_actor(actor.clone());
+                    self.restarts.insert(actor.clone(), restarts + 1);
+                    spawn(self, actor.clone(), sender.clone());
+                    self.notify_restarted(actor, cause, false);
+                }
+                RestartPolicy::Degrade => {
+                    self.stop_actor(actor.clone());
+                    self.notify_restarted(actor, cause, true);
+                }
+                RestartPolicy::Escalate => {
+                    if let Err(e) = sender.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})) {
+                        println!("Error: {}", e);
+                    }
+                }
+            }
+        }
+    }
+
+    fn report(&self, message: String) {
+        match self.get_sender(SmartSpeakerActors::LoggerActor) {
+            Some(logger) => write_log_message(logger, SmartSpeakerActors::CoreActor, SmartSpeakerLogMessageType::Error(message)),
+            None => println!("Error: {}", message),
+        }
+    }
+
+    fn notify_restarted(&self, actor: SmartSpeakerActors, cause: String, degraded: bool) {
+        if let Some(context) = self.get_sender(SmartSpeakerActors::ContextActor) {
+            actor_restarted_message(context, SmartSpeakerActors::CoreActor, SmartSpeakerActors::ContextActor, actor, cause, degraded);
+        }
+    }
+
+    /// Ask a failed actor to shut down and close its channel. A hung actor stops once it reads either of them.
+    fn stop_actor(&mut self, actor: SmartSpeakerActors) {
+        if let Some(sender) = self.get_sender(actor.clone()) {
+            let _ = sender.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {}));
+        }
+        self.remove_sender(actor);
+    }
+
+    pub(crate) fn add_sender(&mut self, actor: SmartSpeakerActors, sender: mpsc::Sender<SmartSpeakerMessage>) {
         self.senders.insert(actor, sender);
     }
 
@@ -56,20 +183,35 @@ impl CoreActorManager {
         if let Some(sender) = self.senders.remove(&actor) {
             drop(sender);
         }
+        // a terminated actor stops sending heartbeats
+        if let Some(supervised) = self.supervised.get_mut(&actor) {
+            supervised.last_heartbeat = None;
+        }
     }
 
     fn spawn_actor(&mut self, config: &Config, actor: SmartSpeakerActors, sender: mpsc::Sender<SmartSpeakerMessage>) {
         let (tx, rx) = mpsc::channel();
-        match actor {
+        let handle = match actor {
             SmartSpeakerActors::AudioActor => {
+                let audio_listener = if config.audio_file_path.is_empty() {
+                    AudioListener::new(config.mic_index.clone())
+                } else {
+                    match AudioListener::from_wav_file(&config.audio_file_path, config.audio_file_realtime) {
+                        Ok(audio_listener) => audio_listener,
+                        Err(e) => {
+                            self.report(format!("failed to open audio file {}: {}", config.audio_file_path, e));
+                            return;
+                        }
+                    }
+                };
                 let mut audio_actor = AudioActor::new(
-                    AudioListener::new(config.mic_index.clone()),
+                    audio_listener,
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     audio_actor.run();
-                });
+                }))
             },
             SmartSpeakerActors::CameraActor => {
                 let mut capture_source = Capture::new();
@@ -79,7 +221,13 @@ impl CoreActorManager {
                         vision_util::set_pupil_capture(&mut capture_source, config.zmq_in_endpoint.clone()).expect("TODO: panic message");
                     }
                     VisionType::BuiltInCamera => {
-                        vision_util::set_camera_capture(&mut capture_source).expect("TODO: panic message");
+                        vision_util::set_camera_capture(&mut capture_source, &CameraSettings::from_config(&config)).expect("failed to open camera");
+                    }
+                    VisionType::Recording => {
+                        vision_util::set_recording_capture(&mut capture_source,
+                                                           &config.recording_path,
+                                                           config.recording_fps,
+                                                           config.recording_loop).expect("failed to open recording");
                     }
                 }
                 let mut camera_actor = CameraActor::new(
@@ -87,86 +235,96 @@ impl CoreActorManager {
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     camera_actor.run();
-                });
+                }))
             },
             SmartSpeakerActors::VisionActor => {
                 let mut vision_actor = VisionActor::new(
                     rx,
                     sender.clone(),
-                    config.debug.clone()
+                    config.debug.clone(),
+                    Duration::from_millis(config.gaze_max_skew_ms as u64),
+                    load_calibration(config).expect("failed to load camera calibration"),
+                    MarkerBoard::from_config(config).expect("failed to load aruco board"),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     vision_actor.run();
-                });
+                }))
             },
             SmartSpeakerActors::GazeActor => {
-                let gaze = Gaze::new(config.vision_type.clone(), 0.5, 0.5, config.zmq_in_endpoint.clone()).unwrap();
+                let gaze = Gaze::new(config.vision_type.clone(), 0.5, 0.5, config.zmq_in_endpoint.clone(), GazeFilter::from_config(config)).unwrap();
                 let mut gaze_actor = GazeActor::new(
                     gaze,
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     gaze_actor.run();
-                });
+                }))
             }
             SmartSpeakerActors::WakeWordActor => {
                 let mut wake_word_actor = WakeWordActor::new(
-                    WakeWordDetector::new(config.pico_voice_api_key.clone(),
-                                          config.pico_voice_ppn_model_path.clone(),
-                                          config.language.clone()),
+                    WakeWordDetector::from_config(config).expect("failed to init wake word engine"),
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     wake_word_actor.run();
                     drop(wake_word_actor);
-                });
+                }))
             },
             SmartSpeakerActors::SpeechToIntentActor => {
                 let mut speech_to_intent_actor = SpeechToIntentActor::new(
-                    SpeechToIntent::new(config.pico_voice_api_key.clone(),
-                                        config.pico_voice_rhn_model_path.clone(),
-                                        config.language.clone()),
+                    SpeechToIntent::from_config(config).expect("failed to init speech to intent engine"),
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     speech_to_intent_actor.run();
                     drop(speech_to_intent_actor);
-                });
+                }))
             },
             SmartSpeakerActors::MachineSpeechActor => {
                 let mut machine_speech_actor = MachineSpeechActor::new(
-                    MachineSpeech::new(config.language.clone()),
+                    MachineSpeech::from_config(config).expect("failed to init speech output"),
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     machine_speech_actor.run();
-                });
+                }))
             },
             SmartSpeakerActors::VoiceActivityDetectActor => {
                 let mut voice_activity_detect_actor = VoiceActivityDetectActor::new(
-                    VoiceActivityDetector::new(config.pico_voice_api_key.clone()),
+                    VoiceActivityDetector::from_config(config).expect("failed to init voice activity engine"),
                     rx,
                     sender.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     voice_activity_detect_actor.run();
-                });
+                }))
             }
             SmartSpeakerActors::ContextActor => {
                 let mut context_actor = ContextActor::new(
                     rx,
                     sender.clone(),
-                    config.vision.clone()
+                    config.vision.clone(),
+                    self.recipes.clone(),
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     context_actor.run();
-                });
+                }))
+            }
+            SmartSpeakerActors::InputActor => {
+                spawn_stdin_reader(tx.clone());
+                let mut input_actor = InputActor::new(
+                    rx,
+                    sender.clone(),
+                );
+                Some(thread::spawn(move || {
+                    input_actor.run();
+                }))
             }
             SmartSpeakerActors::LoggerActor => {
                 let mut logger_actor = LoggerActor::new(
@@ -174,9 +332,9 @@ impl CoreActorManager {
                     sender.clone(),
                     config.debug.clone()
                 );
-                thread::spawn(move || {
+                Some(thread::spawn(move || {
                     logger_actor.run();
-                });
+                }))
             }
             // SmartSpeakerActors::StreamActor => {
             //     let mut stream_actor = StreamActor::new(
@@ -187,7 +345,10 @@ impl CoreActorManager {
             //         stream_actor.run();
             //     });
             // }
-            _ => {}
+            _ => None,
+        };
+        if let Some(handle) = handle {
+            self.watch(actor.clone(), handle);
         }
         self.add_sender(actor, tx);
     }
@@ -202,12 +363,17 @@ pub(crate) enum CoreActorState {
         actor: SmartSpeakerActors,
         custom_args: Option<String>,
     },
+    HeartbeatReceived {
+        actor: SmartSpeakerActors,
+    },
     WaitForNextMessage {},
     ShutdownRequested {},
 }
 
 pub(crate) struct CoreActorMessageHandler {
     pub(crate) debug: DebugData,
+    /// Commands come from `InputActor`. Wake word and voice activity detection are never started.
+    pub(crate) text_input: bool,
 }
 
 impl CoreActorMessageHandler {
@@ -253,19 +419,23 @@ impl CoreActorMessageHandler {
                 }
                 CoreActorState::WaitForNextMessage {}
             },
-            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to,
-                                                        frame_data_bytes, height }) => {
+            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to, frame }) => {
                 if self.debug.activated && send_from == &SmartSpeakerActors::CameraActor {
-                    self.debug.update_frame(&frame_data_bytes, height);
+                    if let Some(frame) = frame {
+                        self.debug.update_frame(frame);
+                    }
                 }
                 if let Some(sender) = senders.get(&send_to) {
                     sender.send(message).expect("TODO: panic message");
                 }
                 CoreActorState::WaitForNextMessage {}
             },
-            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from, send_to, gaze_info }) => {
+            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from, send_to, gaze_info, fixation }) => {
                 if self.debug.activated && send_from == &SmartSpeakerActors::GazeActor {
-                    self.debug.update_gaze_info(&gaze_info);
+                    if let Some(sample) = gaze_info {
+                        self.debug.update_gaze_info(&sample.position());
+                    }
+                    self.debug.update_fixation(fixation);
                 }
                 if let Some(sender) = senders.get(&send_to) {
                     sender.send(message).expect("TODO: panic message");
@@ -310,7 +480,7 @@ impl CoreActorMessageHandler {
                     SmartSpeakerActors::ContextActor => {
                         match state {
                             SmartSpeakerState::Idle => {
-                                if senders.get(&SmartSpeakerActors::WakeWordActor).is_none() {
+                                if !self.text_input && senders.get(&SmartSpeakerActors::WakeWordActor).is_none() {
                                     return CoreActorState::NewActorRequested {
                                         actor: SmartSpeakerActors::WakeWordActor,
                                         custom_args: None,
@@ -323,7 +493,7 @@ impl CoreActorMessageHandler {
                                         if self.debug.activated {
                                             self.debug.update_state(state.clone(), SmartSpeakerActors::VoiceActivityDetectActor);
                                         }
-                                        if senders.get(&SmartSpeakerActors::VoiceActivityDetectActor).is_none() {
+                                        if !self.text_input && senders.get(&SmartSpeakerActors::VoiceActivityDetectActor).is_none() {
                                             return CoreActorState::NewActorRequested {
                                                 actor: SmartSpeakerActors::VoiceActivityDetectActor,
                                                 custom_args: None,
@@ -408,6 +578,17 @@ impl CoreActorMessageHandler {
                 }
                 CoreActorState::WaitForNextMessage {}
             }
+            SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage { send_from: _, send_to: _, actor: _, cause: _, degraded: _ }) => {
+                if let Some(sender) = senders.get(&SmartSpeakerActors::ContextActor) {
+                    sender.send(message).expect("TODO: panic message");
+                }
+                CoreActorState::WaitForNextMessage {}
+            },
+            SmartSpeakerMessage::Heartbeat(HeartbeatMessage { send_from }) => {
+                CoreActorState::HeartbeatReceived {
+                    actor: send_from.clone(),
+                }
+            }
             _ => {
                 CoreActorState::WaitForNextMessage {}
             }
@@ -421,6 +602,7 @@ pub(crate) struct CoreActor {
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     manager: CoreActorManager,
     message_handler: CoreActorMessageHandler,
+    recorder: Option<SessionRecorder>,
 }
 
 impl CoreActor {
@@ -429,20 +611,32 @@ impl CoreActor {
             config: config.clone(),
             sender,
             receiver,
-            manager: CoreActorManager::new(),
+            manager: CoreActorManager::new(
+                RecipeCatalog::load(&config.recipe_path).expect("failed to load recipes"),
+                HEARTBEAT_TIMEOUT),
             message_handler: CoreActorMessageHandler {
                 debug: match config.debug {
-                    true => DebugData::new(true),
+                    true => DebugData::new(true).with_marker_board(MarkerBoard::from_config(&config).expect("failed to load aruco board")),
                     false => DebugData::new(false),
-                }
+                },
+                text_input: config.text_input,
+            },
+            recorder: match config.session_record_path.is_empty() {
+                true => None,
+                false => Some(SessionRecorder::new(&config.session_record_path,
+                                                   config.session_record_media.clone()).expect("failed to create session file")),
             },
         }
     }
 
     fn init(&mut self) {
         self.manager.spawn_actor(&self.config, SmartSpeakerActors::LoggerActor, self.sender.clone());
-        self.manager.spawn_actor(&self.config, SmartSpeakerActors::AudioActor, self.sender.clone());
-        self.manager.spawn_actor(&self.config, SmartSpeakerActors::WakeWordActor, self.sender.clone());
+        if self.config.text_input {
+            self.manager.spawn_actor(&self.config, SmartSpeakerActors::InputActor, self.sender.clone());
+        } else {
+            self.manager.spawn_actor(&self.config, SmartSpeakerActors::AudioActor, self.sender.clone());
+            self.manager.spawn_actor(&self.config, SmartSpeakerActors::WakeWordActor, self.sender.clone());
+        }
         self.manager.spawn_actor(&self.config, SmartSpeakerActors::ContextActor, self.sender.clone());
         self.manager.spawn_actor(&self.config, SmartSpeakerActors::MachineSpeechActor, self.sender.clone());
         if self.config.vision_type != VisionType::None {
@@ -460,24 +654,19 @@ impl CoreActor {
         dbg!(&self.config);
         self.init();
         while self.manager.alive {
-            let mut pending = true;
-            while pending {
-                if let Ok(message) = self.receiver.try_recv() {
-                    match self.message_handler.handle_message(&self.manager.senders, message) {
-                        CoreActorState::ActorTerminated { actor } => {
-                            self.manager.remove_sender(actor);
-                        },
-                        CoreActorState::NewActorRequested { actor, custom_args } => {
-                            self.manager.spawn_actor(&self.config, actor, self.sender.clone());
-                        },
-                        CoreActorState::WaitForNextMessage {} => {
-                        },
-                        CoreActorState::ShutdownRequested {} => {
-                            self.manager.alive = false;
-                        }
-                    }
-                } else {
-                    pending = false;
+            let deadline = Instant::now() + self.loop_interval();
+            while let Some(message) = receive_until(&self.receiver, deadline) {
+                self.handle_message(message);
+                if !self.manager.alive || Instant::now() >= deadline {
+                    break;
+                }
+            }
+            if self.manager.alive {
+                self.manager.supervise(&self.config, self.sender.clone());
+            }
+            if let Some(recorder) = self.recorder.as_mut() {
+                if let Err(e) = recorder.flush_if_due() {
+                    self.manager.report(format!("failed to record session: {}", e));
                 }
             }
             if self.config.debug && self.config.vision {
@@ -485,13 +674,61 @@ impl CoreActor {
                 self.message_handler.debug.print();
             } else {
                 #[cfg(target_os = "macos")]
-                {
+                if self.config.speech_output == SpeechOutputType::Tts {
                     // This block for macOS. without this block, the TTS callback will not be called.
                     // but in debug mode, the highgui window will execute this block internally.
                     self.message_handler.debug.indicator_loop();
                 }
             }
-            thread::sleep(Duration::from_micros(1));
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.finish() {
+                println!("Error: {}", e);
+            }
+        }
+    }
+
+    /// Messages wake the core actor up at once. Without them, it wakes up to supervise and to run the UI loop.
+    fn loop_interval(&self) -> Duration {
+        if self.config.debug && self.config.vision {
+            return UI_LOOP_INTERVAL;
+        }
+        #[cfg(target_os = "macos")]
+        if self.config.speech_output == SpeechOutputType::Tts {
+            return UI_LOOP_INTERVAL;
+        }
+        HEARTBEAT_INTERVAL
+    }
+
+    fn handle_message(&mut self, message: SmartSpeakerMessage) {
+        // heartbeats would only bloat the session
+        let recorder = match &message {
+            SmartSpeakerMessage::Heartbeat(_) => None,
+            _ => self.recorder.as_mut(),
+        };
+        if let Some(recorder) = recorder {
+            if let Err(e) = recorder.record(&message) {
+                if let Some(sender) = self.manager.get_sender(SmartSpeakerActors::LoggerActor) {
+                    write_log_message(sender, SmartSpeakerActors::CoreActor,
+                                      SmartSpeakerLogMessageType::Error(format!("failed to record session: {}", e)));
+                }
+            }
+        }
+        match self.message_handler.handle_message(&self.manager.senders, message) {
+            CoreActorState::ActorTerminated { actor } => {
+                self.manager.remove_sender(actor);
+            },
+            CoreActorState::NewActorRequested { actor, custom_args } => {
+                self.manager.spawn_actor(&self.config, actor, self.sender.clone());
+            },
+            CoreActorState::HeartbeatReceived { actor } => {
+                self.manager.heartbeat(actor);
+            },
+            CoreActorState::WaitForNextMessage {} => {
+            },
+            CoreActorState::ShutdownRequested {} => {
+                self.manager.alive = false;
+            }
         }
     }
 }
@@ -2,9 +2,12 @@
 mod core_actor_tests {
     use std::collections::HashMap;
     use std::sync::mpsc;
-    use crate::smart_speaker::models::core_model::SmartSpeakerState;
+    use std::thread;
+    use std::time::Duration;
+    use crate::smart_speaker::models::core_model::{RestartPolicy, SmartSpeakerState};
     use crate::smart_speaker::models::debug_model::DebugData;
     use crate::smart_speaker::models::message_model::*;
+    use crate::smart_speaker::models::recipe_model::RecipeCatalog;
     use super::super::core_actor::*;
 
     #[test]
@@ -14,6 +17,7 @@ mod core_actor_tests {
         senders.insert(SmartSpeakerActors::CoreActor, tx);
         let mut handler = CoreActorMessageHandler {
             debug: DebugData::new(false),
+            text_input: false,
             };
         let message = SmartSpeakerMessage::RequestShutdown(ShutdownMessage {});
         let state = handler.handle_message(&senders, message);
@@ -28,6 +32,7 @@ mod core_actor_tests {
         senders.insert(SmartSpeakerActors::CoreActor, tx);
         let mut handler = CoreActorMessageHandler {
             debug: DebugData::new(false),
+            text_input: false,
         };
         let message = SmartSpeakerMessage::ReportTerminated(ReportTerminated {
             send_from: SmartSpeakerActors::CoreActor, send_to: SmartSpeakerActors::CoreActor});
@@ -42,6 +47,7 @@ mod core_actor_tests {
         senders.insert(SmartSpeakerActors::CoreActor, tx);
         let mut handler = CoreActorMessageHandler {
             debug: DebugData::new(false),
+            text_input: false,
         };
         let message = SmartSpeakerMessage::RequestAudioStream(AudioStreamMessage {
             send_from: SmartSpeakerActors::CoreActor,
@@ -65,6 +71,7 @@ mod core_actor_tests {
         senders.insert(SmartSpeakerActors::CoreActor, tx);
         let mut handler = CoreActorMessageHandler {
             debug: DebugData::new(false),
+            text_input: false,
         };
         let message = SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage {
             send_from: SmartSpeakerActors::CoreActor,
@@ -82,6 +89,7 @@ mod core_actor_tests {
         senders.insert(SmartSpeakerActors::CoreActor, tx);
         let mut handler = CoreActorMessageHandler {
             debug: DebugData::new(false),
+            text_input: false,
         };
         let message = SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage {
             send_from: SmartSpeakerActors::CoreActor,
@@ -91,4 +99,144 @@ mod core_actor_tests {
         let state = handler.handle_message(&senders, message);
         assert_eq!(state, CoreActorState::NewActorRequested { actor: SmartSpeakerActors::WakeWordActor, custom_args: None });
     }
+
+    #[test]
+    fn core_message_handler_text_input_idle_test() {
+        let (tx, _rx) = mpsc::channel();
+        let mut senders = HashMap::new();
+        senders.insert(SmartSpeakerActors::LoggerActor, tx);
+        let mut handler = CoreActorMessageHandler {
+            debug: DebugData::new(false),
+            text_input: true,
+        };
+        let message = SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage {
+            send_from: SmartSpeakerActors::ContextActor,
+            send_to: SmartSpeakerActors::CoreActor,
+            state: SmartSpeakerState::Idle,
+        });
+        let state = handler.handle_message(&senders, message);
+        assert_eq!(state, CoreActorState::WaitForNextMessage {});
+    }
+
+    #[test]
+    fn core_message_handler_heartbeat_test() {
+        let senders = HashMap::new();
+        let mut handler = CoreActorMessageHandler {
+            debug: DebugData::new(false),
+            text_input: false,
+        };
+        let message = SmartSpeakerMessage::Heartbeat(HeartbeatMessage { send_from: SmartSpeakerActors::AudioActor });
+        let state = handler.handle_message(&senders, message);
+        assert_eq!(state, CoreActorState::HeartbeatReceived { actor: SmartSpeakerActors::AudioActor });
+    }
+
+    #[test]
+    fn core_message_handler_actor_restarted_test() {
+        let (tx, rx) = mpsc::channel();
+        let mut senders = HashMap::new();
+        senders.insert(SmartSpeakerActors::ContextActor, tx);
+        let mut handler = CoreActorMessageHandler {
+            debug: DebugData::new(false),
+            text_input: false,
+        };
+        let message = SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            actor: SmartSpeakerActors::CameraActor,
+            cause: "no heartbeat for 10s".to_string(),
+            degraded: true,
+        });
+        let state = handler.handle_message(&senders, message.clone());
+        assert_eq!(state, CoreActorState::WaitForNextMessage {});
+        assert_eq!(rx.recv().unwrap(), message);
+    }
+
+    #[test]
+    fn restart_policy_test() {
+        assert_eq!(RestartPolicy::of(&SmartSpeakerActors::ContextActor), RestartPolicy::Escalate);
+        assert_eq!(RestartPolicy::of(&SmartSpeakerActors::InputActor), RestartPolicy::Escalate);
+        assert_eq!(RestartPolicy::of(&SmartSpeakerActors::AudioActor), RestartPolicy::Restart(3));
+        assert_eq!(RestartPolicy::of(&SmartSpeakerActors::CameraActor), RestartPolicy::Restart(2));
+        assert_eq!(RestartPolicy::of(&SmartSpeakerActors::QueryActor), RestartPolicy::Degrade);
+    }
+
+    /// Restart `actor` with a thread that only waits for its channel to close.
+    fn spawn_idle_actor(manager: &mut CoreActorManager, actor: SmartSpeakerActors, _: mpsc::Sender<SmartSpeakerMessage>) {
+        let (tx, rx) = mpsc::channel::<SmartSpeakerMessage>();
+        manager.watch(actor.clone(), thread::spawn(move || {
+            while rx.recv().is_ok() {}
+        }));
+        manager.add_sender(actor, tx);
+    }
+
+    fn restarted_message(actor: SmartSpeakerActors, cause: &str) -> SmartSpeakerMessage {
+        SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            actor,
+            cause: cause.to_string(),
+            degraded: false,
+        })
+    }
+
+    #[test]
+    fn supervise_panicked_actor_test() {
+        let (context_tx, context_rx) = mpsc::channel();
+        let (core_tx, _core_rx) = mpsc::channel();
+        let mut manager = CoreActorManager::new(RecipeCatalog::new(vec![]), Duration::from_secs(10));
+        manager.add_sender(SmartSpeakerActors::ContextActor, context_tx);
+        let (tx, _rx) = mpsc::channel();
+        manager.add_sender(SmartSpeakerActors::AudioActor, tx);
+        let handle = thread::spawn(|| panic!("microphone unplugged"));
+        while !handle.is_finished() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        manager.watch(SmartSpeakerActors::AudioActor, handle);
+
+        let mut spawned = vec![];
+        manager.supervise_with(core_tx, |manager, actor, sender| {
+            spawned.push(actor.clone());
+            spawn_idle_actor(manager, actor, sender);
+        });
+        assert_eq!(spawned, vec![SmartSpeakerActors::AudioActor]);
+        assert_eq!(context_rx.try_recv().unwrap(),
+                   restarted_message(SmartSpeakerActors::AudioActor, "panicked: microphone unplugged"));
+        // the new thread is watched and healthy
+        assert!(manager.find_failed_actors().is_empty());
+    }
+
+    #[test]
+    fn supervise_hung_actor_test() {
+        let (context_tx, context_rx) = mpsc::channel();
+        let (core_tx, _core_rx) = mpsc::channel();
+        let mut manager = CoreActorManager::new(RecipeCatalog::new(vec![]), Duration::from_millis(50));
+        manager.add_sender(SmartSpeakerActors::ContextActor, context_tx);
+        let (tx, rx) = mpsc::channel();
+        let (stopped_tx, stopped_rx) = mpsc::channel();
+        manager.add_sender(SmartSpeakerActors::CameraActor, tx);
+        // beats once, then only waits for a shutdown request
+        manager.watch(SmartSpeakerActors::CameraActor, thread::spawn(move || {
+            while let Ok(message) = rx.recv() {
+                if let SmartSpeakerMessage::RequestShutdown(_) = message {
+                    break;
+                }
+            }
+            stopped_tx.send(()).unwrap();
+        }));
+        manager.heartbeat(SmartSpeakerActors::CameraActor);
+        assert!(manager.find_failed_actors().is_empty());
+        thread::sleep(Duration::from_millis(100));
+
+        let mut spawned = vec![];
+        manager.supervise_with(core_tx, |manager, actor, sender| {
+            spawned.push(actor.clone());
+            spawn_idle_actor(manager, actor, sender);
+        });
+        assert_eq!(spawned, vec![SmartSpeakerActors::CameraActor]);
+        assert_eq!(context_rx.try_recv().unwrap(),
+                   restarted_message(SmartSpeakerActors::CameraActor, "no heartbeat for 50ms"));
+        // the old thread was asked to stop
+        assert!(stopped_rx.recv_timeout(Duration::from_secs(1)).is_ok());
+        assert!(manager.find_failed_actors().is_empty());
+    }
 }
@@ -1,17 +1,19 @@
 use std::sync::mpsc;
-use std::thread;
 use std::time::Duration;
-use crate::smart_speaker::models::gaze_model::Gaze;
+use crate::smart_speaker::models::gaze_model::{Gaze, GazeSample};
 use crate::smart_speaker::models::message_model::*;
 use crate::utils::message_util::*;
 
+const GAZE_UPDATE_INTERVAL: Duration = Duration::from_millis(200);
+
 pub(crate) struct GazeActor {
     alive: bool,
     core: Gaze,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
-    gaze_x: f32,
-    gaze_y: f32,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
+    sample: Option<GazeSample>,
 }
 
 impl GazeActor {
@@ -21,20 +23,27 @@ impl GazeActor {
             core,
             receiver,
             sender,
-            gaze_x: 0.,
-            gaze_y: 0.,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::GazeActor),
+            ticker: Ticker::new(GAZE_UPDATE_INTERVAL),
+            sample: None,
         }
     }
 
     pub(crate) fn run(&mut self) {
         write_log_message(&self.sender, SmartSpeakerActors::GazeActor, SmartSpeakerLogMessageType::Info("GazeActor started".to_string()));
         while self.alive {
-            self.core.update_gaze();
-            self.handle_gaze(self.core.get_gaze());
-            if let Ok(message) = self.receiver.try_recv() {
+            self.heartbeat.beat(&self.sender);
+            if self.ticker.tick() {
+                self.core.update_gaze();
+                for fixation in self.core.take_fixations() {
+                    write_log_message(&self.sender, SmartSpeakerActors::GazeActor, SmartSpeakerLogMessageType::Debug(
+                        format!("fixation: ({:.3}, {:.3}) for {:?}, {} samples", fixation.x, fixation.y, fixation.duration, fixation.samples)));
+                }
+                self.handle_gaze(self.core.get_gaze());
+            }
+            if let Some(message) = receive_until(&self.receiver, self.ticker.deadline()) {
                 self.handle_message(message);
             }
-            thread::sleep(Duration::from_millis(200));
         }
     }
 
@@ -43,21 +52,21 @@ impl GazeActor {
             SmartSpeakerMessage::RequestShutdown(_) => {
                 self.alive = false;
             },
-            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage {send_from, send_to: _, gaze_info: _}) => {
+            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage {send_from, send_to: _, gaze_info: _, fixation: _}) => {
                 gaze_info_message(
                     &self.sender,
                     SmartSpeakerActors::GazeActor,
                     send_from,
-                    (self.gaze_x, self.gaze_y)
+                    self.sample.clone(),
+                    self.core.current_fixation(),
                 )
             },
             _ => {}
         }
     }
 
-    fn handle_gaze(&mut self, (gaze_x, gaze_y): (f32, f32)) {
-        write_log_message(&self.sender, SmartSpeakerActors::GazeActor, SmartSpeakerLogMessageType::Debug(format!("gaze: ({}, {})", gaze_x, gaze_y)));
-        self.gaze_x = gaze_x;
-        self.gaze_y = gaze_y;
+    fn handle_gaze(&mut self, sample: GazeSample) {
+        write_log_message(&self.sender, SmartSpeakerActors::GazeActor, SmartSpeakerLogMessageType::Debug(format!("gaze: ({}, {}) at {:?}", sample.x, sample.y, sample.timestamp)));
+        self.sample = Some(sample);
     }
 }
@@ -0,0 +1,53 @@
+#[cfg(test)]
+mod gaze_actor_tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use crate::smart_speaker::models::gaze_model::{Gaze, GazeFilter};
+    use crate::smart_speaker::models::message_model::*;
+    use crate::utils::config_util::Config;
+    use crate::utils::fake_pupil_util::*;
+    use crate::utils::vision_util::VisionType;
+    use super::super::gaze_actor::*;
+
+    #[test]
+    fn gaze_actor_fake_pupil_test() {
+        let server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Recording(vec![FakePupilGazeSample {
+                timestamp: 0.,
+                norm_pos: (0.3, 0.7),
+                confidence: 0.99,
+            }]),
+            60.).unwrap();
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let gaze = Gaze::new(VisionType::Pupil, 0.5, 0.5, server.endpoint(), GazeFilter::from_config(&Config::new())).unwrap();
+        let mut gaze_actor = GazeActor::new(gaze, actor_rx, core_tx.clone());
+        thread::spawn(move || {
+            gaze_actor.run();
+        });
+        thread::sleep(Duration::from_millis(300));
+        actor_tx.send(SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage {
+            send_from: SmartSpeakerActors::CoreActor,
+            send_to: SmartSpeakerActors::GazeActor,
+            gaze_info: None,
+            fixation: None,
+        })).expect("TODO: panic message");
+        loop {
+            match core_rx.recv_timeout(Duration::from_secs(2)).expect("no gaze info") {
+                SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from, send_to, gaze_info, fixation: _ }) => {
+                    assert_eq!(send_from, SmartSpeakerActors::GazeActor);
+                    assert_eq!(send_to, SmartSpeakerActors::CoreActor);
+                    let sample = gaze_info.expect("no gaze sample");
+                    assert_eq!(sample.position(), (0.3, 0.7));
+                    assert!(sample.timestamp.is_some());
+                    break;
+                }
+                _ => {}
+            }
+        }
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).expect("TODO: panic message");
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::mpsc;
+use std::thread;
+use crate::smart_speaker::models::intent_model::{IntentAction, TextCommand};
+use crate::smart_speaker::models::message_model::*;
+use crate::utils::message_util::*;
+
+/// Read lines from stdin on a separate thread. stdin blocks, so each line is sent to the input actor as a `StringMessage`.
+pub(crate) fn spawn_stdin_reader(sender: mpsc::Sender<SmartSpeakerMessage>) {
+    thread::spawn(move || {
+        let stdin = std::io::stdin();
+        for line in stdin.lock().lines() {
+            match line {
+                Ok(line) => {
+                    let message = SmartSpeakerMessage::StringMessage(StringMessage {
+                        send_from: SmartSpeakerActors::InputActor,
+                        send_to: SmartSpeakerActors::InputActor,
+                        message: line,
+                    });
+                    if sender.send(message).is_err() {
+                        break;
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+    });
+}
+
+/// Typed commands in place of wake word, voice activity detection and speech to intent.
+pub(crate) struct InputActor {
+    alive: bool,
+    receiver: mpsc::Receiver<SmartSpeakerMessage>,
+    sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
+}
+
+impl InputActor {
+    pub(crate) fn new(receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>) -> Self {
+        Self {
+            alive: true,
+            receiver,
+            sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::InputActor),
+        }
+    }
+
+    pub(crate) fn run(&mut self) {
+        write_log_message(&self.sender, SmartSpeakerActors::InputActor, SmartSpeakerLogMessageType::Info("InputActor started".to_string()));
+        println!("type a command. cook <menu> / next / repeat / cancel / quit");
+        while self.alive {
+            self.heartbeat.beat(&self.sender);
+            if let Some(message) = receive_until(&self.receiver, self.heartbeat.deadline()) {
+                self.handle_message(message);
+            }
+        }
+    }
+
+    fn handle_message(&mut self, message: SmartSpeakerMessage) {
+        match message {
+            SmartSpeakerMessage::RequestShutdown(ShutdownMessage {}) => {
+                self.alive = false;
+            },
+            SmartSpeakerMessage::StringMessage(StringMessage { send_from: _, send_to: _, message: line }) => {
+                self.handle_line(&line);
+            },
+            _ => {
+                write_log_message(&self.sender, SmartSpeakerActors::InputActor, SmartSpeakerLogMessageType::Error("unhandled message".to_string()));
+            }
+        }
+    }
+
+    fn handle_line(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        match TextCommand::from_str(line) {
+            Ok(TextCommand::Intent(content)) => {
+                self.intent_finalized(ProcessResult::Success, content);
+            }
+            Ok(TextCommand::Quit) => {
+                match self.sender.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})) {
+                    Ok(_) => {}
+                    Err(e) => {
+                        println!("Error: {}", e);
+                    }
+                }
+            }
+            Err(e) => {
+                // same as a speech that could not be understood
+                write_log_message(&self.sender, SmartSpeakerActors::InputActor, SmartSpeakerLogMessageType::Error(format!("failed to parse intent: {}", e)));
+                self.intent_finalized(ProcessResult::Failure, IntentContent::new(IntentAction::None, vec![]));
+            }
+        }
+    }
+
+    fn intent_finalized(&mut self, result: ProcessResult, content: IntentContent) {
+        intent_finalized_message(
+            &self.sender,
+            SmartSpeakerActors::InputActor,
+            SmartSpeakerActors::ContextActor,
+            result,
+            content,
+        )
+    }
+}
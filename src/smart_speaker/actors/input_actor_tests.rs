@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod input_actor_tests {
+    use std::str::FromStr;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use crate::smart_speaker::models::intent_model::{IntentAction, IntentCookingMenu, TextCommand};
+    use crate::smart_speaker::models::message_model::*;
+    use super::super::input_actor::*;
+
+    fn next_intent(core_rx: &mpsc::Receiver<SmartSpeakerMessage>) -> IntentFinalizedMessage {
+        loop {
+            match core_rx.recv_timeout(Duration::from_secs(1)).expect("no intent") {
+                SmartSpeakerMessage::IntentFinalized(message) => return message,
+                _ => {}
+            }
+        }
+    }
+
+    fn line(text: &str) -> SmartSpeakerMessage {
+        SmartSpeakerMessage::StringMessage(StringMessage {
+            send_from: SmartSpeakerActors::InputActor,
+            send_to: SmartSpeakerActors::InputActor,
+            message: text.to_string(),
+        })
+    }
+
+    #[test]
+    fn text_command_parse_test() {
+        match TextCommand::from_str("cook ポテトサラダ").unwrap() {
+            TextCommand::Intent(content) => {
+                assert_eq!(content.intent, IntentAction::CookingTask);
+                let menu = content.entities[0].as_any().downcast_ref::<IntentCookingMenu>().unwrap();
+                assert_eq!(menu.name, "ポテトサラダ");
+            }
+            _ => panic!("unexpected command"),
+        }
+        assert_eq!(TextCommand::from_str(" Next ").unwrap(), TextCommand::Intent(IntentContent::new(IntentAction::Next, vec![])));
+        assert_eq!(TextCommand::from_str("repeat").unwrap(), TextCommand::Intent(IntentContent::new(IntentAction::Repeat, vec![])));
+        assert_eq!(TextCommand::from_str("cancel").unwrap(), TextCommand::Intent(IntentContent::new(IntentAction::Cancel, vec![])));
+        assert_eq!(TextCommand::from_str("quit").unwrap(), TextCommand::Quit);
+        assert!(TextCommand::from_str("cook").is_err());
+        assert!(TextCommand::from_str("dance").is_err());
+    }
+
+    #[test]
+    fn input_actor_test() {
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let mut input_actor = InputActor::new(actor_rx, core_tx.clone());
+        let handle = thread::spawn(move || {
+            input_actor.run();
+        });
+        actor_tx.send(line("cook にんじんサラダ")).unwrap();
+        let message = next_intent(&core_rx);
+        assert_eq!(message.send_from, SmartSpeakerActors::InputActor);
+        assert_eq!(message.send_to, SmartSpeakerActors::ContextActor);
+        assert_eq!(message.result, ProcessResult::Success);
+        assert_eq!(message.content.intent, IntentAction::CookingTask);
+        actor_tx.send(line("sing a song")).unwrap();
+        let message = next_intent(&core_rx);
+        assert_eq!(message.result, ProcessResult::Failure);
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).unwrap();
+        handle.join().unwrap();
+    }
+}
@@ -1,18 +1,21 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use chrono::Local;
 use log::LevelFilter;
 use log4rs::append::file::FileAppender;
 use log4rs::encode::pattern::PatternEncoder;
 use log4rs::config::{Appender, Config, Root};
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::message_util::{receive_until, Heartbeat};
+
+/// Log files are written here, relative to the working directory.
+pub(crate) const LOG_DIR: &str = "log";
 
 pub(crate) struct LoggerActor {
     alive: bool,
     debug: bool,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
 }
 
 impl LoggerActor {
@@ -22,6 +25,7 @@ impl LoggerActor {
             debug,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::LoggerActor),
         }
     }
 
@@ -29,10 +33,10 @@ impl LoggerActor {
         log::info!("[{}] LoggerActor started", SmartSpeakerActors::LoggerActor);
         let _ = self.init_logger();
         while self.alive {
-            if let Ok(message) = self.receiver.try_recv() {
+            self.heartbeat.beat(&self.sender);
+            if let Some(message) = receive_until(&self.receiver, self.heartbeat.deadline()) {
                 self.handle_message(message);
             }
-            thread::sleep(Duration::from_millis(1));
         }
     }
 
@@ -64,7 +68,7 @@ impl LoggerActor {
     fn init_logger(&self) -> Result<(), Box<dyn std::error::Error>> {
         let logfile = FileAppender::builder()
             .encoder(Box::new(PatternEncoder::new("{d} [{l}] - {m}\n")))
-            .build(format!("log/vgv-{}.log", Local::now().format("%Y-%m-%dT%H_%M")))?;
+            .build(format!("{}/vgv-{}.log", LOG_DIR, Local::now().format("%Y-%m-%dT%H_%M")))?;
 
         let mut root= Root::builder()
             .appender("logfile")
@@ -1,6 +1,5 @@
 use std::sync::{mpsc};
 use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::models::speak_model::{MachineSpeech, MachineSpeechBoilerplate};
 use crate::smart_speaker::models::message_model::*;
 use crate::utils::message_util::*;
@@ -11,20 +10,17 @@ pub(crate) struct MachineSpeechActor {
     app: MachineSpeech,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
-    callback_sender: mpsc::Sender<SmartSpeakerActors>,
-    callback_receiver: mpsc::Receiver<SmartSpeakerActors>
+    heartbeat: Heartbeat,
 }
 
 impl MachineSpeechActor {
     pub(crate) fn new(app: MachineSpeech, receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>) -> Self {
-        let (tx, rx) = mpsc::channel();
         Self {
             alive: true,
             app,
             receiver,
             sender,
-            callback_sender: tx,
-            callback_receiver: rx
+            heartbeat: Heartbeat::new(SmartSpeakerActors::MachineSpeechActor),
         }
     }
 
@@ -35,19 +31,10 @@ impl MachineSpeechActor {
 
         self.speech(TextToSpeechMessageType::Boilerplate(MachineSpeechBoilerplate::PowerOn as usize), Some(SmartSpeakerActors::CoreActor));
         while self.alive {
-            match self.callback_receiver.try_recv() {
-                Ok(actor) => {
-                    self.text_to_speech_finished_message(actor);
-                },
-                _ => {}
+            self.heartbeat.beat(&self.sender);
+            if let Some(message) = receive_until(&self.receiver, self.heartbeat.deadline()) {
+                self.handle_message(message);
             }
-            match self.receiver.try_recv() {
-                Ok(message) => {
-                    self.handle_message(message);
-                },
-                _ => {}
-            }
-            thread::sleep(Duration::from_millis(33));
         }
     }
 
@@ -55,21 +42,24 @@ impl MachineSpeechActor {
         let (micro_tx, micro_rx) = mpsc::channel();
         let mut speech_callback_actor = MachineSpeechCallbackMicroActor {
             receiver: micro_rx,
-            sender: self.callback_sender.clone(),
+            sender: self.sender.clone(),
             message: request_from
         };
         thread::spawn(move || {
             speech_callback_actor.run();
         });
-        match message {
+        let spoken = match message {
             TextToSpeechMessageType::Normal(i18n_text) => {
-                self.app.speak_with_callback(i18n_text, micro_tx);
+                self.app.speak_with_callback(i18n_text, micro_tx)
             }
             TextToSpeechMessageType::Boilerplate(index) => {
                 self.app.speak_with_callback(
                     MachineSpeechBoilerplate::try_from(index).unwrap().to_i18n(),
-                    micro_tx);
+                    micro_tx)
             }
+        };
+        if let Err(e) = spoken {
+            write_log_message(&self.sender, SmartSpeakerActors::MachineSpeechActor, SmartSpeakerLogMessageType::Error(format!("failed to speak: {}", e)));
         }
     }
 
@@ -87,40 +77,29 @@ impl MachineSpeechActor {
             }
         }
     }
-
-    fn text_to_speech_finished_message(&mut self, request_from: SmartSpeakerActors) {
-        text_to_speech_finished_message(
-            &self.sender,
-            SmartSpeakerActors::MachineSpeechActor,
-            request_from,
-            "".to_string()
-        )
-    }
 }
 
+/// Waits for the callback of one utterance and reports the finished speech to the core actor.
 pub(crate) struct MachineSpeechCallbackMicroActor {
     receiver: mpsc::Receiver<usize>,
-    sender: mpsc::Sender<SmartSpeakerActors>,
+    sender: mpsc::Sender<SmartSpeakerMessage>,
     message: Option<SmartSpeakerActors>
 }
 
 impl MachineSpeechCallbackMicroActor {
     fn run(&mut self) {
         dbg!(format!("MachineSpeechCallbackMicroActor started with {:?}", &self.message.clone()));
-        let mut alive = true;
-        while alive {
-            match self.receiver.try_recv() {
-                Ok(message) => {
-                    alive = false;
-                    dbg!(format!("MachineSpeechCallbackMicroActor({:?}) got callback({:?})", &self.message, message));
-                    if let Some(actor) = &self.message {
-                        self.sender.send(actor.clone()).unwrap();
-                    }
-                },
-                _ => {
-                }
+        // the callback is dropped without being called when the speech output is gone.
+        if let Ok(message) = self.receiver.recv() {
+            dbg!(format!("MachineSpeechCallbackMicroActor({:?}) got callback({:?})", &self.message, message));
+            if let Some(actor) = &self.message {
+                text_to_speech_finished_message(
+                    &self.sender,
+                    SmartSpeakerActors::MachineSpeechActor,
+                    actor.clone(),
+                    "".to_string()
+                )
             }
-            thread::sleep(Duration::from_millis(1));
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod machine_speech_actor_tests {
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use crate::smart_speaker::models::message_model::*;
+    use crate::smart_speaker::models::speak_model::{ConsoleSpeechOutput, MachineSpeech};
+    use crate::utils::config_util::LanguageTag;
+    use super::super::machine_speech_actor::*;
+
+    fn next_finished(core_rx: &mpsc::Receiver<SmartSpeakerMessage>) -> StringMessage {
+        loop {
+            match core_rx.recv_timeout(Duration::from_secs(1)).expect("no speech finished") {
+                SmartSpeakerMessage::TextToSpeechFinished(message) => return message,
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn machine_speech_console_test() {
+        let (core_tx, core_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let (actor_tx, actor_rx) = mpsc::channel::<SmartSpeakerMessage>();
+        let speech = MachineSpeech::with_output(Box::new(ConsoleSpeechOutput::new(false)), LanguageTag::English);
+        let mut machine_speech_actor = MachineSpeechActor::new(speech, actor_rx, core_tx.clone());
+        let handle = thread::spawn(move || {
+            machine_speech_actor.run();
+        });
+        // power on
+        assert_eq!(next_finished(&core_rx).send_to, SmartSpeakerActors::CoreActor);
+        actor_tx.send(SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage {
+            send_from: SmartSpeakerActors::ContextActor,
+            send_to: SmartSpeakerActors::MachineSpeechActor,
+            message: TextToSpeechMessageType::Normal(SmartSpeakerI18nText::new().en("Cut the carrot.")),
+        })).unwrap();
+        let message = next_finished(&core_rx);
+        assert_eq!(message.send_from, SmartSpeakerActors::MachineSpeechActor);
+        assert_eq!(message.send_to, SmartSpeakerActors::ContextActor);
+        actor_tx.send(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})).unwrap();
+        handle.join().unwrap();
+    }
+}
@@ -11,9 +11,13 @@ mod speech_to_intent_actor;
 // mod stream_actor;
 mod query_actor;
 mod gaze_actor;
+mod gaze_actor_tests;
 mod machine_speech_actor;
-mod context_actor;
+mod machine_speech_actor_tests;
+pub(crate) mod context_actor;
+mod context_actor_tests;
 mod voice_activity_detect_actor;
 mod input_actor;
-mod logger_actor;
+mod input_actor_tests;
+pub(crate) mod logger_actor;
 mod subtask_actor;
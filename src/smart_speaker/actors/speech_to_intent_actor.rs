@@ -1,11 +1,9 @@
-use std::str::FromStr;
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::controllers::mic_controller;
-use crate::smart_speaker::models::intent_model::{IntentAction, IntentCookingMenu};
+use crate::smart_speaker::models::intent_model::IntentAction;
 use crate::smart_speaker::models::mic_model::SpeechToIntent;
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::audio_util::FRAME_DURATION;
 use crate::utils::message_util::*;
 
 pub(crate) struct SpeechToIntentActor {
@@ -13,6 +11,8 @@ pub(crate) struct SpeechToIntentActor {
     app: SpeechToIntent,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
     stream_before: Vec<i16>,
 }
 
@@ -23,6 +23,8 @@ impl SpeechToIntentActor {
             app,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::SpeechToIntentActor),
+            ticker: Ticker::new(FRAME_DURATION),
             stream_before: vec![],
         }
     }
@@ -31,16 +33,13 @@ impl SpeechToIntentActor {
         write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Info("SpeechToIntentActor started".to_string()));
         // write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Debug(self.app.info()));
         while self.alive {
-            match self.receiver.try_recv() {
-                Ok(message) => {
-                    self.handle_message(message);
-                },
-                _ => {}
-            }
-            if self.alive {
+            self.heartbeat.beat(&self.sender);
+            if self.ticker.tick() {
                 self.request_audio_stream();
             }
-            thread::sleep(Duration::from_millis(33));
+            if let Some(message) = receive_until(&self.receiver, self.ticker.deadline()) {
+                self.handle_message(message);
+            }
         }
     }
 
@@ -64,41 +63,23 @@ impl SpeechToIntentActor {
     fn listen(&mut self, stream: &Vec<i16>) {
         if let Ok(finalized) = mic_controller::speech_to_intent_feed(&mut self.app, &stream) {
             if finalized {
-                let mut content = IntentContent {
+                let content = IntentContent {
                     intent: IntentAction::None,
                     entities: vec![],
                 };
-                if let Ok(inference) = self.app.get_inference() {
-                    match inference {
-                        None => {
-                            dbg!("inference is none");
-                            write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Error(format!("failed to parse intent: None")));
-                            self.intent_finalized(ProcessResult::Failure, content);
-                        }
-                        Some(i) => {
-                            let intent = &i.intent.unwrap();
-                            match IntentAction::from_str(intent) {
-                                Ok(action) => {
-                                    content.intent = action;
-                                    for (key, value) in i.slots {
-                                        match key.as_str() {
-                                            "menu_name" => {
-                                                content.entities.push(Box::new(IntentCookingMenu::from_str(&value).unwrap()));
-                                            }
-                                            &_ => {}
-                                        }
-                                    }
-                                    self.intent_finalized(ProcessResult::Success, content);
-                                }
-                                Err(_) => {
-                                    write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Error(format!("failed to parse intent: {}", intent)));
-                                    self.intent_finalized(ProcessResult::Failure, content);
-                                }
-                            }
-                        }
+                match self.app.get_inference() {
+                    Ok(Some(inference)) => {
+                        self.intent_finalized(ProcessResult::Success, inference);
+                    }
+                    Ok(None) => {
+                        dbg!("inference is none");
+                        write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Error(format!("failed to parse intent: None")));
+                        self.intent_finalized(ProcessResult::Failure, content);
+                    }
+                    Err(e) => {
+                        write_log_message(&self.sender, SmartSpeakerActors::SpeechToIntentActor, SmartSpeakerLogMessageType::Error(e.to_string()));
+                        self.intent_finalized(ProcessResult::Failure, content);
                     }
-                } else {
-                    self.intent_finalized(ProcessResult::Failure, content);
                 }
                 self.terminate();
             }
@@ -1,6 +1,4 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use chrono::Local;
 use log::LevelFilter;
 use log4rs::append::file::FileAppender;
@@ -29,10 +27,10 @@ impl SubtaskActor {
         log::info!("[{}] SubtaskActor started", SmartSpeakerActors::LoggerActor);
         let _ = self.init_logger();
         while self.alive {
-            if let Ok(message) = self.receiver.try_recv() {
-                self.handle_message(message);
+            match self.receiver.recv() {
+                Ok(message) => self.handle_message(message),
+                Err(_) => self.alive = false,
             }
-            thread::sleep(Duration::from_millis(1));
         }
     }
 
@@ -1,56 +1,86 @@
 use std::sync::mpsc;
-use std::thread;
 use std::time::Duration;
 use anyhow::{anyhow, Result};
 use bounded_vec_deque::BoundedVecDeque;
-use opencv::{core::Mat, core::Vector, types::VectorOfVectorOfPoint2f};
+use opencv::{core::Mat, core::Point2f, core::Vector, objdetect::ArucoDetector, types::VectorOfVectorOfPoint2f};
 use opencv::prelude::MatTraitConst;
 use crate::smart_speaker::controllers::vision_controller;
 use crate::smart_speaker::models::core_model::{WaitingInteraction, SmartSpeakerState};
-use crate::smart_speaker::models::vision_model::{DetectableObject, DetectionDetail, DetectionMode, VisionAction, VisionObject, VisionSlot};
+use crate::smart_speaker::models::frame_model::SharedFrame;
+use crate::smart_speaker::models::gaze_model::{GazeHistory, GazeSample};
+use crate::smart_speaker::models::marker_model::{MarkerBoard, MarkerTag};
+use crate::smart_speaker::models::vision_model::{DetectionDetail, DetectionMode, DwellSelection, VisionAction, VisionObject, VisionSlot};
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::calibration_util::CameraCalibration;
 use crate::utils::message_util::*;
 use crate::utils::vision_util;
 
+/// Camera frames and gaze are requested at the camera frame rate.
+const FRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(33);
+/// Gaze dwell is accumulated over the frames of this window before the request.
+const DWELL_WINDOW: Duration = Duration::from_secs(1);
+/// A frame stands for at most this long, so a gap in the frames is not dwell.
+const MAX_FRAME_DWELL: Duration = Duration::from_millis(100);
+/// Shortest dwell to select an object.
+const MIN_DWELL: Duration = Duration::from_millis(300);
+/// Objects looked at for at least this ratio of the longest dwell are a tie.
+const DWELL_TIE_RATIO: f32 = 0.8;
+/// Gaze just outside an object still counts for it within this distance in pixels.
+const DWELL_MAX_DISTANCE: f32 = 30.;
+/// Gaze on the contents of a tagged tray counts for its marker within this distance in pixels.
+const MARKER_GAZE_MAX_DISTANCE: f32 = 100.;
+
 pub(crate) struct VisionActor {
     alive: bool,
     debug: bool,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
-    previous_frames: BoundedVecDeque<Mat>,
-    previous_gaze_info: BoundedVecDeque<(f32, f32)>,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
+    previous_frames: BoundedVecDeque<SharedFrame>,
+    gaze_history: GazeHistory,
     previous_aruco_info: BoundedVecDeque<(VectorOfVectorOfPoint2f, Vector<i32>)>,
+    marker_board: MarkerBoard,
+    aruco_detector: ArucoDetector,
+    /// Lens of the camera. Detected points are undistorted before they are measured.
+    calibration: Option<CameraCalibration>,
 }
 
 impl VisionActor {
-    pub(crate) fn new(receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>, debug: bool) -> Self {
+    pub(crate) fn new(receiver: mpsc::Receiver<SmartSpeakerMessage>, sender: mpsc::Sender<SmartSpeakerMessage>, debug: bool, gaze_max_skew: Duration, calibration: Option<CameraCalibration>, marker_board: MarkerBoard) -> Self {
         Self {
             alive: true,
             debug,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::VisionActor),
+            ticker: Ticker::new(FRAME_REQUEST_INTERVAL),
             previous_frames: BoundedVecDeque::new(30),
-            previous_gaze_info: BoundedVecDeque::new(30),
+            gaze_history: GazeHistory::new(30, gaze_max_skew),
             previous_aruco_info: BoundedVecDeque::new(30),
+            aruco_detector: vision_controller::aruco_detector(&marker_board).expect("failed to create aruco detector"),
+            marker_board,
+            calibration,
         }
     }
 
     pub(crate) fn run(&mut self) {
         write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Info("VisionActor started".to_string()));
+        match &self.calibration {
+            Some(calibration) => write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Info(
+                format!("Camera calibration of {}: {:.3}px reprojection error", calibration.device, calibration.rms_error))),
+            None => write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Warn(
+                "no camera calibration, measurements include the lens distortion".to_string())),
+        }
         while self.alive {
-            if self.alive {
+            self.heartbeat.beat(&self.sender);
+            if self.ticker.tick() {
                 self.request_camera_frame();
                 self.request_gaze_info();
             }
-            let mut pending = true;
-            while pending {
-                if let Ok(message) = self.receiver.try_recv() {
-                    self.handle_message(message);
-                } else {
-                    pending = false;
-                }
+            if let Some(message) = receive_until(&self.receiver, self.ticker.deadline()) {
+                self.handle_message(message);
             }
-            thread::sleep(Duration::from_millis(33));
         }
     }
 
@@ -59,52 +89,32 @@ impl VisionActor {
             SmartSpeakerMessage::RequestShutdown(_) => {
                 self.alive = false;
             },
-            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from: _,
-                                                        send_to: _,
-                                                        frame_data_bytes,
-                                                        height,}) => {
-                self.handle_frame_data_bytes(frame_data_bytes, height);
+            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from: _, send_to: _, frame }) => {
+                if let Some(frame) = frame {
+                    self.previous_frames.push_back(frame);
+                }
                 match self.previous_frames.back() {
                     Some(frame) => {
-                        let aruco_result = vision_controller::detect_aruco(frame).unwrap();
-                        self.previous_aruco_info.push_back(aruco_result);
+                        let (corners, ids) = vision_controller::detect_aruco(&frame.view_bgr().unwrap(), &self.aruco_detector).unwrap();
+                        let corners = self.undistort(corners, (frame.header().width, frame.header().height)).unwrap();
+                        self.previous_aruco_info.push_back((corners, ids));
                     }
                     None => {
                         self.previous_aruco_info.push_back((VectorOfVectorOfPoint2f::new(), Vector::new()));
                     }
                 }
             },
-            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from: _, send_to: _, gaze_info }) => {
-                self.handle_gaze_info(gaze_info);
+            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from: _, send_to: _, gaze_info, fixation: _ }) => {
+                if let Some(sample) = gaze_info {
+                    self.handle_gaze_info(sample);
+                }
             },
             SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage { send_from: _, send_to: _, state }) => {
                 match state {
                     SmartSpeakerState::WaitingForInteraction(p) => {
                         match p {
                             WaitingInteraction::Vision(actions) => {
-                                let mut result: Vec<VisionContent> = Vec::new();
-                                for action in actions {
-                                    match action {
-                                        VisionAction::None => {}
-                                        VisionAction::ObjectDetection(detail) => {
-                                            match detail.detection_mode {
-                                                DetectionMode::None => {}
-                                                DetectionMode::Aruco => {
-                                                    match self.handle_object_detection_with_aruco(detail.clone()) {
-                                                        Ok(content) => {
-                                                            result.push(content);
-                                                        }
-                                                        Err(_) => {
-                                                            self.send_vision_finalized(ProcessResult::Failure, vec![]);
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                        }
-                                    }
-                                }
-                                self.send_vision_finalized(ProcessResult::Success, result);
+                                self.handle_vision_actions(actions);
                             }
                             _ => {
                                 self.send_vision_finalized(ProcessResult::Failure, vec![]);
@@ -120,20 +130,84 @@ impl VisionActor {
         }
     }
 
-    fn handle_frame_data_bytes(&mut self, frame_data_bytes: Vec<u8>, height: i32) {
-        match vision_controller::data_bytes_to_mat(frame_data_bytes, height) {
-            Ok(frame) => {
-                self.previous_frames.push_back(frame);
+    fn handle_gaze_info(&mut self, sample: GazeSample) {
+        self.gaze_history.push(sample);
+    }
+
+    /// Answers with one content per action. Any failure fails the whole request,
+    /// and an object that could not be chosen by gaze makes it unsure.
+    fn handle_vision_actions(&mut self, actions: Vec<VisionAction>) {
+        let mut result = ProcessResult::Success;
+        let mut contents: Vec<VisionContent> = Vec::new();
+        for action in actions {
+            match action {
+                VisionAction::None => {}
+                VisionAction::ObjectDetection(detail) => {
+                    match detail.detection_mode {
+                        DetectionMode::None => {}
+                        DetectionMode::Aruco => {
+                            match self.handle_object_detection_with_aruco(detail.clone()) {
+                                Ok((content_result, content)) => {
+                                    if content_result == ProcessResult::Unsure {
+                                        result = ProcessResult::Unsure;
+                                    }
+                                    contents.push(content);
+                                }
+                                Err(e) => {
+                                    write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Error(format!("{}", e)));
+                                    self.send_vision_finalized(ProcessResult::Failure, vec![]);
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                }
+                VisionAction::MarkerPresence(tag) => {
+                    match self.handle_marker_presence(tag) {
+                        Ok(content) => {
+                            contents.push(content);
+                        }
+                        Err(e) => {
+                            write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Error(format!("{}", e)));
+                            self.send_vision_finalized(ProcessResult::Failure, vec![]);
+                            return;
+                        }
+                    }
+                }
             }
-            Err(_) => {}
-        };
+        }
+        self.send_vision_finalized(result, contents);
     }
 
-    fn handle_gaze_info(&mut self, (x, y): (f32, f32)) {
-        self.previous_gaze_info.push_back((x, y));
+    /// Contours in pixels of a `frame_size` frame as seen through an ideal lens. Unchanged without calibration.
+    fn undistort(&self, contours: VectorOfVectorOfPoint2f, frame_size: (i32, i32)) -> Result<VectorOfVectorOfPoint2f> {
+        match &self.calibration {
+            Some(calibration) => calibration.undistort_contours(&contours, frame_size),
+            None => Ok(contours),
+        }
+    }
+
+    /// Gaze of the frames in the dwell window, in undistorted pixels of a `frame_size` frame,
+    /// and how long each frame stood until the next one.
+    fn gaze_dwell_points(&self, frame_size: (i32, i32)) -> Result<Vec<((f32, f32), Duration)>> {
+        let latest = match self.previous_frames.back() {
+            Some(frame) => frame.header().captured_at,
+            None => return Ok(vec![]),
+        };
+        let (points, durations): (Vec<Point2f>, Vec<Duration>) = self.previous_frames.iter().zip(self.previous_frames.iter().skip(1))
+            .filter(|(_, frame)| latest.saturating_duration_since(frame.header().captured_at) <= DWELL_WINDOW)
+            .filter_map(|(previous, frame)| {
+                let gaze = self.gaze_history.at(frame.header())?;
+                let duration = frame.header().captured_at.saturating_duration_since(previous.header().captured_at);
+                let (x, y) = vision_util::gaze_to_pxf(&gaze, &frame_size);
+                Some((Point2f::new(x, y), duration.min(MAX_FRAME_DWELL)))
+            })
+            .unzip();
+        let points = self.undistort(VectorOfVectorOfPoint2f::from_iter([Vector::from_iter(points)]), frame_size)?;
+        Ok(points.get(0)?.iter().map(|point| (point.x, point.y)).zip(durations).collect())
     }
 
-    fn handle_object_detection_with_aruco(&self, detail: DetectionDetail) -> Result<VisionContent> {
+    fn handle_object_detection_with_aruco(&self, detail: DetectionDetail) -> Result<(ProcessResult, VisionContent)> {
         match self.previous_aruco_info.back() {
             None => {
                 Err(anyhow!("failed to detect target objects: no aruco data"))
@@ -141,40 +215,42 @@ impl VisionActor {
             Some((aruco, aruco_index)) => {
                 match self.previous_frames.back() {
                     Some(frame) => {
-                        match vision_controller::detect_target_objects(frame, &detail.detectable) {
+                        let view = frame.view_bgr()?;
+                        let frame_info = format!("{:?}", frame);
+                        let frame: &Mat = &view;
+                        match vision_controller::detect_target_objects(frame, &detail.detectable)
+                            .and_then(|objects| self.undistort(objects, (frame.cols(), frame.rows()))) {
                             Ok(objects) => {
-                                write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Detected objects: {}", &objects.len())));
+                                write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Detected objects: {} in {}", &objects.len(), frame_info)));
                                 let shapes = vision_controller::detect_object_shape(&objects).unwrap();
-                                match vision_controller::measure_object_size_by_aruco(aruco, &objects) {
+                                match vision_controller::measure_object_size_by_aruco(aruco, aruco_index, &self.marker_board, &objects) {
                                     Ok(measure_result) => {
                                         write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Measured objects: {:?}", &measure_result)));
                                         if detail.gaze_assist {
-                                            match self.previous_gaze_info.back() {
-                                                Some((x, y)) => {
-                                                    let gaze_as_pxf = vision_util::gaze_to_pxf(&(*x, *y), &(frame.cols(), frame.rows()));
-                                                    let gaze_assist_result = vision_controller::find_nearest_object_from_gaze(&gaze_as_pxf, &objects);
-                                                    match gaze_assist_result {
-                                                        Ok(result) => {
-                                                            let content_result = VisionContent::new(
-                                                                VisionAction::ObjectDetection(detail.clone()),
-                                                                vec![Box::new(VisionObject::new(
-                                                                    detail.detectable.clone(),
-                                                                    measure_result.get(result.0).unwrap().clone(),
-                                                                    shapes.get(result.0).unwrap().clone(),
-                                                                )) as Box<dyn VisionSlot>]
-                                                            );
-                                                            write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Content: {:?}", &content_result)));
-                                                            Ok(content_result)
-                                                        }
-                                                        Err(e) => {
-                                                            Err(anyhow!("failed to measure target objects: {}", e))
-                                                        }
-                                                    }
-                                                }
-                                                _ => {
-                                                    Err(anyhow!("failed to measure target objects: no gaze data"))
-                                                }
+                                            // the objects of the latest frame are assumed to stay still within the dwell window
+                                            let gaze_points = self.gaze_dwell_points((frame.cols(), frame.rows()))?;
+                                            if gaze_points.is_empty() {
+                                                write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Warn(format!("no gaze within {:?} of the frames until {}", self.gaze_history.max_skew(), frame_info)));
                                             }
+                                            let dwell = vision_controller::accumulate_gaze_dwell(&gaze_points, &objects, DWELL_MAX_DISTANCE)?;
+                                            let last_gazed = vision_controller::last_gazed_object(&gaze_points, &objects, DWELL_MAX_DISTANCE)?;
+                                            write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Gaze dwell: {:?}, last gazed: {:?}", &dwell, &last_gazed)));
+                                            let object = |i: usize| {
+                                                Box::new(VisionObject::new(
+                                                    detail.detectable.clone(),
+                                                    measure_result.get(i).unwrap().clone(),
+                                                    shapes.get(i).unwrap().clone(),
+                                                )) as Box<dyn VisionSlot>
+                                            };
+                                            let (result, entities) = match vision_controller::select_by_dwell(&dwell, last_gazed, MIN_DWELL, DWELL_TIE_RATIO) {
+                                                DwellSelection::Selected(i) => (ProcessResult::Success, vec![object(i)]),
+                                                // asking again would not help if nothing was looked at
+                                                DwellSelection::None => return Err(anyhow!("no detected object was looked at")),
+                                                DwellSelection::Unsure(candidates) => (ProcessResult::Unsure, candidates.into_iter().map(object).collect()),
+                                            };
+                                            let content_result = VisionContent::new(VisionAction::ObjectDetection(detail.clone()), entities);
+                                            write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Content: {:?} {:?}", &result, &content_result)));
+                                            Ok((result, content_result))
                                         } else {
                                             let content_result = VisionContent::new(
                                                 VisionAction::ObjectDetection(detail.clone()),
@@ -188,7 +264,7 @@ impl VisionActor {
                                                     }).collect()
                                                 );
                                             write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Content: {:?}", &content_result)));
-                                            Ok(content_result)
+                                            Ok((ProcessResult::Success, content_result))
                                         }
                                     }
                                     Err(_) => {
@@ -209,12 +285,36 @@ impl VisionActor {
         }
     }
 
+    /// Presence of the markers tagged with `tag` over the frames of `previous_aruco_info`.
+    fn handle_marker_presence(&self, tag: MarkerTag) -> Result<VisionContent> {
+        let (corners, ids) = self.previous_aruco_info.back()
+            .ok_or(anyhow!("failed to check marker {}: no aruco data", tag))?;
+        if !self.marker_board.tags().contains(&tag) {
+            return Err(anyhow!("failed to check marker {}: no marker of the aruco board is tagged with it", tag));
+        }
+        let gaze_points = match self.previous_frames.back() {
+            Some(frame) => self.gaze_dwell_points((frame.header().width, frame.header().height))?,
+            None => vec![],
+        };
+        let recent_ids = self.previous_aruco_info.iter().map(|(_, ids)| ids.clone()).collect::<Vec<Vector<i32>>>();
+        let markers = vision_controller::tagged_marker_presence(&self.marker_board, &recent_ids, corners, ids,
+                                                                &gaze_points, MARKER_GAZE_MAX_DISTANCE, MIN_DWELL)?;
+        write_log_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerLogMessageType::Debug(format!("Tagged markers: {:?}", &markers)));
+        let content = VisionContent::new(
+            VisionAction::MarkerPresence(tag),
+            markers.into_iter()
+                .filter(|marker| marker.tag == tag)
+                .map(|marker| Box::new(marker) as Box<dyn VisionSlot>)
+                .collect());
+        Ok(content)
+    }
+
     fn request_camera_frame(&self) {
-        camera_frame_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerActors::CameraActor, vec![], 0);
+        camera_frame_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerActors::CameraActor, None);
     }
 
     fn request_gaze_info(&self) {
-        gaze_info_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerActors::GazeActor, (0., 0.));
+        gaze_info_message(&self.sender, SmartSpeakerActors::VisionActor, SmartSpeakerActors::GazeActor, None, None);
     }
 
     fn send_vision_finalized(&self, result: ProcessResult, contents: Vec<VisionContent>) {
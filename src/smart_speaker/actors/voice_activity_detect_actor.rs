@@ -1,9 +1,8 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::models::core_model::SmartSpeakerState;
 use crate::smart_speaker::models::mic_model::VoiceActivityDetector;
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::audio_util::FRAME_DURATION;
 use crate::utils::message_util::*;
 
 pub(crate) struct VoiceActivityDetectActor {
@@ -11,6 +10,8 @@ pub(crate) struct VoiceActivityDetectActor {
     app: VoiceActivityDetector,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
     stream_before: Vec<i16>,
 }
 
@@ -21,6 +22,8 @@ impl VoiceActivityDetectActor {
             app,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::VoiceActivityDetectActor),
+            ticker: Ticker::new(FRAME_DURATION),
             stream_before: vec![],
         }
     }
@@ -29,16 +32,13 @@ impl VoiceActivityDetectActor {
         println!("VoiceActivityDetectActor started");
         self.app.info();
         while self.alive {
-            match self.receiver.try_recv() {
-                Ok(message) => {
-                    self.handle_message(message);
-                },
-                _ => {}
-            }
-            if self.alive {
+            self.heartbeat.beat(&self.sender);
+            if self.ticker.tick() {
                 self.request_audio_stream();
             }
-            thread::sleep(Duration::from_millis(33));
+            if let Some(message) = receive_until(&self.receiver, self.ticker.deadline()) {
+                self.handle_message(message);
+            }
         }
     }
 
@@ -78,12 +78,9 @@ impl VoiceActivityDetectActor {
     }
 
     fn listen(&mut self, stream: &Vec<i16>) {
-        if let Ok(probability) = self.app.detect(&stream) {
-            // dbg!(&probability);
-            if probability > 0.5 {
-                self.request_attention();
-                self.terminate();
-            }
+        if let Ok(true) = self.app.is_speech(&stream) {
+            self.request_attention();
+            self.terminate();
         }
     }
 
@@ -1,8 +1,7 @@
 use std::sync::mpsc;
-use std::thread;
-use std::time::Duration;
 use crate::smart_speaker::models::mic_model::WakeWordDetector;
 use crate::smart_speaker::models::message_model::*;
+use crate::utils::audio_util::FRAME_DURATION;
 use crate::utils::message_util::*;
 use crate::smart_speaker::models::core_model::SmartSpeakerState;
 
@@ -11,6 +10,8 @@ pub(crate) struct WakeWordActor {
     core: WakeWordDetector,
     receiver: mpsc::Receiver<SmartSpeakerMessage>,
     sender: mpsc::Sender<SmartSpeakerMessage>,
+    heartbeat: Heartbeat,
+    ticker: Ticker,
     stream_before: Vec<i16>,
 }
 
@@ -21,6 +22,8 @@ impl WakeWordActor {
             core,
             receiver,
             sender,
+            heartbeat: Heartbeat::new(SmartSpeakerActors::WakeWordActor),
+            ticker: Ticker::new(FRAME_DURATION),
             stream_before: vec![],
         }
     }
@@ -29,16 +32,13 @@ impl WakeWordActor {
         write_log_message(&self.sender, SmartSpeakerActors::WakeWordActor, SmartSpeakerLogMessageType::Info("WakeWordActor started".to_string()));
         write_log_message(&self.sender, SmartSpeakerActors::WakeWordActor, SmartSpeakerLogMessageType::Info(self.core.info()));
         while self.alive {
-            match self.receiver.try_recv() {
-                Ok(message) => {
-                    self.handle_message(message);
-                },
-                _ => {}
-            }
-            if self.alive {
+            self.heartbeat.beat(&self.sender);
+            if self.ticker.tick() {
                 self.request_audio_stream();
             }
-            thread::sleep(Duration::from_millis(33));
+            if let Some(message) = receive_until(&self.receiver, self.ticker.deadline()) {
+                self.handle_message(message);
+            }
         }
         write_log_message(&self.sender, SmartSpeakerActors::WakeWordActor, SmartSpeakerLogMessageType::Info("WakeWordActor terminated".to_string()));
     }
@@ -1,4 +1,45 @@
 #[cfg(test)]
 mod camera_controller_tests {
+    use opencv::prelude::*;
+    use opencv::core::{Mat, Scalar, Vector, CV_8UC3};
+    use opencv::imgcodecs;
+    use crate::smart_speaker::models::vision_model::{CaptureSource, ImageSequenceCaptureSource};
+    use crate::utils::camera_util::ImageSequence;
     use super::super::camera_controller::*;
-}
\ No newline at end of file
+
+    fn write_image_sequence(name: &str, count: usize) -> String {
+        let directory = std::env::temp_dir().join(name);
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        for i in 0..count {
+            let image = Mat::new_rows_cols_with_default(48, 64, CV_8UC3, Scalar::all(i as f64)).unwrap();
+            imgcodecs::imwrite(directory.join(format!("frame_{:04}.png", i)).to_str().unwrap(), &image, &Vector::new()).unwrap();
+        }
+        directory.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn capture_image_sequence_test() {
+        let directory = write_image_sequence("vas_capture_image_sequence", 2);
+        let mut source: Box<dyn CaptureSource + Send + 'static> = Box::new(
+            ImageSequenceCaptureSource::new(ImageSequence::new(&directory, 1000., false).unwrap()));
+        for _ in 0..2 {
+            let frame = capture_frame(&mut source).unwrap();
+            assert_eq!(frame.size().unwrap().width, 64);
+            assert_eq!(frame.size().unwrap().height, 48);
+        }
+        assert!(capture_frame(&mut source).is_err());
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn capture_image_sequence_loop_test() {
+        let directory = write_image_sequence("vas_capture_image_sequence_loop", 2);
+        let mut source: Box<dyn CaptureSource + Send + 'static> = Box::new(
+            ImageSequenceCaptureSource::new(ImageSequence::new(&directory, 1000., true).unwrap()));
+        for _ in 0..5 {
+            assert!(capture_frame(&mut source).is_ok());
+        }
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+}
@@ -1,4 +1,39 @@
 #[cfg(test)]
 mod mic_controller_tests {
+    use anyhow::Result;
+    use crate::smart_speaker::models::intent_model::IntentAction;
+    use crate::smart_speaker::models::message_model::IntentContent;
+    use crate::smart_speaker::models::mic_model::{IntentEngine, SpeechToIntent};
     use super::super::mic_controller::*;
-}
\ No newline at end of file
+
+    /// Finalizes after a number of frames and always understands `next`.
+    struct MockIntentEngine {
+        frames_until_finalized: usize,
+    }
+
+    impl IntentEngine for MockIntentEngine {
+        fn info(&mut self) -> String {
+            "mock".to_string()
+        }
+
+        fn process(&mut self, _pcm: &Vec<i16>) -> Result<bool> {
+            self.frames_until_finalized = self.frames_until_finalized.saturating_sub(1);
+            Ok(self.frames_until_finalized == 0)
+        }
+
+        fn get_inference(&mut self) -> Result<Option<IntentContent>> {
+            Ok(Some(IntentContent::new(IntentAction::Next, vec![])))
+        }
+    }
+
+    #[test]
+    fn speech_to_intent_feed_test() {
+        let mut speech_to_intent = SpeechToIntent::new(Box::new(MockIntentEngine { frames_until_finalized: 3 }));
+        let frame = vec![0i16; 512];
+        assert!(!speech_to_intent_feed(&mut speech_to_intent, &frame).unwrap());
+        assert!(!speech_to_intent_feed(&mut speech_to_intent, &frame).unwrap());
+        assert!(speech_to_intent_feed(&mut speech_to_intent, &frame).unwrap());
+        let inference = speech_to_intent.get_inference().unwrap().unwrap();
+        assert_eq!(inference.intent, IntentAction::Next);
+    }
+}
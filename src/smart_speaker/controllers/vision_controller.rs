@@ -1,30 +1,14 @@
+use std::time::Duration;
 use anyhow::{anyhow, Result};
 use opencv::{objdetect, imgproc};
 use opencv::prelude::*;
 use opencv::core::{Vector, Size, Point2f, Point};
 use opencv::types::{VectorOfi32, VectorOfVectorOfPoint2f};
-use crate::smart_speaker::models::vision_model::{DetectableObject, VisionObjectShape, VisionObjectSize};
+use crate::smart_speaker::models::marker_model::{MarkerBoard, MarkerTag};
+use crate::smart_speaker::models::vision_model::{DetectableObject, DwellSelection, MarkerPresence, VisionMarker, VisionObjectShape, VisionObjectSize};
 use crate::utils::vision_util;
 
 
-pub(crate) fn data_bytes_to_mat(bytes: Vec<u8>, height: i32) -> Result<Mat> {
-    match Mat::from_slice(&bytes) {
-        Ok(mat) => {
-            match mat.reshape(3, height) {
-                Ok(mat) => {
-                    Ok(mat)
-                }
-                Err(_) => {
-                    Err(anyhow!("failed to reshape Mat"))
-                }
-            }
-        }
-        Err(_) => {
-            Err(anyhow!("failed to convert bytes to Mat"))
-        }
-    }
-}
-
 pub(crate) fn resize_frame(frame: Mat) -> Mat {
     let mut resized_frame = Mat::default();
     imgproc::resize(
@@ -98,37 +82,33 @@ pub(crate) fn detect_object_shape(object_contours: &VectorOfVectorOfPoint2f) ->
     Ok(shapes)
 }
 
-/// measure object size by aruco marker
-pub(crate) fn measure_object_size_by_aruco(aruco_corners: &VectorOfVectorOfPoint2f, object_contours: &VectorOfVectorOfPoint2f) -> Result<Vec<VisionObjectSize>> {
+/// measure object size by aruco marker.
+/// The contours are projected on the board plane, so the objects are assumed to lie on the plane of the markers.
+pub(crate) fn measure_object_size_by_aruco(aruco_corners: &VectorOfVectorOfPoint2f, aruco_ids: &Vector<i32>, board: &MarkerBoard,
+                                           object_contours: &VectorOfVectorOfPoint2f) -> Result<Vec<VisionObjectSize>> {
     let mut results: Vec<VisionObjectSize> = Vec::new();
-    let (width_ratios, height_ratios) = vision_util::get_measure_criteria_from_aruco(&aruco_corners)?;
-    let ratios = width_ratios.iter().zip(height_ratios.iter()).map(|(a, b)| a * b).collect::<Vec<f32>>();
+    let planes = vision_util::get_marker_planes(aruco_corners, aruco_ids, board)?;
+    if planes.is_empty() {
+        return Err(anyhow!("no marker to measure with"));
+    }
 
     for contour in object_contours {
-        let rect = vision_util::get_min_rect2f(&contour);
-        let mut points = [Point2f::default(); 4];
-        rect.points(&mut points).unwrap();
-        let width = vision_util::distance(&points[1].x, &points[1].y, &points[2].x, &points[2].y);
-        let height = vision_util::distance(&points[0].x, &points[0].y, &points[1].x, &points[1].y);
         let mut width_candidates: Vec<f32> = Vec::new();
         let mut height_candidates: Vec<f32> = Vec::new();
-        let mut perimeter_candidates: Vec<f32> = Vec::new();
-        for i in 0..ratios.len() {
-            let object_width = vision_util::pixel_to_metric(
-                width,
-                width_ratios.get(i).unwrap());
-            let object_height = vision_util::pixel_to_metric(
-                height,
-                height_ratios.get(i).unwrap());
-            perimeter_candidates.push(vision_util::approx_to_arch_length_metric(&contour, ratios.get(i).unwrap()));
-            width_candidates.push(object_width);
-            height_candidates.push(object_height);
+        let mut area_candidates: Vec<f32> = Vec::new();
+        for plane in planes.iter() {
+            let projected = vision_util::project_to_plane(&contour, plane)?;
+            let rect = vision_util::get_min_rect2f(&projected);
+            // the rect angle differs between the markers, so the longer side is the width
+            width_candidates.push(rect.size.width.max(rect.size.height));
+            height_candidates.push(rect.size.width.min(rect.size.height));
+            area_candidates.push(imgproc::contour_area(&projected, false)? as f32);
         }
         let object_width = width_candidates.iter().sum::<f32>() / width_candidates.len() as f32;
         let object_height = height_candidates.iter().sum::<f32>() / height_candidates.len() as f32;
-        let object_perimeter = perimeter_candidates.iter().sum::<f32>() / perimeter_candidates.len() as f32;
+        let object_area = area_candidates.iter().sum::<f32>() / area_candidates.len() as f32;
         results.push(VisionObjectSize::new(
-            object_perimeter,
+            object_area,
             object_width,
             object_height,
         ));
@@ -137,15 +117,18 @@ pub(crate) fn measure_object_size_by_aruco(aruco_corners: &VectorOfVectorOfPoint
     Ok(results)
 }
 
-pub(crate) fn detect_aruco(frame: &Mat) -> Result<(VectorOfVectorOfPoint2f, Vector<i32>)> {
-    let parameters = opencv::objdetect::DetectorParameters::default()?;
-    let dictionary = opencv::objdetect::get_predefined_dictionary(objdetect::PredefinedDictionaryType::DICT_4X4_50)?;
+/// Detector of the board dictionary. Build it once and reuse it for every frame.
+pub(crate) fn aruco_detector(board: &MarkerBoard) -> Result<objdetect::ArucoDetector> {
+    let parameters = objdetect::DetectorParameters::default()?;
+    Ok(objdetect::ArucoDetector::new(&board.dictionary.dictionary()?, &parameters,
+                                     objdetect::RefineParameters::new(10., 3., true)?)?)
+}
+
+pub(crate) fn detect_aruco(frame: &Mat, detector: &objdetect::ArucoDetector) -> Result<(VectorOfVectorOfPoint2f, Vector<i32>)> {
     let mut corners: VectorOfVectorOfPoint2f = Default::default();
     let mut rejected: VectorOfVectorOfPoint2f = Default::default();
     let mut ids = VectorOfi32::default();
-    let detector = objdetect::ArucoDetector::new(&dictionary, &parameters,
-                                                 objdetect::RefineParameters::new(10., 3., true).unwrap())?;
-    detector.detect_markers(frame, &mut corners, &mut ids, &mut rejected).expect("TODO: panic message");
+    detector.detect_markers(frame, &mut corners, &mut ids, &mut rejected)?;
     Ok((corners, ids))
 }
 
@@ -169,3 +152,92 @@ pub(crate) fn find_nearest_object_from_gaze(gaze_as_px: &(f32, f32), objects: &V
     let nearest_distance = nearest_distances.get(nearest_index).unwrap().clone();
     Ok((nearest_index, nearest_distance))
 }
+
+/// The object that contains the gaze point in pixels, or the nearest object within `max_distance` pixels.
+fn gazed_object((x, y): (f32, f32), objects: &Vector<Vector<Point2f>>, max_distance: f32) -> Result<Option<usize>> {
+    let mut nearest: Option<(usize, f64)> = None;
+    for (i, object) in objects.iter().enumerate() {
+        // positive inside the contour, negative outside
+        let distance = imgproc::point_polygon_test(&object, Point2f::new(x, y), true)?;
+        match nearest {
+            Some((_, nearest_distance)) if nearest_distance >= distance => {}
+            _ => nearest = Some((i, distance)),
+        }
+    }
+    Ok(nearest.filter(|(_, distance)| *distance >= -(max_distance as f64)).map(|(i, _)| i))
+}
+
+/// Gaze dwell on each object. A gaze point in pixels counts for its duration on the object that contains it,
+/// or on the nearest object within `max_distance` pixels.
+pub(crate) fn accumulate_gaze_dwell(gaze_points: &[((f32, f32), Duration)], objects: &Vector<Vector<Point2f>>, max_distance: f32) -> Result<Vec<Duration>> {
+    let mut dwell = vec![Duration::ZERO; objects.len()];
+    for (point, duration) in gaze_points {
+        if let Some(i) = gazed_object(*point, objects, max_distance)? {
+            dwell[i] += *duration;
+        }
+    }
+    Ok(dwell)
+}
+
+/// The object of the latest gaze point that fell on one, as `accumulate_gaze_dwell` counts it. The gaze points are oldest first.
+pub(crate) fn last_gazed_object(gaze_points: &[((f32, f32), Duration)], objects: &Vector<Vector<Point2f>>, max_distance: f32) -> Result<Option<usize>> {
+    for (point, _) in gaze_points.iter().rev() {
+        if let Some(i) = gazed_object(*point, objects, max_distance)? {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+/// The object with the longest dwell. None if no object was looked at.
+/// Unsure if the longest dwell is shorter than `min_dwell`. If other objects have at least `tie_ratio` of it,
+/// the tie goes to `last_gazed`, the object looked at last, and is unsure if that is none of them.
+pub(crate) fn select_by_dwell(dwell: &[Duration], last_gazed: Option<usize>, min_dwell: Duration, tie_ratio: f32) -> DwellSelection {
+    let mut order = (0..dwell.len()).filter(|i| !dwell[*i].is_zero()).collect::<Vec<usize>>();
+    order.sort_by(|a, b| dwell[*b].cmp(&dwell[*a]));
+    match order.first() {
+        None => DwellSelection::None,
+        Some(&longest) if dwell[longest] >= min_dwell => {
+            let tie = dwell[longest].mul_f32(tie_ratio);
+            let candidates = order.into_iter().take_while(|i| dwell[*i] >= tie).collect::<Vec<usize>>();
+            match last_gazed {
+                _ if candidates.len() == 1 => DwellSelection::Selected(longest),
+                Some(last) if candidates.contains(&last) => DwellSelection::Selected(last),
+                _ => DwellSelection::Unsure(candidates),
+            }
+        }
+        _ => DwellSelection::Unsure(order),
+    }
+}
+
+/// Presence of every tag of the board, in the order of `MarkerBoard::tags`.
+/// A tag is present if one of its markers is in any of `recent_ids`, and near the gaze if the gaze stayed
+/// within `max_distance` of its markers in the latest frame for at least `min_dwell`.
+pub(crate) fn tagged_marker_presence(board: &MarkerBoard, recent_ids: &[Vector<i32>], aruco_corners: &VectorOfVectorOfPoint2f, aruco_ids: &Vector<i32>,
+                                     gaze_points: &[((f32, f32), Duration)], max_distance: f32, min_dwell: Duration) -> Result<Vec<VisionMarker>> {
+    let mut tagged_corners = VectorOfVectorOfPoint2f::new();
+    let mut corner_tags: Vec<MarkerTag> = vec![];
+    for (corners, id) in aruco_corners.iter().zip(aruco_ids.iter()) {
+        if let Some(tag) = board.tag(id) {
+            tagged_corners.push(corners);
+            corner_tags.push(tag);
+        }
+    }
+    let dwell = accumulate_gaze_dwell(gaze_points, &tagged_corners, max_distance)?;
+    Ok(board.tags().into_iter().map(|tag| {
+        // a tray may carry several markers of the same tag
+        let tag_dwell = corner_tags.iter().zip(dwell.iter())
+            .filter(|(corner_tag, _)| **corner_tag == tag)
+            .map(|(_, dwell)| *dwell)
+            .sum::<Duration>();
+        let seen = recent_ids.iter().any(|ids| ids.iter().any(|id| board.tag(id) == Some(tag)));
+        let presence = if seen && tag_dwell >= min_dwell {
+            MarkerPresence::NearGaze
+        } else if seen {
+            MarkerPresence::Present
+        } else {
+            MarkerPresence::Missing
+        };
+        VisionMarker::new(tag, presence)
+    }).collect())
+}
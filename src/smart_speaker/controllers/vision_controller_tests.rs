@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod vision_controller_tests {
+    use std::time::Duration;
+    use opencv::core::{Mat, Point2f, Vector};
+    use crate::smart_speaker::models::marker_model::{ArucoDictionary, BoardMarker, IngredientMarker, MarkerBoard, MarkerTag, ToolMarker};
+    use crate::smart_speaker::models::vision_model::{DwellSelection, MarkerPresence, VisionMarker};
+    use crate::utils::vision_util;
+    use super::super::vision_controller::*;
+
+    fn square(x: f32, y: f32, size: f32) -> Vector<Point2f> {
+        Vector::from_iter([
+            Point2f::new(x, y),
+            Point2f::new(x + size, y),
+            Point2f::new(x + size, y + size),
+            Point2f::new(x, y + size),
+        ])
+    }
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn accumulate_gaze_dwell_test() {
+        let objects = Vector::<Vector<Point2f>>::from_iter([square(0., 0., 100.), square(200., 0., 100.)]);
+        let gaze_points = vec![
+            ((50., 50.), ms(30)),
+            ((250., 50.), ms(30)),
+            ((260., 60.), ms(30)),
+            // just outside the second object
+            ((310., 50.), ms(30)),
+            // far from both
+            ((150., 400.), ms(30)),
+        ];
+        let dwell = accumulate_gaze_dwell(&gaze_points, &objects, 30.).unwrap();
+        assert_eq!(dwell, vec![ms(30), ms(90)]);
+    }
+
+    #[test]
+    fn select_by_dwell_test() {
+        assert_eq!(select_by_dwell(&[ms(100), ms(600), ms(200)], None, ms(300), 0.8), DwellSelection::Selected(1));
+        // two objects looked at about as long
+        assert_eq!(select_by_dwell(&[ms(550), ms(600), ms(100)], None, ms(300), 0.8), DwellSelection::Unsure(vec![1, 0]));
+        assert_eq!(select_by_dwell(&[ms(550), ms(600), ms(100)], Some(2), ms(300), 0.8), DwellSelection::Unsure(vec![1, 0]));
+        // the tie goes to the object looked at last
+        assert_eq!(select_by_dwell(&[ms(550), ms(600), ms(100)], Some(0), ms(300), 0.8), DwellSelection::Selected(0));
+        // nothing looked at long enough
+        assert_eq!(select_by_dwell(&[ms(100), ms(0), ms(200)], Some(2), ms(300), 0.8), DwellSelection::Unsure(vec![2, 0]));
+        // nothing looked at
+        assert_eq!(select_by_dwell(&[ms(0), ms(0)], None, ms(300), 0.8), DwellSelection::None);
+        assert_eq!(select_by_dwell(&[], None, ms(300), 0.8), DwellSelection::None);
+    }
+
+    #[test]
+    fn last_gazed_object_test() {
+        let objects = Vector::<Vector<Point2f>>::from_iter([square(0., 0., 100.), square(200., 0., 100.)]);
+        let gaze_points = vec![((250., 50.), ms(30)), ((50., 50.), ms(30)), ((150., 400.), ms(30))];
+        // the last point is far from both
+        assert_eq!(last_gazed_object(&gaze_points, &objects, 30.).unwrap(), Some(0));
+        assert_eq!(last_gazed_object(&gaze_points[..1], &objects, 30.).unwrap(), Some(1));
+        assert_eq!(last_gazed_object(&gaze_points[2..], &objects, 30.).unwrap(), None);
+    }
+
+    /// Points on the table in cm as a camera looking down at an angle sees them.
+    fn tilted_view(points: &[(f32, f32)]) -> Vector<Point2f> {
+        let homography = Mat::from_slice_2d(&[[20., 4., 100.], [0., 12., 80.], [0., 0.01, 1.]]).unwrap();
+        let mut projected = Vector::<Point2f>::new();
+        opencv::core::perspective_transform(&points.iter().map(|(x, y)| Point2f::new(*x, *y)).collect::<Vector<Point2f>>(),
+                                            &mut projected, &homography).unwrap();
+        projected
+    }
+
+    fn marker_at(x: f32, y: f32, side: f32) -> Vector<Point2f> {
+        tilted_view(&[(x, y), (x + side, y), (x + side, y + side), (x, y + side)])
+    }
+
+    #[test]
+    fn measure_object_size_by_aruco_test() {
+        let board = MarkerBoard::default();
+        let markers = Vector::<Vector<Point2f>>::from_iter([marker_at(0., 0., 3.8), marker_at(30., 2., 3.8)]);
+        // 10 x 5 cm, farther from the camera than the markers
+        let objects = Vector::<Vector<Point2f>>::from_iter([tilted_view(&[(8., 20.), (18., 20.), (18., 25.), (8., 25.)])]);
+        let size = measure_object_size_by_aruco(&markers, &Vector::from_iter([3, 7]), &board, &objects).unwrap();
+        assert_eq!(size.len(), 1);
+        assert!((size[0].width - 10.).abs() < 0.05);
+        assert!((size[0].height - 5.).abs() < 0.05);
+        assert!((size[0].area - 50.).abs() < 0.5);
+        // no scale without markers
+        assert!(measure_object_size_by_aruco(&Vector::new(), &Vector::new(), &board, &objects).is_err());
+    }
+
+    #[test]
+    fn measure_object_size_by_board_test() {
+        let board = MarkerBoard::new(ArucoDictionary::default(), 38., vec![
+            BoardMarker { id: 0, size_mm: None, position_mm: Some([0., 0.]), tag: None },
+            BoardMarker { id: 1, size_mm: Some(50.), position_mm: Some([250., 0.]), tag: None },
+            BoardMarker { id: 2, size_mm: Some(60.), position_mm: None, tag: None },
+        ]).unwrap();
+        // the board is seen shifted on the table, which does not change the lengths
+        let markers = Vector::<Vector<Point2f>>::from_iter([marker_at(2., 1., 3.8), marker_at(27., 1., 5.), marker_at(10., 30., 6.)]);
+        let objects = Vector::<Vector<Point2f>>::from_iter([tilted_view(&[(8., 20.), (20., 20.), (20., 24.), (8., 24.)])]);
+        let size = measure_object_size_by_aruco(&markers, &Vector::from_iter([0, 1, 2]), &board, &objects).unwrap();
+        assert!((size[0].width - 12.).abs() < 0.05);
+        assert!((size[0].height - 4.).abs() < 0.05);
+        // a marker of another size measured as the default size would scale the object
+        let unsized_board = MarkerBoard::default();
+        let size = measure_object_size_by_aruco(&Vector::from_iter([marker_at(10., 30., 6.)]), &Vector::from_iter([2]), &unsized_board, &objects).unwrap();
+        assert!((size[0].width - 12. * 3.8 / 6.).abs() < 0.05);
+    }
+
+    #[test]
+    fn marker_planes_skip_tagged_test() {
+        let board = MarkerBoard::new(ArucoDictionary::default(), 38., vec![
+            BoardMarker { id: 11, size_mm: None, position_mm: None, tag: Some(MarkerTag::Ingredient(IngredientMarker::Carrot)) },
+        ]).unwrap();
+        // the tray marker is tilted on the tray and would give a wrong scale
+        let markers = Vector::<Vector<Point2f>>::from_iter([marker_at(0., 0., 3.8), marker_at(30., 2., 2.)]);
+        let ids = Vector::from_iter([3, 11]);
+        assert_eq!(vision_util::get_marker_planes(&markers, &ids, &board).unwrap().len(), 1);
+        let objects = Vector::<Vector<Point2f>>::from_iter([tilted_view(&[(8., 20.), (18., 20.), (18., 25.), (8., 25.)])]);
+        let size = measure_object_size_by_aruco(&markers, &ids, &board, &objects).unwrap();
+        assert!((size[0].width - 10.).abs() < 0.05);
+        assert!((size[0].height - 5.).abs() < 0.05);
+    }
+
+    #[test]
+    fn tagged_marker_presence_test() {
+        let carrot = MarkerTag::Ingredient(IngredientMarker::Carrot);
+        let potato = MarkerTag::Ingredient(IngredientMarker::Potato);
+        let knife = MarkerTag::Tool(ToolMarker::Knife);
+        let tagged = |id: i32, tag: MarkerTag| BoardMarker { id, size_mm: None, position_mm: None, tag: Some(tag) };
+        let board = MarkerBoard::new(ArucoDictionary::default(), 38., vec![
+            BoardMarker { id: 0, size_mm: None, position_mm: Some([0., 0.]), tag: None },
+            tagged(11, carrot),
+            // both ends of the carrot tray
+            tagged(12, carrot),
+            tagged(13, potato),
+            tagged(14, knife),
+        ]).unwrap();
+        // the latest frame shows the board marker and the carrot tray, the knife was seen a moment ago
+        let corners = Vector::<Vector<Point2f>>::from_iter([square(0., 0., 40.), square(200., 200., 40.), square(400., 200., 40.)]);
+        let ids = Vector::from_iter([0, 11, 12]);
+        let recent_ids = vec![Vector::from_iter([0, 14]), ids.clone()];
+        // the gaze stays on the tray between its markers, split between the two
+        let gaze_points = vec![((300., 220.), ms(200)), ((330., 220.), ms(200))];
+        let presence = tagged_marker_presence(&board, &recent_ids, &corners, &ids, &gaze_points, 100., ms(300)).unwrap();
+        assert_eq!(presence, vec![
+            VisionMarker::new(carrot, MarkerPresence::NearGaze),
+            VisionMarker::new(potato, MarkerPresence::Missing),
+            VisionMarker::new(knife, MarkerPresence::Present),
+        ]);
+        // without gaze the tray is only present
+        let presence = tagged_marker_presence(&board, &recent_ids, &corners, &ids, &[], 100., ms(300)).unwrap();
+        assert_eq!(presence[0], VisionMarker::new(carrot, MarkerPresence::Present));
+    }
+}
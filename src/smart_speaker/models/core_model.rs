@@ -1,6 +1,46 @@
 use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use crate::smart_speaker::models::message_model::SmartSpeakerActors;
 use crate::smart_speaker::models::vision_model::VisionAction;
 
+/// How often actors report that their loop is alive.
+pub(crate) const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+/// An actor that has sent a heartbeat before and is silent this long is treated as hung.
+pub(crate) const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the core actor runs the debug window and the macOS run loop.
+pub(crate) const UI_LOOP_INTERVAL: Duration = Duration::from_millis(16);
+
+/// What the core actor does when an actor panics or stops sending heartbeats.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum RestartPolicy {
+    /// Start it again, up to the given times. After that the actor is degraded.
+    Restart(u32),
+    /// Shut the smart speaker down.
+    Escalate,
+    /// Run on without it.
+    Degrade,
+}
+
+impl RestartPolicy {
+    pub(crate) fn of(actor: &SmartSpeakerActors) -> Self {
+        match actor {
+            // the dialogue state lives here and typed input owns stdin. a new one can not take over.
+            SmartSpeakerActors::ContextActor => RestartPolicy::Escalate,
+            SmartSpeakerActors::InputActor => RestartPolicy::Escalate,
+            SmartSpeakerActors::AudioActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::LoggerActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::MachineSpeechActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::WakeWordActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::VoiceActivityDetectActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::SpeechToIntentActor => RestartPolicy::Restart(3),
+            SmartSpeakerActors::CameraActor => RestartPolicy::Restart(2),
+            SmartSpeakerActors::GazeActor => RestartPolicy::Restart(2),
+            SmartSpeakerActors::VisionActor => RestartPolicy::Restart(2),
+            _ => RestartPolicy::Degrade,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum SmartSpeakerState {
     Idle,
@@ -1,20 +1,28 @@
 use std::ops::Mul;
 use opencv::{prelude::*, highgui, core::Point2f, imgproc};
-use opencv::core::{Point, Scalar};
+use opencv::core::{Point, Scalar, Vector};
+use opencv::objdetect::ArucoDetector;
 use crate::smart_speaker::controllers::vision_controller;
 use crate::smart_speaker::controllers::debug_controller;
 use crate::smart_speaker::models::core_model::{SmartSpeakerState, WaitingInteraction};
+use crate::smart_speaker::models::frame_model::SharedFrame;
+use crate::smart_speaker::models::gaze_model::Fixation;
+use crate::smart_speaker::models::marker_model::MarkerBoard;
 use crate::smart_speaker::models::message_model::SmartSpeakerActors;
 use crate::smart_speaker::models::vision_model;
 use crate::utils::vision_util;
 
 pub(crate) struct DebugData {
     pub activated: bool,
-    pub frame: Option<Mat>,
+    pub frame: Option<SharedFrame>,
     pub gaze_x: f32,
     pub gaze_y: f32,
     pub gaze_as_px: (i32, i32),
+    pub fixation: Option<Fixation>,
     pub state: (SmartSpeakerState, SmartSpeakerActors),
+    pub marker_board: MarkerBoard,
+    /// Built on the first frame.
+    aruco_detector: Option<ArucoDetector>,
 }
 
 impl DebugData {
@@ -25,10 +33,19 @@ impl DebugData {
             gaze_x: 0.,
             gaze_y: 0.,
             gaze_as_px: (0, 0),
+            fixation: None,
             state: (SmartSpeakerState::Idle, SmartSpeakerActors::CoreActor),
+            marker_board: MarkerBoard::default(),
+            aruco_detector: None,
         }
     }
 
+    pub(crate) fn with_marker_board(mut self, marker_board: MarkerBoard) -> Self {
+        self.marker_board = marker_board;
+        self.aruco_detector = None;
+        self
+    }
+
     pub(crate) fn force_cocoa_loop(&self) {
         // Force to create a frame to display (for TTS callback)
         let display_frame = Mat::new_rows_cols_with_default(480, 640, opencv::core::CV_8UC3, opencv::core::Scalar::all(0.)).unwrap();
@@ -87,8 +104,15 @@ impl DebugData {
 
     pub(crate) fn print(&mut self) {
         let verbose = false;
+        if self.aruco_detector.is_none() {
+            self.aruco_detector = Some(vision_controller::aruco_detector(&self.marker_board).unwrap());
+        }
+        let aruco_detector = self.aruco_detector.as_ref().unwrap();
         match &self.frame {
             Some(frame) => {
+                let header = frame.header().clone();
+                let view = frame.view_bgr().unwrap();
+                let frame: &Mat = &view;
                 let mut display_frame: Mat = Default::default();
                 frame.copy_to(&mut display_frame).unwrap();
                 self.gaze_as_px = vision_util::gaze_to_px(&(self.gaze_x, self.gaze_y), &(frame.cols(), frame.rows()));
@@ -107,28 +131,27 @@ impl DebugData {
                     }
                 }
                 debug_controller::write_text_to_mat(&mut display_frame, &format!("Gaze: ({}, {})", self.gaze_x, self.gaze_y), 10, 20);
+                if let Some(fixation) = &self.fixation {
+                    debug_controller::write_text_to_mat(&mut display_frame, &format!("Fixation: ({:.2}, {:.2}) {} ms", fixation.x, fixation.y, fixation.duration.as_millis()), 10, 80);
+                }
+                debug_controller::write_text_to_mat(&mut display_frame, &format!("Frame: #{} {}x{} {} {:?}", header.sequence, header.width, header.height, header.format, header.source), 10, 60);
                 debug_controller::draw_circle_to_mat(&mut display_frame, self.gaze_as_px.0, self.gaze_as_px.1);
 
                 // Begin debug for object detection
-                let (aruco_contours, aruco_index) = vision_controller::detect_aruco(frame).unwrap();
-                let (width_ratios, height_ratios) = vision_util::get_measure_criteria_from_aruco(&aruco_contours).unwrap();
+                let (aruco_contours, aruco_index) = vision_controller::detect_aruco(frame, aruco_detector).unwrap();
 
                 // For ArUco debug print
                 debug_controller::draw_aruco(&mut display_frame, &aruco_contours, &aruco_index);
                 if verbose {
                     for i in 0..aruco_contours.len() {
                         let square = vision_util::get_min_rect2f(&aruco_contours.get(i).unwrap());
-                        let mut points = [Point2f::default(); 4];
-                        square.points(&mut points).unwrap();
-                        let width = vision_util::distance(&points[1].x, &points[1].y, &points[2].x, &points[2].y);
-                        let height = vision_util::distance(&points[0].x, &points[0].y, &points[1].x, &points[1].y);
-                        let points_width = vision_util::pixel_to_metric(
-                            width,
-                            &width_ratios.get(i).unwrap());
-                        let points_height = vision_util::pixel_to_metric(
-                            height,
-                            &height_ratios.get(i).unwrap());
-                        debug_controller::write_text_to_mat(&mut display_frame, &format!("{}: ({:.1}x{:.1}) cm", aruco_index.get(i).unwrap() as u32, points_width, points_height), square.center.x as i32, square.center.y as i32 + 20 );
+                        let id = aruco_index.get(i).unwrap();
+                        let placed = match (self.marker_board.marker(id).and_then(|marker| marker.position_mm), self.marker_board.tag(id)) {
+                            (Some(_), _) => " on board".to_string(),
+                            (None, Some(tag)) => format!(" {}", tag),
+                            (None, None) => "".to_string(),
+                        };
+                        debug_controller::write_text_to_mat(&mut display_frame, &format!("{}: {:.0} mm{}", id, self.marker_board.marker_mm(id), placed), square.center.x as i32, square.center.y as i32 + 20 );
                     }
 
 
@@ -139,7 +162,7 @@ impl DebugData {
                         let shapes = vision_controller::detect_object_shape(&objects).unwrap();
                         debug_controller::write_text_to_mat(&mut display_frame, &format!("Contour: {}", &objects.len()), 10, 60);
 
-                        match vision_controller::measure_object_size_by_aruco(&aruco_contours, &objects) {
+                        match vision_controller::measure_object_size_by_aruco(&aruco_contours, &aruco_index, &self.marker_board, &objects) {
                             Ok(measure_result) => {
                                 if measure_result.len() > 0 {
                                     for i in 0..measure_result.len() {
@@ -150,21 +173,20 @@ impl DebugData {
                                         debug_controller::draw_rotated_rect_to_mat(&mut display_frame, &rect);
                                         debug_controller::draw_approx_poly_to_mat(&mut display_frame, &objects.get(i).unwrap());
                                         debug_controller::draw_approx_poly_to_mat(&mut display_frame, &shape_poly);
-                                        debug_controller::write_text_to_mat(&mut display_frame, &format!("Object: {:.1} cm^2 ({:.1}x{:.1}) cm\nShape: {}", object_size.perimeter, object_size.width, object_size.height, &shapes.get(i).unwrap().to_i18n().en), rect.center.x as i32, rect.center.y as i32 + 20 );
+                                        debug_controller::write_text_to_mat(&mut display_frame, &format!("Object: {:.1} cm^2 ({:.1}x{:.1}) cm\nShape: {}", object_size.area, object_size.width, object_size.height, &shapes.get(i).unwrap().to_i18n().en), rect.center.x as i32, rect.center.y as i32 + 20 );
                                     }
                                     if aruco_index.len() > 0 {
-                                        let (width_ratios, height_ratios) = vision_util::get_measure_criteria_from_aruco(&aruco_contours).unwrap();
-                                        let ratios = width_ratios.iter().zip(height_ratios.iter()).map(|(a, b)| a * b).collect::<Vec<f32>>();
+                                        let planes = vision_util::get_marker_planes(&aruco_contours, &aruco_index, &self.marker_board).unwrap();
                                         let gaze_as_pxf = vision_util::gaze_to_pxf(&(self.gaze_x, self.gaze_y), &(frame.cols(), frame.rows()));
                                         let nearest_info = vision_controller::find_nearest_object_from_gaze(&(gaze_as_pxf.0, gaze_as_pxf.1), &objects).unwrap();
+                                        let rect = vision_util::get_min_rect2f(&objects.get(nearest_info.0).unwrap());
                                         let mut distance_candidates = vec![];
-                                        for r in ratios {
-                                            distance_candidates.push(vision_util::pixel_to_metric(
-                                                nearest_info.1,
-                                                &r));
+                                        for plane in planes.iter() {
+                                            let points = vision_util::project_to_plane(&Vector::from_iter([Point2f::new(gaze_as_pxf.0, gaze_as_pxf.1), rect.center]), plane).unwrap();
+                                            let (gaze, center) = (points.get(0).unwrap(), points.get(1).unwrap());
+                                            distance_candidates.push(vision_util::distance(&gaze.x, &gaze.y, &center.x, &center.y));
                                         }
                                         let distance_as_metric = distance_candidates.iter().sum::<f32>() / distance_candidates.len() as f32;
-                                        let rect = vision_util::get_min_rect2f(&objects.get(nearest_info.0).unwrap());
                                         debug_controller::draw_line_to_mat(&mut display_frame, self.gaze_as_px.0, self.gaze_as_px.1, rect.center.x as i32, rect.center.y as i32);
                                         debug_controller::draw_rotated_rect_to_mat(&mut display_frame, &rect);
                                         debug_controller::write_text_to_mat(&mut display_frame, &format!("Nearest from gaze: {} cm", distance_as_metric), rect.center.x as i32, rect.center.y as i32 + 50 );
@@ -193,11 +215,13 @@ impl DebugData {
         }
     }
 
-    pub(crate) fn update_frame(&mut self, frame_data_bytes: &Vec<u8>, height: &i32) {
-        if let Ok(frame) = vision_controller::data_bytes_to_mat(frame_data_bytes.clone(), height.clone()) {
-            // let frame = vision_controller::resize_frame(frame);
-            self.frame = Some(frame);
-        }
+    pub(crate) fn update_frame(&mut self, frame: &SharedFrame) {
+        // let frame = vision_controller::resize_frame(frame);
+        self.frame = Some(frame.clone());
+    }
+
+    pub(crate) fn update_fixation(&mut self, fixation: &Option<Fixation>) {
+        self.fixation = fixation.clone();
     }
 
     pub(crate) fn update_gaze_info(&mut self, (gaze_x, gaze_y): &(f32, f32)) {
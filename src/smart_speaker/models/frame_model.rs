@@ -0,0 +1,216 @@
+use std::ffi::c_void;
+use std::fmt::{Debug, Display, Formatter};
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use anyhow::{anyhow, Result};
+use opencv::core::{Mat, CV_8U, CV_8UC1, CV_8UC3, CV_8UC4};
+use opencv::imgproc;
+use opencv::prelude::*;
+use crate::utils::vision_util::VisionType;
+
+/// Layout of the 8 bit pixels of a frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PixelFormat {
+    Gray,
+    Bgr,
+    Rgb,
+    Bgra,
+}
+
+impl PixelFormat {
+    pub(crate) fn channels(&self) -> i32 {
+        match self {
+            PixelFormat::Gray => 1,
+            PixelFormat::Bgr | PixelFormat::Rgb => 3,
+            PixelFormat::Bgra => 4,
+        }
+    }
+
+    pub(crate) fn mat_type(&self) -> i32 {
+        match self.channels() {
+            1 => CV_8UC1,
+            3 => CV_8UC3,
+            _ => CV_8UC4,
+        }
+    }
+
+    /// Color conversion to BGR, which the vision pipeline expects. None if the pixels are already BGR.
+    fn bgr_conversion(&self) -> Option<i32> {
+        match self {
+            PixelFormat::Gray => Some(imgproc::COLOR_GRAY2BGR),
+            PixelFormat::Bgr => None,
+            PixelFormat::Rgb => Some(imgproc::COLOR_RGB2BGR),
+            PixelFormat::Bgra => Some(imgproc::COLOR_BGRA2BGR),
+        }
+    }
+}
+
+impl FromStr for PixelFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "gray" | "grey" => Ok(PixelFormat::Gray),
+            "bgr" => Ok(PixelFormat::Bgr),
+            "rgb" => Ok(PixelFormat::Rgb),
+            "bgra" => Ok(PixelFormat::Bgra),
+            _ => Err(anyhow!("unsupported pixel format: {}", s)),
+        }
+    }
+}
+
+impl Display for PixelFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PixelFormat::Gray => write!(f, "gray"),
+            PixelFormat::Bgr => write!(f, "bgr"),
+            PixelFormat::Rgb => write!(f, "rgb"),
+            PixelFormat::Bgra => write!(f, "bgra"),
+        }
+    }
+}
+
+/// What a frame is and when it was captured.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FrameHeader {
+    pub width: i32,
+    pub height: i32,
+    pub channels: i32,
+    pub format: PixelFormat,
+    pub source: VisionType,
+    /// Counts the frames of a capture from 1.
+    pub sequence: u64,
+    /// Monotonic time when the frame was read from the source.
+    pub captured_at: Instant,
+    /// Clock of the source in seconds, such as the pupil time. None if the source has no clock.
+    pub source_timestamp: Option<f64>,
+}
+
+impl FrameHeader {
+    pub(crate) fn new(width: i32, height: i32, format: PixelFormat, source: VisionType, sequence: u64, source_timestamp: Option<f64>) -> Self {
+        Self {
+            width,
+            height,
+            channels: format.channels(),
+            format,
+            source,
+            sequence,
+            captured_at: Instant::now(),
+            source_timestamp,
+        }
+    }
+
+    /// Bytes of the pixels without padding.
+    pub(crate) fn data_len(&self) -> usize {
+        self.width.max(0) as usize * self.height.max(0) as usize * self.channels as usize
+    }
+}
+
+struct FrameBuffer {
+    header: FrameHeader,
+    data: Vec<u8>,
+}
+
+/// Handle of a camera frame shared between actors.
+/// Cloning the handle shares the pixels. They are freed together with the last handle.
+#[derive(Clone)]
+pub(crate) struct SharedFrame {
+    buffer: Arc<FrameBuffer>,
+}
+
+impl SharedFrame {
+    /// Copies the pixels of `mat` once. The size in `header` is taken from `mat`.
+    pub(crate) fn from_mat(mut header: FrameHeader, mat: &Mat) -> Result<Self> {
+        if mat.depth() != CV_8U || mat.channels() != header.channels {
+            return Err(anyhow!("frame does not match {} format", header.format));
+        }
+        let continuous = if mat.is_continuous() { None } else { Some(mat.try_clone()?) };
+        let mat = continuous.as_ref().unwrap_or(mat);
+        header.width = mat.cols();
+        header.height = mat.rows();
+        Self::from_bytes(header, mat.data_bytes()?.to_vec())
+    }
+
+    pub(crate) fn from_bytes(header: FrameHeader, data: Vec<u8>) -> Result<Self> {
+        if header.width <= 0 || header.height <= 0 || data.len() != header.data_len() {
+            return Err(anyhow!("invalid frame: {} bytes for {}x{} {}", data.len(), header.width, header.height, header.format));
+        }
+        Ok(Self {
+            buffer: Arc::new(FrameBuffer { header, data }),
+        })
+    }
+
+    pub(crate) fn header(&self) -> &FrameHeader {
+        &self.buffer.header
+    }
+
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.buffer.data
+    }
+
+    /// Number of handles that share this frame.
+    pub(crate) fn handles(&self) -> usize {
+        Arc::strong_count(&self.buffer)
+    }
+
+    /// Mat header over the shared pixels. Nothing is copied.
+    pub(crate) fn view(&self) -> Result<FrameView<'_>> {
+        let header = self.header();
+        let step = header.width as usize * header.channels as usize;
+        // the header only reads the pixels and does not outlive the borrow of the buffer.
+        let mat = unsafe {
+            Mat::new_rows_cols_with_data(header.height, header.width, header.format.mat_type(),
+                                         self.buffer.data.as_ptr() as *mut c_void, step)?
+        };
+        Ok(FrameView {
+            mat,
+            _frame: PhantomData,
+        })
+    }
+
+    /// Same as `view` for BGR frames. Other formats are converted into a new Mat.
+    pub(crate) fn view_bgr(&self) -> Result<FrameView<'_>> {
+        let view = self.view()?;
+        match self.header().format.bgr_conversion() {
+            None => Ok(view),
+            Some(code) => {
+                let mut mat = Mat::default();
+                imgproc::cvt_color(&*view, &mut mat, code, 0)?;
+                Ok(FrameView {
+                    mat,
+                    _frame: PhantomData,
+                })
+            }
+        }
+    }
+}
+
+impl Debug for SharedFrame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let header = self.header();
+        write!(f, "SharedFrame(#{} {}x{} {} from {:?})", header.sequence, header.width, header.height, header.format, header.source)
+    }
+}
+
+impl PartialEq for SharedFrame {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.buffer, &other.buffer) || (self.header() == other.header() && self.data() == other.data())
+    }
+}
+
+/// Read-only Mat borrowed from a `SharedFrame`.
+pub(crate) struct FrameView<'a> {
+    mat: Mat,
+    _frame: PhantomData<&'a SharedFrame>,
+}
+
+impl Deref for FrameView<'_> {
+    type Target = Mat;
+
+    fn deref(&self) -> &Mat {
+        &self.mat
+    }
+}
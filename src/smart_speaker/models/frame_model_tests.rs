@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod frame_model_tests {
+    use std::str::FromStr;
+    use opencv::core::{Mat, Scalar, Vec3b, CV_8UC1, CV_8UC3};
+    use opencv::prelude::*;
+    use crate::utils::vision_util::VisionType;
+    use super::super::frame_model::*;
+
+    fn header(width: i32, height: i32, format: PixelFormat, sequence: u64) -> FrameHeader {
+        FrameHeader::new(width, height, format, VisionType::BuiltInCamera, sequence, None)
+    }
+
+    #[test]
+    fn pixel_format_test() {
+        assert_eq!(PixelFormat::from_str("BGR").unwrap(), PixelFormat::Bgr);
+        assert_eq!(PixelFormat::from_str("grey").unwrap(), PixelFormat::Gray);
+        assert_eq!(PixelFormat::from_str("bgra").unwrap(), PixelFormat::Bgra);
+        assert!(PixelFormat::from_str("yuyv").is_err());
+        assert_eq!(PixelFormat::Gray.channels(), 1);
+        assert_eq!(PixelFormat::Rgb.channels(), 3);
+        assert_eq!(PixelFormat::Bgra.channels(), 4);
+        assert_eq!(PixelFormat::Rgb.to_string(), "rgb");
+    }
+
+    #[test]
+    fn shared_frame_view_test() {
+        let mat = Mat::new_rows_cols_with_default(4, 6, CV_8UC3, Scalar::new(1., 2., 3., 0.)).unwrap();
+        // the size is taken from the mat
+        let frame = SharedFrame::from_mat(header(0, 0, PixelFormat::Bgr, 7), &mat).unwrap();
+        assert_eq!(frame.header().sequence, 7);
+        assert_eq!((frame.header().width, frame.header().height, frame.header().channels), (6, 4, 3));
+        assert_eq!(frame.data().len(), 6 * 4 * 3);
+        let view = frame.view().unwrap();
+        assert_eq!(view.typ(), CV_8UC3);
+        assert_eq!(*view.at_2d::<Vec3b>(3, 5).unwrap(), Vec3b::from([1, 2, 3]));
+        // the view reads the shared pixels
+        assert_eq!(view.data(), frame.data().as_ptr());
+        assert_eq!(frame.view_bgr().unwrap().data(), frame.data().as_ptr());
+        assert!(SharedFrame::from_mat(header(0, 0, PixelFormat::Gray, 8), &mat).is_err());
+    }
+
+    #[test]
+    fn shared_frame_convert_test() {
+        let frame = SharedFrame::from_bytes(header(2, 1, PixelFormat::Gray, 1), vec![10, 20]).unwrap();
+        let view = frame.view().unwrap();
+        assert_eq!(view.typ(), CV_8UC1);
+        let bgr = frame.view_bgr().unwrap();
+        assert_eq!(bgr.typ(), CV_8UC3);
+        assert_eq!(*bgr.at_2d::<Vec3b>(0, 1).unwrap(), Vec3b::from([20, 20, 20]));
+        let frame = SharedFrame::from_bytes(header(1, 1, PixelFormat::Rgb, 2), vec![1, 2, 3]).unwrap();
+        assert_eq!(*frame.view_bgr().unwrap().at_2d::<Vec3b>(0, 0).unwrap(), Vec3b::from([3, 2, 1]));
+    }
+
+    #[test]
+    fn shared_frame_handle_test() {
+        let frame = SharedFrame::from_bytes(header(2, 2, PixelFormat::Bgr, 1), vec![0; 12]).unwrap();
+        let handle = frame.clone();
+        assert_eq!(frame.handles(), 2);
+        assert_eq!(frame, handle);
+        assert_eq!(frame.data().as_ptr(), handle.data().as_ptr());
+        drop(handle);
+        assert_eq!(frame.handles(), 1);
+        assert_ne!(frame, SharedFrame::from_bytes(header(2, 2, PixelFormat::Bgr, 2), vec![0; 12]).unwrap());
+        assert!(SharedFrame::from_bytes(header(0, 2, PixelFormat::Bgr, 3), vec![]).is_err());
+        assert!(SharedFrame::from_bytes(header(5, 2, PixelFormat::Bgr, 3), vec![0; 12]).is_err());
+        assert!(SharedFrame::from_bytes(header(2, 2, PixelFormat::Bgra, 3), vec![0; 12]).is_err());
+    }
+}
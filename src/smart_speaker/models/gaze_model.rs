@@ -1,17 +1,393 @@
-use anyhow::Result;
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use bounded_vec_deque::BoundedVecDeque;
+use crate::smart_speaker::models::frame_model::FrameHeader;
+use crate::utils::config_util::Config;
 use crate::utils::pupil_util::{Pupil, PupilRemote};
 use crate::utils::vision_util::VisionType;
 
+/// A gaze position and when it was measured.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct GazeSample {
+    pub x: f32,
+    pub y: f32,
+    /// Pupil time in seconds, the same clock as the pupil frames. None if the source has no clock.
+    pub timestamp: Option<f64>,
+    /// Monotonic time when the sample was read from the source.
+    pub received_at: Instant,
+    /// 0.0 to 1.0. 1.0 if the source does not tell.
+    pub confidence: f32,
+}
+
+impl GazeSample {
+    pub(crate) fn new(x: f32, y: f32, timestamp: Option<f64>) -> Self {
+        Self {
+            x,
+            y,
+            timestamp,
+            received_at: Instant::now(),
+            confidence: 1.,
+        }
+    }
+
+    pub(crate) fn with_confidence(mut self, confidence: f32) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    pub(crate) fn position(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    /// Seconds from the capture of `frame` to this sample, negative if the sample is older.
+    /// The source clocks are compared if both have one, otherwise the monotonic times.
+    pub(crate) fn offset_from(&self, frame: &FrameHeader) -> f64 {
+        match (self.timestamp, frame.source_timestamp) {
+            (Some(sample), Some(frame)) => sample - frame,
+            _ => signed_seconds(self.received_at, frame.captured_at),
+        }
+    }
+
+    /// Seconds from `earlier` to this sample, on the same clocks as `offset_from`.
+    pub(crate) fn seconds_since(&self, earlier: &GazeSample) -> f64 {
+        match (self.timestamp, earlier.timestamp) {
+            (Some(sample), Some(earlier)) => sample - earlier,
+            _ => signed_seconds(self.received_at, earlier.received_at),
+        }
+    }
+
+    fn distance(&self, other: &GazeSample) -> f32 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2)).sqrt()
+    }
+}
+
+fn signed_seconds(later: Instant, earlier: Instant) -> f64 {
+    if later >= earlier {
+        (later - earlier).as_secs_f64()
+    } else {
+        -(earlier - later).as_secs_f64()
+    }
+}
+
+/// Recent gaze samples, matched to frames by time instead of by arrival order.
+pub(crate) struct GazeHistory {
+    samples: BoundedVecDeque<GazeSample>,
+    max_skew: Duration,
+}
+
+impl GazeHistory {
+    pub(crate) fn new(capacity: usize, max_skew: Duration) -> Self {
+        Self {
+            samples: BoundedVecDeque::new(capacity),
+            max_skew,
+        }
+    }
+
+    /// The gaze actor answers with its latest sample, so a sample that is already stored is skipped.
+    pub(crate) fn push(&mut self, sample: GazeSample) {
+        if self.samples.back() == Some(&sample) {
+            return;
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub(crate) fn max_skew(&self) -> Duration {
+        self.max_skew
+    }
+
+    /// Gaze at the capture time of `frame`, interpolated between the samples before and after it.
+    /// If only one side is within the max skew the nearest sample is used. None if no sample is.
+    pub(crate) fn at(&self, frame: &FrameHeader) -> Option<(f32, f32)> {
+        let max_skew = self.max_skew.as_secs_f64();
+        let offsets = self.samples.iter().map(|sample| (sample.offset_from(frame), sample));
+        let before = offsets.clone().filter(|(offset, _)| *offset <= 0.).max_by(|a, b| a.0.total_cmp(&b.0));
+        let after = offsets.filter(|(offset, _)| *offset > 0.).min_by(|a, b| a.0.total_cmp(&b.0));
+        match (before, after) {
+            (Some((before_offset, before)), Some((after_offset, after)))
+                if -before_offset <= max_skew && after_offset <= max_skew => {
+                let t = (-before_offset / (after_offset - before_offset)) as f32;
+                Some((before.x + (after.x - before.x) * t, before.y + (after.y - before.y) * t))
+            }
+            _ => {
+                [before, after].into_iter().flatten()
+                    .filter(|(offset, _)| offset.abs() <= max_skew)
+                    .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()))
+                    .map(|(_, sample)| sample.position())
+            }
+        }
+    }
+}
+
+/// Weight of a new sample in the exponential moving average.
+const EXPONENTIAL_SMOOTHING_ALPHA: f64 = 0.3;
+/// One-Euro filter cutoffs in Hz. The speed is in normalized units per second.
+const ONE_EURO_MIN_CUTOFF: f64 = 1.;
+const ONE_EURO_BETA: f64 = 1.;
+const ONE_EURO_DERIVATIVE_CUTOFF: f64 = 1.;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum GazeSmoothing {
+    None,
+    Exponential,
+    OneEuro,
+}
+
+impl FromStr for GazeSmoothing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(GazeSmoothing::None),
+            "ema" | "exponential" => Ok(GazeSmoothing::Exponential),
+            "one-euro" | "oneeuro" => Ok(GazeSmoothing::OneEuro),
+            _ => Err(anyhow!("invalid gaze smoothing")),
+        }
+    }
+}
+
+/// Smooths the positions of consecutive samples. Times and confidence are kept.
+pub(crate) struct GazeSmoother {
+    smoothing: GazeSmoothing,
+    last: Option<GazeSample>,
+    /// Filtered speed of the One-Euro filter.
+    derivative: (f64, f64),
+}
+
+impl GazeSmoother {
+    pub(crate) fn new(smoothing: GazeSmoothing) -> Self {
+        Self {
+            smoothing,
+            last: None,
+            derivative: (0., 0.),
+        }
+    }
+
+    pub(crate) fn smooth(&mut self, sample: GazeSample) -> GazeSample {
+        let (x, y) = match (&self.smoothing, &self.last) {
+            (GazeSmoothing::None, _) | (_, None) => (sample.x as f64, sample.y as f64),
+            (GazeSmoothing::Exponential, Some(last)) => {
+                (lerp(last.x, sample.x, EXPONENTIAL_SMOOTHING_ALPHA), lerp(last.y, sample.y, EXPONENTIAL_SMOOTHING_ALPHA))
+            }
+            (GazeSmoothing::OneEuro, Some(last)) => {
+                let dt = sample.seconds_since(last);
+                if dt <= 0. {
+                    (last.x as f64, last.y as f64)
+                } else {
+                    let speed = ((sample.x - last.x) as f64 / dt, (sample.y - last.y) as f64 / dt);
+                    let alpha = one_euro_alpha(ONE_EURO_DERIVATIVE_CUTOFF, dt);
+                    self.derivative = (
+                        self.derivative.0 + alpha * (speed.0 - self.derivative.0),
+                        self.derivative.1 + alpha * (speed.1 - self.derivative.1),
+                    );
+                    let cutoff = ONE_EURO_MIN_CUTOFF + ONE_EURO_BETA * self.derivative.0.hypot(self.derivative.1);
+                    let alpha = one_euro_alpha(cutoff, dt);
+                    (lerp(last.x, sample.x, alpha), lerp(last.y, sample.y, alpha))
+                }
+            }
+        };
+        let smoothed = GazeSample {
+            x: x as f32,
+            y: y as f32,
+            ..sample
+        };
+        self.last = Some(smoothed.clone());
+        smoothed
+    }
+}
+
+fn lerp(from: f32, to: f32, alpha: f64) -> f64 {
+    from as f64 + alpha * (to - from) as f64
+}
+
+fn one_euro_alpha(cutoff: f64, dt: f64) -> f64 {
+    let tau = 1. / (2. * PI * cutoff);
+    1. / (1. + tau / dt)
+}
+
+/// Samples that stayed together, where the user actually looked.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Fixation {
+    /// Centroid of the samples.
+    pub x: f32,
+    pub y: f32,
+    pub duration: Duration,
+    /// Pupil time of the first sample. None if the source has no clock.
+    pub timestamp: Option<f64>,
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum FixationMethod {
+    /// I-DT. Samples within a dispersion threshold.
+    Dispersion,
+    /// I-VT. Consecutive samples slower than a velocity threshold.
+    Velocity,
+}
+
+impl FromStr for FixationMethod {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "idt" | "i-dt" | "dispersion" => Ok(FixationMethod::Dispersion),
+            "ivt" | "i-vt" | "velocity" => Ok(FixationMethod::Velocity),
+            _ => Err(anyhow!("invalid fixation method")),
+        }
+    }
+}
+
+pub(crate) struct FixationDetector {
+    method: FixationMethod,
+    /// Normalized units for I-DT, normalized units per second for I-VT.
+    threshold: f32,
+    min_duration: Duration,
+    window: VecDeque<GazeSample>,
+}
+
+impl FixationDetector {
+    pub(crate) fn new(method: FixationMethod, threshold: f32, min_duration: Duration) -> Self {
+        Self {
+            method,
+            threshold,
+            min_duration,
+            window: VecDeque::new(),
+        }
+    }
+
+    /// Add the next sample. Returns the fixation that it ended, if any.
+    pub(crate) fn push(&mut self, sample: GazeSample) -> Option<Fixation> {
+        match self.method {
+            FixationMethod::Dispersion => {
+                self.window.push_back(sample);
+                if dispersion(&self.window) <= self.threshold {
+                    return None;
+                }
+                let sample = self.window.pop_back().expect("window has the new sample");
+                let ended = self.current();
+                if ended.is_some() {
+                    self.window.clear();
+                }
+                self.window.push_back(sample);
+                // a too short window slides forward until it fits again
+                while self.window.len() > 1 && dispersion(&self.window) > self.threshold {
+                    self.window.pop_front();
+                }
+                ended
+            }
+            FixationMethod::Velocity => {
+                let fixating = match self.window.back() {
+                    None => true,
+                    Some(last) => {
+                        let dt = sample.seconds_since(last);
+                        if dt > 0. {
+                            sample.distance(last) as f64 / dt <= self.threshold as f64
+                        } else {
+                            sample.distance(last) == 0.
+                        }
+                    }
+                };
+                if fixating {
+                    self.window.push_back(sample);
+                    return None;
+                }
+                let ended = self.current();
+                self.window.clear();
+                self.window.push_back(sample);
+                ended
+            }
+        }
+    }
+
+    /// The fixation in progress. None until its samples span the min duration.
+    pub(crate) fn current(&self) -> Option<Fixation> {
+        let first = self.window.front()?;
+        let last = self.window.back()?;
+        let duration = last.seconds_since(first);
+        if duration < self.min_duration.as_secs_f64() {
+            return None;
+        }
+        let count = self.window.len() as f32;
+        Some(Fixation {
+            x: self.window.iter().map(|sample| sample.x).sum::<f32>() / count,
+            y: self.window.iter().map(|sample| sample.y).sum::<f32>() / count,
+            duration: Duration::from_secs_f64(duration),
+            timestamp: first.timestamp,
+            samples: self.window.len(),
+        })
+    }
+}
+
+/// Width plus height of the bounding box of the samples.
+fn dispersion(samples: &VecDeque<GazeSample>) -> f32 {
+    let (mut min_x, mut max_x, mut min_y, mut max_y) = (f32::MAX, f32::MIN, f32::MAX, f32::MIN);
+    for sample in samples {
+        min_x = min_x.min(sample.x);
+        max_x = max_x.max(sample.x);
+        min_y = min_y.min(sample.y);
+        max_y = max_y.max(sample.y);
+    }
+    (max_x - min_x) + (max_y - min_y)
+}
+
+/// Confidence threshold, smoothing and fixation detection of the samples of a source.
+pub(crate) struct GazeFilter {
+    min_confidence: f32,
+    smoother: GazeSmoother,
+    fixation: FixationDetector,
+}
+
+impl GazeFilter {
+    pub(crate) fn new(min_confidence: f32, smoother: GazeSmoother, fixation: FixationDetector) -> Self {
+        Self {
+            min_confidence,
+            smoother,
+            fixation,
+        }
+    }
+
+    pub(crate) fn from_config(config: &Config) -> Self {
+        let threshold = match config.gaze_fixation {
+            FixationMethod::Dispersion => config.gaze_fixation_dispersion,
+            FixationMethod::Velocity => config.gaze_fixation_velocity,
+        };
+        Self::new(
+            config.gaze_min_confidence,
+            GazeSmoother::new(config.gaze_smoothing.clone()),
+            FixationDetector::new(config.gaze_fixation.clone(), threshold, Duration::from_millis(config.gaze_fixation_min_ms as u64)),
+        )
+    }
+
+    /// None if the sample is below the confidence threshold.
+    /// Otherwise the smoothed sample and the fixation that the sample ended, if any.
+    /// Fixations are detected on the samples before smoothing.
+    pub(crate) fn push(&mut self, sample: GazeSample) -> Option<(GazeSample, Option<Fixation>)> {
+        if sample.confidence < self.min_confidence {
+            return None;
+        }
+        let ended = self.fixation.push(sample.clone());
+        Some((self.smoother.smooth(sample), ended))
+    }
+
+    pub(crate) fn current_fixation(&self) -> Option<Fixation> {
+        self.fixation.current()
+    }
+}
+
 pub(crate) struct Gaze {
     pub source: Option<Box<(dyn GazeSource + Send + 'static)>>,
     pub fallback_x: f32,
     pub fallback_y: f32,
-    pub x: f32,
-    pub y: f32
+    pub sample: GazeSample,
+    filter: GazeFilter,
+    /// Fixations that ended since the last `take_fixations`.
+    fixations: Vec<Fixation>,
 }
 
 impl Gaze {
-    pub(crate) fn new(vision_type: VisionType, fallback_x: f32, fallback_y: f32, zmq_endpoint: String) -> Result<Self> {
+    pub(crate) fn new(vision_type: VisionType, fallback_x: f32, fallback_y: f32, zmq_endpoint: String, filter: GazeFilter) -> Result<Self> {
         match vision_type {
             VisionType::Pupil => {
                 let source = PupilGazeSource::new(zmq_endpoint);
@@ -19,8 +395,9 @@ impl Gaze {
                     source: Some(Box::new(source)),
                     fallback_x,
                     fallback_y,
-                    x: fallback_x,
-                    y: fallback_y,
+                    sample: GazeSample::new(fallback_x, fallback_y, None),
+                    filter,
+                    fixations: vec![],
                 })
             },
             _ => {
@@ -28,8 +405,9 @@ impl Gaze {
                     source: None,
                     fallback_x,
                     fallback_y,
-                    x: fallback_x,
-                    y: fallback_y,
+                    sample: GazeSample::new(fallback_x, fallback_y, None),
+                    filter,
+                    fixations: vec![],
                 })
             }
         }
@@ -39,13 +417,17 @@ impl Gaze {
     pub(crate) fn update_gaze(&mut self) {
         match &mut self.source {
             None => {
-                self.x = self.fallback_x;
-                self.y = self.fallback_y;
+                self.sample = GazeSample::new(self.fallback_x, self.fallback_y, None);
             },
             Some(source) => {
-                match source.get_gaze() {
-                    Ok(result) => {
-                        (self.x, self.y) = result;
+                match source.get_gazes() {
+                    Ok(samples) => {
+                        for sample in samples {
+                            if let Some((sample, ended)) = self.filter.push(sample) {
+                                self.sample = sample;
+                                self.fixations.extend(ended);
+                            }
+                        }
                     }
                     Err(_) => {}
                 }
@@ -53,14 +435,29 @@ impl Gaze {
         }
     }
 
-    pub(crate) fn get_gaze(&self) -> (f32, f32) {
-        (self.x, self.y)
+    pub(crate) fn get_gaze(&self) -> GazeSample {
+        self.sample.clone()
+    }
+
+    pub(crate) fn current_fixation(&self) -> Option<Fixation> {
+        self.filter.current_fixation()
+    }
+
+    pub(crate) fn take_fixations(&mut self) -> Vec<Fixation> {
+        std::mem::take(&mut self.fixations)
     }
 }
 
+/// Upper bound of the samples read in one update, so a fast source can not hold the gaze actor.
+const MAX_GAZES_PER_UPDATE: usize = 512;
+
 pub(crate) trait GazeSource {
     fn get_vision_type(&self) -> VisionType;
-    fn get_gaze(&mut self) -> Result<(f32, f32)>;
+    fn get_gaze(&mut self) -> Result<GazeSample>;
+    /// Samples received since the last call, at least one. Sources without a queue return the latest sample.
+    fn get_gazes(&mut self) -> Result<Vec<GazeSample>> {
+        Ok(vec![self.get_gaze()?])
+    }
 }
 
 pub(crate) struct PupilGazeSource {
@@ -80,7 +477,16 @@ impl GazeSource for PupilGazeSource {
         VisionType::Pupil
     }
 
-    fn get_gaze(&mut self) -> Result<(f32, f32)> {
-        self.pupil.get_gaze()
+    fn get_gaze(&mut self) -> Result<GazeSample> {
+        let ((x, y), timestamp, confidence) = self.pupil.get_gaze_with_info()?;
+        Ok(GazeSample::new(x, y, Some(timestamp)).with_confidence(confidence))
+    }
+
+    fn get_gazes(&mut self) -> Result<Vec<GazeSample>> {
+        let mut samples = vec![self.get_gaze()?];
+        while samples.len() < MAX_GAZES_PER_UPDATE && self.pupil.has_pending() {
+            samples.push(self.get_gaze()?);
+        }
+        Ok(samples)
     }
 }
@@ -0,0 +1,126 @@
+#[cfg(test)]
+mod gaze_model_tests {
+    use std::time::{Duration, Instant};
+    use crate::smart_speaker::models::frame_model::{FrameHeader, PixelFormat};
+    use crate::utils::vision_util::VisionType;
+    use super::super::gaze_model::*;
+
+    fn frame_at(timestamp: Option<f64>) -> FrameHeader {
+        FrameHeader::new(320, 240, PixelFormat::Bgr, VisionType::Pupil, 1, timestamp)
+    }
+
+    #[test]
+    fn gaze_history_interpolate_test() {
+        let mut history = GazeHistory::new(10, Duration::from_millis(100));
+        history.push(GazeSample::new(0.2, 0.4, Some(10.0)));
+        history.push(GazeSample::new(0.4, 0.8, Some(10.1)));
+        history.push(GazeSample::new(0.9, 0.9, Some(10.5)));
+        let (x, y) = history.at(&frame_at(Some(10.025))).unwrap();
+        assert!((x - 0.25).abs() < 1e-4);
+        assert!((y - 0.5).abs() < 1e-4);
+        assert_eq!(history.at(&frame_at(Some(10.1))), Some((0.4, 0.8)));
+    }
+
+    #[test]
+    fn gaze_history_max_skew_test() {
+        let mut history = GazeHistory::new(10, Duration::from_millis(100));
+        history.push(GazeSample::new(0.2, 0.4, Some(10.0)));
+        history.push(GazeSample::new(0.9, 0.9, Some(10.5)));
+        // the later sample is too far to interpolate, the nearest one is used
+        assert_eq!(history.at(&frame_at(Some(10.05))), Some((0.2, 0.4)));
+        assert_eq!(history.at(&frame_at(Some(10.45))), Some((0.9, 0.9)));
+        assert_eq!(history.at(&frame_at(Some(10.25))), None);
+        assert_eq!(history.at(&frame_at(Some(12.0))), None);
+    }
+
+    #[test]
+    fn gaze_history_monotonic_test() {
+        let mut history = GazeHistory::new(10, Duration::from_millis(100));
+        let sample = GazeSample::new(0.5, 0.5, None);
+        history.push(sample.clone());
+        // a repeated answer of the gaze actor is stored once
+        history.push(sample.clone());
+        // without pupil timestamps the monotonic times are compared
+        let mut frame = frame_at(Some(10.0));
+        frame.captured_at = sample.received_at + Duration::from_millis(50);
+        assert_eq!(history.at(&frame), Some((0.5, 0.5)));
+        frame.captured_at = Instant::now() + Duration::from_secs(1);
+        assert_eq!(history.at(&frame), None);
+    }
+
+    fn at(x: f32, y: f32, timestamp: f64) -> GazeSample {
+        GazeSample::new(x, y, Some(timestamp))
+    }
+
+    #[test]
+    fn gaze_filter_confidence_test() {
+        let mut filter = GazeFilter::new(0.8, GazeSmoother::new(GazeSmoothing::None),
+                                         FixationDetector::new(FixationMethod::Dispersion, 0.05, Duration::from_millis(100)));
+        assert!(filter.push(at(0.1, 0.1, 0.).with_confidence(0.5)).is_none());
+        let (sample, ended) = filter.push(at(0.2, 0.3, 0.01).with_confidence(0.9)).unwrap();
+        assert_eq!(sample.position(), (0.2, 0.3));
+        assert_eq!(ended, None);
+    }
+
+    #[test]
+    fn gaze_smoother_test() {
+        let mut smoother = GazeSmoother::new(GazeSmoothing::Exponential);
+        assert_eq!(smoother.smooth(at(0., 0., 0.)).position(), (0., 0.));
+        let sample = smoother.smooth(at(1., 1., 0.01));
+        assert!((sample.x - 0.3).abs() < 1e-4);
+        assert_eq!(sample.timestamp, Some(0.01));
+        // the one euro filter follows slowly at rest and quickly when the gaze moves fast
+        let mut smoother = GazeSmoother::new(GazeSmoothing::OneEuro);
+        smoother.smooth(at(0.5, 0.5, 0.));
+        let still = smoother.smooth(at(0.51, 0.5, 0.01));
+        assert!(still.x > 0.5 && still.x < 0.51);
+        let mut jumps = vec![];
+        for i in 2..10 {
+            jumps.push(smoother.smooth(at(0.9, 0.5, i as f64 * 0.01)).x);
+        }
+        assert!(jumps.windows(2).all(|w| w[0] <= w[1]));
+        assert!(*jumps.last().unwrap() > 0.8);
+    }
+
+    #[test]
+    fn fixation_dispersion_test() {
+        let mut detector = FixationDetector::new(FixationMethod::Dispersion, 0.05, Duration::from_millis(100));
+        for i in 0..20 {
+            let jitter = if i % 2 == 0 { 0.01 } else { -0.01 };
+            assert_eq!(detector.push(at(0.3 + jitter, 0.6, i as f64 * 0.01)), None);
+        }
+        let current = detector.current().unwrap();
+        assert!((current.x - 0.3).abs() < 1e-3);
+        assert_eq!(current.samples, 20);
+        // a saccade ends the fixation
+        let fixation = detector.push(at(0.8, 0.2, 0.2)).unwrap();
+        assert_eq!(fixation.samples, 20);
+        assert_eq!(fixation.timestamp, Some(0.));
+        assert!((fixation.duration.as_secs_f64() - 0.19).abs() < 1e-6);
+        assert_eq!(detector.current(), None);
+    }
+
+    #[test]
+    fn fixation_dispersion_short_test() {
+        let mut detector = FixationDetector::new(FixationMethod::Dispersion, 0.05, Duration::from_millis(100));
+        // samples that move on before the min duration are no fixation
+        for i in 0..10 {
+            assert_eq!(detector.push(at(i as f32 * 0.1, 0.5, i as f64 * 0.02)), None);
+        }
+        assert_eq!(detector.current(), None);
+    }
+
+    #[test]
+    fn fixation_velocity_test() {
+        let mut detector = FixationDetector::new(FixationMethod::Velocity, 0.5, Duration::from_millis(100));
+        for i in 0..15 {
+            assert_eq!(detector.push(at(0.5 + i as f32 * 0.001, 0.5, i as f64 * 0.01)), None);
+        }
+        assert_eq!(detector.current().unwrap().samples, 15);
+        // 0.3 in 10 ms is far faster than 0.5 per second
+        let fixation = detector.push(at(0.8, 0.5, 0.15)).unwrap();
+        assert_eq!(fixation.samples, 15);
+        assert!((fixation.x - 0.507).abs() < 1e-4);
+        assert_eq!(detector.current(), None);
+    }
+}
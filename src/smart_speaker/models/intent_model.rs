@@ -1,8 +1,11 @@
 use std::fmt;
 use std::fmt::{Debug, Display, Formatter};
 use std::str::FromStr;
-use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
-use crate::smart_speaker::models::task_model::cooking_task::{CookingIngredient, CookingIngredientName, CookingIngredientAmount};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use crate::smart_speaker::models::message_model::{IntentContent, SmartSpeakerI18nText};
+use crate::smart_speaker::models::recipe_model::{Recipe, RecipeCatalog};
+use crate::smart_speaker::models::task_model::cooking_task::CookingIngredient;
 
 pub(crate) trait IntentSlot: Send {
     fn clone_box(&self) -> Box<dyn IntentSlot>;
@@ -54,59 +57,29 @@ impl FromStr for IntentPlace {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
-pub(crate) enum IntentCookingMenu {
-    CarrotSalad,
-    PotatoSalad,
+/// Menu name as heard from the user. Resolved to a `Recipe` through the `RecipeCatalog`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct IntentCookingMenu {
+    pub(crate) name: String,
 }
 
 impl IntentCookingMenu {
-    pub(crate) fn to_i18n(&self) -> SmartSpeakerI18nText {
-        match self {
-            IntentCookingMenu::CarrotSalad => SmartSpeakerI18nText::new()
-                .en("Carrot salad")
-                .ja("にんじんサラダ")
-                .zh("胡萝卜沙拉")
-                .ko("당근 샐러드"),
-            IntentCookingMenu::PotatoSalad => SmartSpeakerI18nText::new()
-                .en("Potato salad")
-                .ja("ポテトサラダ")
-                .zh("土豆沙拉")
-                .ko("감자 샐러드")
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
         }
     }
 
-    pub(crate) fn to_ingredient(&self) -> Vec<CookingIngredient> {
-        match self {
-            IntentCookingMenu::CarrotSalad => vec![
-                CookingIngredient::new(
-                    CookingIngredientName::Carrot,
-                    CookingIngredientAmount::MilliGram(1000)),
-                CookingIngredient::new(
-                    CookingIngredientName::Salt,
-                    CookingIngredientAmount::MilliGram(50)),
-                CookingIngredient::new(
-                    CookingIngredientName::Pepper,
-                    CookingIngredientAmount::MilliGram(50)),
-                CookingIngredient::new(
-                    CookingIngredientName::SesameOil,
-                    CookingIngredientAmount::MilliLiter(5)),
-            ],
-            IntentCookingMenu::PotatoSalad => vec![
-                CookingIngredient::new(
-                    CookingIngredientName::Potato,
-                    CookingIngredientAmount::MilliGram(1500)),
-                CookingIngredient::new(
-                    CookingIngredientName::Salt,
-                    CookingIngredientAmount::MilliGram(20)),
-                CookingIngredient::new(
-                    CookingIngredientName::Pepper,
-                    CookingIngredientAmount::MilliGram(20)),
-                CookingIngredient::new(
-                    CookingIngredientName::Mayonnaise,
-                    CookingIngredientAmount::MilliLiter(15)),
-            ],
-        }
+    pub(crate) fn to_recipe<'a>(&self, catalog: &'a RecipeCatalog) -> Result<&'a Recipe> {
+        catalog.find(self).ok_or(anyhow!("no recipe found for menu: {}", self.name))
+    }
+
+    pub(crate) fn to_i18n(&self, catalog: &RecipeCatalog) -> Result<SmartSpeakerI18nText> {
+        Ok(self.to_recipe(catalog)?.to_i18n())
+    }
+
+    pub(crate) fn to_ingredient(&self, catalog: &RecipeCatalog) -> Result<Vec<CookingIngredient>> {
+        self.to_recipe(catalog)?.to_ingredient()
     }
 }
 
@@ -123,45 +96,21 @@ impl FromStr for IntentCookingMenu {
     type Err = ();
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "carrot salad" => Ok(IntentCookingMenu::CarrotSalad),
-            "胡萝卜沙拉" => Ok(IntentCookingMenu::CarrotSalad),
-            "胡萝卜泥沙拉" => Ok(IntentCookingMenu::CarrotSalad),
-            "胡萝卜温沙拉" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんサラダ" => Ok(IntentCookingMenu::CarrotSalad),
-            "人参サラダ" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんのサラダ" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんりようり" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんのほっとさらだ" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんほっとさらだ" => Ok(IntentCookingMenu::CarrotSalad),
-            "にんじんサラダりようり" => Ok(IntentCookingMenu::CarrotSalad),
-            "ポテトサラダ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテサラ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテトのサラダ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテトりようり" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテトのほっとサラダ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテトほっとサラダ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ぽてとサラダ" => Ok(IntentCookingMenu::PotatoSalad),
-            "ポテトサラダりようり" => Ok(IntentCookingMenu::PotatoSalad),
-            "ぽてとさらだりようり" => Ok(IntentCookingMenu::PotatoSalad),
-            "土豆沙拉" => Ok(IntentCookingMenu::PotatoSalad),
-            "土豆泥沙拉" => Ok(IntentCookingMenu::PotatoSalad),
-            "土豆泥温沙拉" => Ok(IntentCookingMenu::PotatoSalad),
-            _ => Err(()),
+        match s.trim() {
+            "" => Err(()),
+            name => Ok(IntentCookingMenu::new(name)),
         }
     }
 }
 
 impl Display for IntentCookingMenu {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        match self {
-            IntentCookingMenu::CarrotSalad => write!(f, "にんじんサラダ"),
-            IntentCookingMenu::PotatoSalad => write!(f, "ポテトサラダ"),
-        }
+        write!(f, "{}", self.name)
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum IntentAction {
     None,
     TurnOn,
@@ -201,6 +150,39 @@ impl FromStr for IntentAction {
     }
 }
 
+/// A typed command for the text input. (e.g. `cook ポテトサラダ`, `next`, `repeat`, `cancel`)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TextCommand {
+    Intent(IntentContent),
+    Quit,
+}
+
+impl FromStr for TextCommand {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (command, argument) = match s.split_once(char::is_whitespace) {
+            None => (s, ""),
+            Some((command, argument)) => (command, argument.trim()),
+        };
+        let intent = match command.to_lowercase().as_str() {
+            "quit" | "exit" => return Ok(TextCommand::Quit),
+            "cook" | "料理" => {
+                let menu = IntentCookingMenu::from_str(argument).map_err(|_| anyhow!("no menu given. e.g. cook ポテトサラダ"))?;
+                return Ok(TextCommand::Intent(IntentContent::new(IntentAction::CookingTask, vec![Box::new(menu)])))
+            }
+            "next" | "ok" | "次" => IntentAction::Next,
+            "repeat" | "again" | "繰り返し" => IntentAction::Repeat,
+            "cancel" | "キャンセル" => IntentAction::Cancel,
+            "confirm" | "yes" | "承認" => IntentAction::Confirm,
+            "look" | "見えているもの" => IntentAction::WhatYouSee,
+            _ => return Err(anyhow!("unknown command: {}", s)),
+        };
+        Ok(TextCommand::Intent(IntentContent::new(intent, vec![])))
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum IntentObject {
     Light,
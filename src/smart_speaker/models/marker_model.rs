@@ -1,6 +1,18 @@
 use std::fmt;
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use opencv::objdetect;
+use opencv::objdetect::{Dictionary, PredefinedDictionaryType};
+use serde::{Deserialize, Serialize};
+use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
+use crate::utils::config_util::Config;
 
-#[derive(Debug, PartialEq)]
+/// Edge length of the markers printed 12 to an A4 sheet.
+pub(crate) const DEFAULT_MARKER_MM: f32 = 38.;
+
+/// Ingredient in a container or tray tagged with a marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum IngredientMarker {
     Carrot,
     Tomato,
@@ -11,7 +23,22 @@ pub(crate) enum IngredientMarker {
     Pumpkin,
     Broccoli,
     GreenPepper,
-    Unknown,
+}
+
+impl IngredientMarker {
+    pub(crate) fn to_i18n(&self) -> SmartSpeakerI18nText {
+        match self {
+            IngredientMarker::Carrot => SmartSpeakerI18nText::new().en("carrot").ja("にんじん").zh("胡萝卜").ko("당근"),
+            IngredientMarker::Tomato => SmartSpeakerI18nText::new().en("tomato").ja("トマト").zh("番茄").ko("토마토"),
+            IngredientMarker::Onion => SmartSpeakerI18nText::new().en("onion").ja("玉ねぎ").zh("洋葱").ko("양파"),
+            IngredientMarker::Potato => SmartSpeakerI18nText::new().en("potato").ja("じゃがいも").zh("土豆").ko("감자"),
+            IngredientMarker::Eggplant => SmartSpeakerI18nText::new().en("eggplant").ja("なす").zh("茄子").ko("가지"),
+            IngredientMarker::Cabbage => SmartSpeakerI18nText::new().en("cabbage").ja("キャベツ").zh("卷心菜").ko("양배추"),
+            IngredientMarker::Pumpkin => SmartSpeakerI18nText::new().en("pumpkin").ja("かぼちゃ").zh("南瓜").ko("호박"),
+            IngredientMarker::Broccoli => SmartSpeakerI18nText::new().en("broccoli").ja("ブロッコリー").zh("西兰花").ko("브로콜리"),
+            IngredientMarker::GreenPepper => SmartSpeakerI18nText::new().en("green pepper").ja("ピーマン").zh("青椒").ko("피망"),
+        }
+    }
 }
 
 impl fmt::Display for IngredientMarker {
@@ -26,24 +53,274 @@ impl fmt::Display for IngredientMarker {
             IngredientMarker::Pumpkin => write!(f, "pumpkin"),
             IngredientMarker::Broccoli => write!(f, "broccoli"),
             IngredientMarker::GreenPepper => write!(f, "green pepper"),
-            IngredientMarker::Unknown => write!(f, "unknown"),
         }
     }
 }
 
-impl From<u32> for IngredientMarker {
-    fn from(value: u32) -> Self {
-        match value {
-            0 => IngredientMarker::Carrot,
-            1 => IngredientMarker::Tomato,
-            2 => IngredientMarker::Onion,
-            3 => IngredientMarker::Potato,
-            4 => IngredientMarker::Eggplant,
-            5 => IngredientMarker::Cabbage,
-            6 => IngredientMarker::Pumpkin,
-            7 => IngredientMarker::Broccoli,
-            8 => IngredientMarker::GreenPepper,
-            _ => IngredientMarker::Unknown,
+/// Kitchen tool tagged with a marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ToolMarker {
+    Knife,
+    CuttingBoard,
+    Peeler,
+    Bowl,
+    Pot,
+    Pan,
+}
+
+impl ToolMarker {
+    pub(crate) fn to_i18n(&self) -> SmartSpeakerI18nText {
+        match self {
+            ToolMarker::Knife => SmartSpeakerI18nText::new().en("knife").ja("包丁").zh("刀").ko("칼"),
+            ToolMarker::CuttingBoard => SmartSpeakerI18nText::new().en("cutting board").ja("まな板").zh("砧板").ko("도마"),
+            ToolMarker::Peeler => SmartSpeakerI18nText::new().en("peeler").ja("ピーラー").zh("削皮器").ko("필러"),
+            ToolMarker::Bowl => SmartSpeakerI18nText::new().en("bowl").ja("ボウル").zh("碗").ko("그릇"),
+            ToolMarker::Pot => SmartSpeakerI18nText::new().en("pot").ja("鍋").zh("锅").ko("냄비"),
+            ToolMarker::Pan => SmartSpeakerI18nText::new().en("pan").ja("フライパン").zh("平底锅").ko("프라이팬"),
+        }
+    }
+}
+
+/// What a marker is attached to. Written as the bare name in the board file, e.g. `"carrot"` or `"knife"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum MarkerTag {
+    Ingredient(IngredientMarker),
+    Tool(ToolMarker),
+}
+
+impl MarkerTag {
+    /// The tray of an ingredient or the tool itself.
+    pub(crate) fn to_i18n(&self) -> SmartSpeakerI18nText {
+        match self {
+            MarkerTag::Ingredient(ingredient) => {
+                let name = ingredient.to_i18n();
+                SmartSpeakerI18nText::new()
+                    .en(&format!("the {} tray", name.en))
+                    .ja(&format!("{}のトレー", name.ja))
+                    .zh(&format!("{}托盘", name.zh))
+                    .ko(&format!("{} 트레이", name.ko))
+            }
+            MarkerTag::Tool(tool) => {
+                let name = tool.to_i18n();
+                SmartSpeakerI18nText::new()
+                    .en(&format!("the {}", name.en))
+                    .ja(&name.ja)
+                    .zh(&name.zh)
+                    .ko(&name.ko)
+            }
+        }
+    }
+}
+
+/// `word` with the Korean particle that fits its last syllable, e.g. `ko_with_particle("칼", "을", "를")` is "칼을".
+/// A word that does not end in Hangul gets both forms, as in "을(를)".
+pub(crate) fn ko_with_particle(word: &str, with_final: &str, without_final: &str) -> String {
+    match word.chars().last().map(|c| c as u32) {
+        Some(c) if (0xAC00..=0xD7A3).contains(&c) => {
+            let has_final = (c - 0xAC00) % 28 != 0;
+            format!("{}{}", word, if has_final { with_final } else { without_final })
+        }
+        _ => format!("{}{}({})", word, with_final, without_final),
+    }
+}
+
+impl fmt::Display for MarkerTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MarkerTag::Ingredient(ingredient) => write!(f, "{}", ingredient),
+            MarkerTag::Tool(tool) => write!(f, "{}", tool.to_i18n().en),
+        }
+    }
+}
+
+/// Predefined ArUco dictionary. `4x4_50` has 50 markers of 4x4 bits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct ArucoDictionary {
+    pub bits: u32,
+    pub size: u32,
+}
+
+impl ArucoDictionary {
+    pub(crate) fn predefined(&self) -> Result<PredefinedDictionaryType> {
+        Ok(match (self.bits, self.size) {
+            (4, 50) => PredefinedDictionaryType::DICT_4X4_50,
+            (4, 100) => PredefinedDictionaryType::DICT_4X4_100,
+            (4, 250) => PredefinedDictionaryType::DICT_4X4_250,
+            (4, 1000) => PredefinedDictionaryType::DICT_4X4_1000,
+            (5, 50) => PredefinedDictionaryType::DICT_5X5_50,
+            (5, 100) => PredefinedDictionaryType::DICT_5X5_100,
+            (5, 250) => PredefinedDictionaryType::DICT_5X5_250,
+            (5, 1000) => PredefinedDictionaryType::DICT_5X5_1000,
+            (6, 50) => PredefinedDictionaryType::DICT_6X6_50,
+            (6, 100) => PredefinedDictionaryType::DICT_6X6_100,
+            (6, 250) => PredefinedDictionaryType::DICT_6X6_250,
+            (6, 1000) => PredefinedDictionaryType::DICT_6X6_1000,
+            (7, 50) => PredefinedDictionaryType::DICT_7X7_50,
+            (7, 100) => PredefinedDictionaryType::DICT_7X7_100,
+            (7, 250) => PredefinedDictionaryType::DICT_7X7_250,
+            (7, 1000) => PredefinedDictionaryType::DICT_7X7_1000,
+            _ => return Err(anyhow!("unsupported aruco dictionary: {}", self)),
+        })
+    }
+
+    pub(crate) fn dictionary(&self) -> Result<Dictionary> {
+        Ok(objdetect::get_predefined_dictionary(self.predefined()?)?)
+    }
+}
+
+impl Default for ArucoDictionary {
+    fn default() -> Self {
+        Self { bits: 4, size: 50 }
+    }
+}
+
+impl FromStr for ArucoDictionary {
+    type Err = anyhow::Error;
+
+    /// `4x4_50` or `DICT_4X4_50`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        let name = lower.strip_prefix("dict_").unwrap_or(&lower);
+        let dictionary = name.split_once('_')
+            .and_then(|(bits, size)| {
+                let (bits, bits_again) = bits.split_once('x')?;
+                if bits != bits_again {
+                    return None;
+                }
+                Some(Self { bits: bits.parse().ok()?, size: size.parse().ok()? })
+            })
+            .ok_or(anyhow!("invalid aruco dictionary {:?}. e.g. 4x4_50", s))?;
+        dictionary.predefined()?;
+        Ok(dictionary)
+    }
+}
+
+impl fmt::Display for ArucoDictionary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}x{}_{}", self.bits, self.bits, self.size)
+    }
+}
+
+/// A marker of the board file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct BoardMarker {
+    pub id: i32,
+    /// Edge length. The `marker_mm` of the board if none.
+    #[serde(default)]
+    pub size_mm: Option<f32>,
+    /// Top left corner of the marker on the board, markers aligned with the board edges.
+    #[serde(default)]
+    pub position_mm: Option<[f32; 2]>,
+    /// Ingredient or tool the marker is attached to. Tagged markers move with it and are not used as the board.
+    #[serde(default)]
+    pub tag: Option<MarkerTag>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+struct MarkerBoardFile {
+    #[serde(default)]
+    dictionary: Option<String>,
+    #[serde(default)]
+    marker_mm: Option<f32>,
+    #[serde(default)]
+    markers: Vec<BoardMarker>,
+}
+
+/// Markers used for measurement and tagging. Markers not in `markers` have the `marker_mm` size, no position and no tag.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct MarkerBoard {
+    pub dictionary: ArucoDictionary,
+    pub marker_mm: f32,
+    pub markers: Vec<BoardMarker>,
+}
+
+impl MarkerBoard {
+    pub(crate) fn new(dictionary: ArucoDictionary, marker_mm: f32, markers: Vec<BoardMarker>) -> Result<Self> {
+        let board = Self {
+            dictionary,
+            marker_mm,
+            markers,
+        };
+        board.validate()?;
+        Ok(board)
+    }
+
+    /// `--aruco-board` if given. The dictionary and the marker size of the file default to `--aruco-dictionary` and `--aruco-marker-mm`.
+    pub(crate) fn from_config(config: &Config) -> Result<Self> {
+        if config.aruco_board_path.is_empty() {
+            return Self::new(config.aruco_dictionary, config.aruco_marker_mm, vec![]);
+        }
+        Self::load(&config.aruco_board_path, config.aruco_dictionary, config.aruco_marker_mm)
+    }
+
+    pub(crate) fn load(path: &str, dictionary: ArucoDictionary, marker_mm: f32) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read aruco board {}: {}", path, e))?;
+        let file: MarkerBoardFile = serde_json::from_str(&text)
+            .map_err(|e| anyhow!("failed to parse aruco board {}: {}", path, e))?;
+        let dictionary = match file.dictionary {
+            Some(name) => ArucoDictionary::from_str(&name)?,
+            None => dictionary,
+        };
+        Self::new(dictionary, file.marker_mm.unwrap_or(marker_mm), file.markers)
+            .map_err(|e| anyhow!("{}: {}", path, e))
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.marker_mm <= 0. {
+            return Err(anyhow!("marker size must be positive"));
+        }
+        for (i, marker) in self.markers.iter().enumerate() {
+            if marker.id < 0 || marker.id as u32 >= self.dictionary.size {
+                return Err(anyhow!("marker {} is not in {}", marker.id, self.dictionary));
+            }
+            if matches!(marker.size_mm, Some(size) if size <= 0.) {
+                return Err(anyhow!("marker {}: size must be positive", marker.id));
+            }
+            if marker.tag.is_some() && marker.position_mm.is_some() {
+                return Err(anyhow!("marker {}: a tagged marker moves and cannot have a position", marker.id));
+            }
+            if self.markers[..i].iter().any(|m| m.id == marker.id) {
+                return Err(anyhow!("duplicated marker id: {}", marker.id));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn marker(&self, id: i32) -> Option<&BoardMarker> {
+        self.markers.iter().find(|marker| marker.id == id)
+    }
+
+    /// Tag of marker `id`. None for untagged markers.
+    pub(crate) fn tag(&self, id: i32) -> Option<MarkerTag> {
+        self.marker(id).and_then(|marker| marker.tag)
+    }
+
+    /// Distinct tags of the board, in the order of the file.
+    pub(crate) fn tags(&self) -> Vec<MarkerTag> {
+        let mut tags: Vec<MarkerTag> = vec![];
+        for tag in self.markers.iter().filter_map(|marker| marker.tag) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Edge length of marker `id` in mm.
+    pub(crate) fn marker_mm(&self, id: i32) -> f32 {
+        self.marker(id).and_then(|marker| marker.size_mm).unwrap_or(self.marker_mm)
+    }
+}
+
+impl Default for MarkerBoard {
+    fn default() -> Self {
+        Self {
+            dictionary: ArucoDictionary::default(),
+            marker_mm: DEFAULT_MARKER_MM,
+            markers: vec![],
         }
     }
 }
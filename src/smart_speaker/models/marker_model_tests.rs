@@ -0,0 +1,67 @@
+#[cfg(test)]
+mod marker_model_tests {
+    use std::str::FromStr;
+    use super::super::marker_model::*;
+
+    #[test]
+    fn aruco_dictionary_parse_test() {
+        assert_eq!(ArucoDictionary::from_str("4x4_50").unwrap(), ArucoDictionary::default());
+        assert_eq!(ArucoDictionary::from_str("DICT_6X6_250").unwrap(), ArucoDictionary { bits: 6, size: 250 });
+        assert_eq!(ArucoDictionary { bits: 5, size: 100 }.to_string(), "5x5_100");
+        assert!(ArucoDictionary::from_str("4x5_50").is_err());
+        assert!(ArucoDictionary::from_str("4x4_60").is_err());
+        assert!(ArucoDictionary::from_str("apriltag").is_err());
+    }
+
+    #[test]
+    fn marker_board_load_test() {
+        let board = MarkerBoard::load(&format!("{}/resources/aruco_board.example.json", env!("CARGO_MANIFEST_DIR")), ArucoDictionary { bits: 5, size: 50 }, 20.).unwrap();
+        assert_eq!(board.dictionary, ArucoDictionary::default());
+        assert_eq!(board.marker_mm(0), 38.);
+        assert_eq!(board.marker_mm(10), 50.);
+        // markers not in the file have the board size
+        assert_eq!(board.marker_mm(20), 38.);
+        assert_eq!(board.marker(1).unwrap().position_mm, Some([262., 0.]));
+        assert_eq!(board.marker(10).unwrap().position_mm, None);
+        assert_eq!(board.tag(11), Some(MarkerTag::Ingredient(IngredientMarker::Carrot)));
+        assert_eq!(board.tag(0), None);
+        assert_eq!(board.marker_mm(13), 25.);
+        assert_eq!(board.tags(), vec![
+            MarkerTag::Ingredient(IngredientMarker::Carrot),
+            MarkerTag::Ingredient(IngredientMarker::Potato),
+            MarkerTag::Tool(ToolMarker::Knife),
+        ]);
+    }
+
+    #[test]
+    fn marker_tag_parse_test() {
+        assert_eq!(serde_json::from_str::<MarkerTag>(r#""green_pepper""#).unwrap(), MarkerTag::Ingredient(IngredientMarker::GreenPepper));
+        assert_eq!(serde_json::from_str::<MarkerTag>(r#""cutting_board""#).unwrap(), MarkerTag::Tool(ToolMarker::CuttingBoard));
+        assert!(serde_json::from_str::<MarkerTag>(r#""spoon""#).is_err());
+        assert_eq!(serde_json::to_value(MarkerTag::Tool(ToolMarker::CuttingBoard)).unwrap(), serde_json::json!("cutting_board"));
+        assert_eq!(MarkerTag::Ingredient(IngredientMarker::Carrot).to_i18n().en, "the carrot tray");
+    }
+
+    #[test]
+    fn marker_board_validate_test() {
+        let marker = |id: i32, size_mm: Option<f32>| BoardMarker { id, size_mm, position_mm: None, tag: None };
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 38., vec![marker(0, None), marker(49, Some(50.))]).is_ok());
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 38., vec![marker(50, None)]).is_err());
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 38., vec![marker(1, None), marker(1, None)]).is_err());
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 38., vec![marker(1, Some(0.))]).is_err());
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 0., vec![]).is_err());
+        // a tagged marker moves with its tray and cannot be placed on the board
+        let placed_tag = BoardMarker { id: 5, size_mm: None, position_mm: Some([0., 0.]), tag: Some(MarkerTag::Tool(ToolMarker::Knife)) };
+        assert!(MarkerBoard::new(ArucoDictionary::default(), 38., vec![placed_tag]).is_err());
+    }
+
+    #[test]
+    fn ko_with_particle_test() {
+        assert_eq!(ko_with_particle("칼", "을", "를"), "칼을");
+        assert_eq!(ko_with_particle("도마", "을", "를"), "도마를");
+        assert_eq!(ko_with_particle("당근 트레이", "이", "가"), "당근 트레이가");
+        assert_eq!(ko_with_particle("냄비", "이", "가"), "냄비가");
+        assert_eq!(ko_with_particle("그릇", "이", "가"), "그릇이");
+        assert_eq!(ko_with_particle("ABC", "이", "가"), "ABC이(가)");
+    }
+}
@@ -1,4 +1,7 @@
+use serde::Deserialize;
 use crate::smart_speaker::models::core_model::SmartSpeakerState;
+use crate::smart_speaker::models::frame_model::SharedFrame;
+use crate::smart_speaker::models::gaze_model::{Fixation, GazeSample};
 use crate::smart_speaker::models::intent_model::{IntentAction, IntentSlot};
 use crate::smart_speaker::models::vision_model::{VisionAction, VisionSlot};
 use crate::utils::config_util::LanguageTag;
@@ -10,6 +13,7 @@ pub(crate) enum SmartSpeakerActors {
     ContextActor,
     CoreActor,
     GazeActor,
+    InputActor,
     LoggerActor,
     MachineSpeechActor,
     QueryActor,
@@ -28,6 +32,7 @@ impl std::fmt::Display for SmartSpeakerActors {
             SmartSpeakerActors::ContextActor => write!(f, "ContextActor"),
             SmartSpeakerActors::CoreActor => write!(f, "CoreActor"),
             SmartSpeakerActors::GazeActor => write!(f, "GazeActor"),
+            SmartSpeakerActors::InputActor => write!(f, "InputActor"),
             SmartSpeakerActors::LoggerActor => write!(f, "LoggerActor"),
             SmartSpeakerActors::MachineSpeechActor => write!(f, "MachineSpeechActor"),
             SmartSpeakerActors::QueryActor => write!(f, "QueryActor"),
@@ -53,6 +58,8 @@ pub(crate) enum SmartSpeakerMessage {
     RequestCameraFrame(CameraFrameMessage),
     RequestGazeInfo(GazeInfoMessage),
     ReportTerminated(ReportTerminated),
+    ReportActorRestarted(ActorRestartedMessage),
+    Heartbeat(HeartbeatMessage),
     RequestQuery(QueryMessage),
     RequestShutdown(ShutdownMessage),
     RequestStateUpdate(StateUpdateMessage),
@@ -90,15 +97,18 @@ pub(crate) struct AudioStreamMessage {
 pub(crate) struct CameraFrameMessage {
     pub send_from: SmartSpeakerActors,
     pub send_to: SmartSpeakerActors,
-    pub frame_data_bytes: Vec<u8>,
-    pub height: i32,
+    /// None in a request. The reply shares the latest frame of the camera actor.
+    pub frame: Option<SharedFrame>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct GazeInfoMessage {
     pub send_from: SmartSpeakerActors,
     pub send_to: SmartSpeakerActors,
-    pub gaze_info: (f32, f32)
+    /// None in a request. The reply is the latest sample of the gaze actor.
+    pub gaze_info: Option<GazeSample>,
+    /// The fixation in progress when the reply was sent.
+    pub fixation: Option<Fixation>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -107,6 +117,21 @@ pub(crate) struct ReportTerminated {
     pub send_to: SmartSpeakerActors,
 }
 
+/// The supervisor restarted `actor`, or gave up on it if `degraded`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ActorRestartedMessage {
+    pub send_from: SmartSpeakerActors,
+    pub send_to: SmartSpeakerActors,
+    pub actor: SmartSpeakerActors,
+    pub cause: String,
+    pub degraded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct HeartbeatMessage {
+    pub send_from: SmartSpeakerActors,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct QueryMessage {
     pub send_from: SmartSpeakerActors,
@@ -137,7 +162,7 @@ pub(crate) enum TextToSpeechMessageType {
     Boilerplate(usize),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub(crate) struct SmartSpeakerI18nText {
     pub en: String,
     pub ja: String,
@@ -287,4 +312,6 @@ impl VisionContent {
 pub(crate) enum ProcessResult {
     Success,
     Failure,
+    /// Several candidates and none clearly meant. The user is asked to clarify.
+    Unsure,
 }
@@ -1,37 +1,96 @@
 use pv_recorder::{PvRecorder, PvRecorderBuilder, };
 use cobra::{Cobra};
-use rhino::{Rhino, RhinoBuilder, RhinoInference};
+use rhino::{Rhino, RhinoBuilder};
 use porcupine::{Porcupine, PorcupineBuilder, BuiltinKeywords};
 // use cheetah::{Cheetah, CheetahBuilder};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 use anyhow::{anyhow, Result};
-use crate::utils::config_util::LanguageTag;
+use crate::smart_speaker::models::intent_model::{IntentAction, IntentCookingMenu};
+use crate::smart_speaker::models::message_model::IntentContent;
+use crate::utils::audio_util::{frame_energy_db, zero_crossing_rate, WavFile, FRAME_LENGTH, SAMPLE_RATE};
+use crate::utils::camera_util::EndOfStream;
+use crate::utils::config_util::{Config, LanguageTag};
 
-#[derive(Clone)]
+/// Silence streamed after the end of a wav file, so that the last utterance is finished before the end of stream.
+pub(crate) const AUDIO_FILE_TAIL: Duration = Duration::from_secs(3);
+
+/// An audio listener reads 16kHz i16 frames from an audio source, such as a microphone or a wav file.
 pub(crate) struct AudioListener {
-    pub recorder: PvRecorder,
+    pub source: Box<dyn AudioSource + Send + 'static>,
 }
 
 impl AudioListener {
     pub fn new(mic_index: u32) -> Self {
         Self {
-            recorder: PvRecorderBuilder::default().device_index(mic_index as i32).init().unwrap(),
+            source: Box::new(MicAudioSource::new(mic_index)),
         }
     }
 
+    pub fn from_wav_file(path: &str, realtime: bool) -> Result<Self> {
+        Ok(Self {
+            source: Box::new(WavFileAudioSource::new(WavFile::open(path)?, realtime)?),
+        })
+    }
+
     pub fn info(&mut self) -> String {
-        let audio_devices = PvRecorderBuilder::default().get_available_devices().unwrap();
-        format!("{:?}", audio_devices).to_string()
+        self.source.info()
     }
 
     pub fn start(&mut self) {
-        self.recorder.start().expect("failed to start recording");
+        self.source.start().expect("failed to start recording");
     }
 
     pub fn stop(&mut self) {
-        self.recorder.stop().expect("failed to stop recording");
+        self.source.stop().expect("failed to stop recording");
     }
 
     pub fn update(&mut self) -> Result<Vec<i16>> {
+        self.source.read()
+    }
+}
+
+pub(crate) trait AudioSource {
+    fn info(&mut self) -> String;
+    fn start(&mut self) -> Result<()>;
+    fn stop(&mut self) -> Result<()>;
+    fn read(&mut self) -> Result<Vec<i16>>;
+}
+
+/// Names of the recording devices. The position is the `--mic-index`.
+pub(crate) fn list_audio_devices() -> Result<Vec<String>> {
+    PvRecorderBuilder::default().get_available_devices()
+        .map_err(|e| anyhow!("failed to list audio devices: {:?}", e))
+}
+
+pub(crate) struct MicAudioSource {
+    pub recorder: PvRecorder,
+}
+
+impl MicAudioSource {
+    pub fn new(mic_index: u32) -> Self {
+        Self {
+            recorder: PvRecorderBuilder::default().device_index(mic_index as i32).init().unwrap(),
+        }
+    }
+}
+
+impl AudioSource for MicAudioSource {
+    fn info(&mut self) -> String {
+        let audio_devices = PvRecorderBuilder::default().get_available_devices().unwrap();
+        format!("{:?}", audio_devices).to_string()
+    }
+
+    fn start(&mut self) -> Result<()> {
+        self.recorder.start().map_err(|_| anyhow!("failed to start recording"))
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.recorder.stop().map_err(|_| anyhow!("failed to stop recording"))
+    }
+
+    fn read(&mut self) -> Result<Vec<i16>> {
         match self.recorder.read() {
             Ok(pcm) => Ok(pcm),
             Err(_) => Err(anyhow!("failed to read audio frame"))
@@ -39,50 +98,277 @@ impl AudioListener {
     }
 }
 
+/// Streams a wav file frame by frame. After the end of the file, `AUDIO_FILE_TAIL` of silent frames is returned,
+/// then `EndOfStream`. If realtime is false, frames are returned as fast as they are read.
+pub(crate) struct WavFileAudioSource {
+    wav: WavFile,
+    realtime: bool,
+    position: usize,
+    started_at: Option<Instant>,
+    frames_read: u32,
+}
+
+impl WavFileAudioSource {
+    pub fn new(wav: WavFile, realtime: bool) -> Result<Self> {
+        if wav.sample_rate != SAMPLE_RATE {
+            return Err(anyhow!("wav sample rate must be {}Hz, got {}Hz", SAMPLE_RATE, wav.sample_rate));
+        }
+        Ok(Self {
+            wav,
+            realtime,
+            position: 0,
+            started_at: None,
+            frames_read: 0,
+        })
+    }
+}
+
+impl AudioSource for WavFileAudioSource {
+    fn info(&mut self) -> String {
+        format!("wav file: {:.2}s, realtime: {}", self.wav.duration_secs(), self.realtime).to_string()
+    }
+
+    fn start(&mut self) -> Result<()> {
+        self.started_at = Some(Instant::now());
+        self.frames_read = 0;
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<()> {
+        self.started_at = None;
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Vec<i16>> {
+        let started_at = self.started_at.ok_or(anyhow!("wav file source is not started"))?;
+        let tail = (AUDIO_FILE_TAIL.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        if self.position >= self.wav.samples.len() + tail {
+            return Err(EndOfStream.into());
+        }
+        if self.realtime {
+            // same blocking behaviour as the recorder: wait until the frame would have been recorded.
+            let due = Duration::from_secs_f64(
+                (self.frames_read + 1) as f64 * FRAME_LENGTH as f64 / SAMPLE_RATE as f64);
+            let elapsed = started_at.elapsed();
+            if due > elapsed {
+                thread::sleep(due - elapsed);
+            }
+        }
+        let end = (self.position + FRAME_LENGTH).min(self.wav.samples.len());
+        let mut frame = self.wav.samples[self.position.min(end)..end].to_vec();
+        frame.resize(FRAME_LENGTH, 0);
+        self.position += FRAME_LENGTH;
+        self.frames_read += 1;
+        Ok(frame)
+    }
+}
+
+/// Backend of the speech engines. Each stage (wake word, voice activity, intent) is built from it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SpeechEngineType {
+    Picovoice,
+}
+
+impl FromStr for SpeechEngineType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "picovoice" => Ok(SpeechEngineType::Picovoice),
+            "pv" => Ok(SpeechEngineType::Picovoice),
+            _ => Err(anyhow!("invalid speech engine")),
+        }
+    }
+}
+
+/// Backend of the voice activity detection. `Energy` runs offline without an api key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum VadEngineType {
+    Picovoice,
+    Energy,
+}
+
+impl FromStr for VadEngineType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "picovoice" => Ok(VadEngineType::Picovoice),
+            "pv" => Ok(VadEngineType::Picovoice),
+            "energy" => Ok(VadEngineType::Energy),
+            _ => Err(anyhow!("invalid vad engine")),
+        }
+    }
+}
+
+pub(crate) trait WakeWordEngine {
+    fn info(&mut self) -> String;
+    /// True if the wake word is in the frame.
+    fn detect(&mut self, pcm: &Vec<i16>) -> Result<bool>;
+}
+
+pub(crate) trait VadEngine {
+    fn info(&mut self) -> String;
+    /// Voice probability of the frame. 0.0 to 1.0.
+    fn detect(&mut self, pcm: &Vec<i16>) -> Result<f32>;
+}
+
+pub(crate) trait IntentEngine {
+    fn info(&mut self) -> String;
+    /// Feed a frame. True when the end of the utterance is reached and the inference is ready.
+    fn process(&mut self, pcm: &Vec<i16>) -> Result<bool>;
+    /// None if the utterance was not understood.
+    fn get_inference(&mut self) -> Result<Option<IntentContent>>;
+}
+
 pub(crate) struct WakeWordDetector {
-    pub app: Porcupine,
+    pub engine: Box<dyn WakeWordEngine + Send + 'static>,
 }
 
 impl WakeWordDetector {
-    pub fn new(api_key: String, context_path: String, language: LanguageTag) -> Self {
-        if context_path == "default" {
-            Self {
-                app: PorcupineBuilder::new_with_keywords(api_key,
-                                                         &[BuiltinKeywords::Jarvis, BuiltinKeywords::Alexa]).init().unwrap(),
+    pub fn new(engine: Box<dyn WakeWordEngine + Send + 'static>) -> Self {
+        Self {
+            engine,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.speech_engine {
+            SpeechEngineType::Picovoice => {
+                let model_path = if config.pico_voice_porcupine_params_path.is_empty() {
+                    PorcupineWakeWordEngine::default_model_path(&config.language)
+                } else {
+                    config.pico_voice_porcupine_params_path.clone()
+                };
+                Ok(Self::new(Box::new(PorcupineWakeWordEngine::new(config.pico_voice_api_key.clone(),
+                                                                   config.pico_voice_ppn_model_path.clone(),
+                                                                   model_path)?)))
             }
-        } else {
-            match language {
-                LanguageTag::English => {
-                    Self {
-                        app: PorcupineBuilder::new_with_keyword_paths(api_key, &[context_path]).model_path("picovoice_data/porcupine_params_en_v3_0_0.pv").init().unwrap(),
-                    }
-                }
-                LanguageTag::Japanese => {
-                    Self {
-                        app: PorcupineBuilder::new_with_keyword_paths(api_key, &[context_path]).model_path("picovoice_data/porcupine_params_ja_v3_0_0.pv").init().unwrap(),
-                    }
-                }
-                LanguageTag::Chinese => {
-                    Self {
-                        app: PorcupineBuilder::new_with_keyword_paths(api_key, &[context_path]).model_path("picovoice_data/porcupine_params_zh_v3_0_0.pv").init().unwrap(),
-                    }
-                }
-                LanguageTag::Korean => {
-                    Self {
-                        app: PorcupineBuilder::new_with_keyword_paths(api_key, &[context_path]).model_path("picovoice_data/porcupine_params_ko_v3_0_0.pv").init().unwrap(),
-                    }
-                }
+        }
+    }
+
+    pub fn info(&mut self) -> String {
+        self.engine.info()
+    }
+
+    pub fn detect(&mut self, pcm: &Vec<i16>) -> Result<bool> {
+        self.engine.detect(pcm)
+    }
+}
+
+pub(crate) struct VoiceActivityDetector {
+    pub engine: Box<dyn VadEngine + Send + 'static>,
+    /// Voice probability above which the frame is speech.
+    pub threshold: f32,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(engine: Box<dyn VadEngine + Send + 'static>, threshold: f32) -> Self {
+        Self {
+            engine,
+            threshold,
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.vad_engine {
+            VadEngineType::Picovoice => {
+                Ok(Self::new(Box::new(CobraVadEngine::new(config.pico_voice_api_key.clone())?), config.vad_threshold))
             }
+            VadEngineType::Energy => {
+                Ok(Self::new(Box::new(EnergyVadEngine::new(config.vad_energy_margin_db,
+                                                           config.vad_hangover_ms,
+                                                           config.vad_min_speech_ms)), config.vad_threshold))
+            }
+        }
+    }
+
+    pub fn info(&mut self) -> String {
+        self.engine.info()
+    }
 
+    pub fn detect(&mut self, pcm: &Vec<i16>) -> Result<f32> {
+        self.engine.detect(pcm)
+    }
+
+    pub fn is_speech(&mut self, pcm: &Vec<i16>) -> Result<bool> {
+        Ok(self.detect(pcm)? > self.threshold)
+    }
+}
+
+pub(crate) struct SpeechToIntent {
+    pub engine: Box<dyn IntentEngine + Send + 'static>,
+}
+
+impl SpeechToIntent {
+    pub fn new(engine: Box<dyn IntentEngine + Send + 'static>) -> Self {
+        Self {
+            engine,
         }
+    }
 
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.speech_engine {
+            SpeechEngineType::Picovoice => {
+                let model_path = if config.pico_voice_rhino_params_path.is_empty() {
+                    RhinoIntentEngine::default_model_path(&config.language)
+                } else {
+                    config.pico_voice_rhino_params_path.clone()
+                };
+                Ok(Self::new(Box::new(RhinoIntentEngine::new(config.pico_voice_api_key.clone(),
+                                                             config.pico_voice_rhn_model_path.clone(),
+                                                             model_path)?)))
+            }
+        }
     }
 
     pub fn info(&mut self) -> String {
+        self.engine.info()
+    }
+
+    pub fn process(&mut self, pcm: &Vec<i16>) -> Result<bool> {
+        self.engine.process(pcm)
+    }
+
+    pub fn get_inference(&mut self) -> Result<Option<IntentContent>> {
+        self.engine.get_inference()
+    }
+}
+
+/// Picovoice Porcupine.
+pub(crate) struct PorcupineWakeWordEngine {
+    pub app: Porcupine,
+}
+
+impl PorcupineWakeWordEngine {
+    /// Bundled parameter file of the language.
+    pub fn default_model_path(language: &LanguageTag) -> String {
+        match language {
+            LanguageTag::English => "picovoice_data/porcupine_params_en_v3_0_0.pv",
+            LanguageTag::Japanese => "picovoice_data/porcupine_params_ja_v3_0_0.pv",
+            LanguageTag::Chinese => "picovoice_data/porcupine_params_zh_v3_0_0.pv",
+            LanguageTag::Korean => "picovoice_data/porcupine_params_ko_v3_0_0.pv",
+        }.to_string()
+    }
+
+    pub fn new(api_key: String, context_path: String, model_path: String) -> Result<Self> {
+        let app = if context_path == "default" {
+            PorcupineBuilder::new_with_keywords(api_key, &[BuiltinKeywords::Jarvis, BuiltinKeywords::Alexa]).init()
+        } else {
+            PorcupineBuilder::new_with_keyword_paths(api_key, &[context_path]).model_path(&model_path).init()
+        };
+        Ok(Self {
+            app: app.map_err(|e| anyhow!("failed to init porcupine: {:?}", e))?,
+        })
+    }
+}
+
+impl WakeWordEngine for PorcupineWakeWordEngine {
+    fn info(&mut self) -> String {
         self.app.version().to_string()
     }
 
-    pub fn detect(&mut self, pcm: &Vec<i16>) -> Result<bool> {
+    fn detect(&mut self, pcm: &Vec<i16>) -> Result<bool> {
         match self.app.process(pcm) {
             Ok(keyword_index) => {
                 if keyword_index == -1 {
@@ -96,22 +382,25 @@ impl WakeWordDetector {
     }
 }
 
-pub(crate) struct VoiceActivityDetector {
+/// Picovoice Cobra.
+pub(crate) struct CobraVadEngine {
     pub app: Cobra,
 }
 
-impl VoiceActivityDetector {
-    pub fn new(api_key: String) -> Self {
-        Self {
-            app: Cobra::new(api_key).unwrap(),
-        }
+impl CobraVadEngine {
+    pub fn new(api_key: String) -> Result<Self> {
+        Ok(Self {
+            app: Cobra::new(api_key).map_err(|e| anyhow!("failed to init cobra: {:?}", e))?,
+        })
     }
+}
 
-    pub fn info(&mut self) -> String {
+impl VadEngine for CobraVadEngine {
+    fn info(&mut self) -> String {
         self.app.version().to_string()
     }
 
-    pub fn detect(&mut self, pcm: &Vec<i16>) -> Result<f32> {
+    fn detect(&mut self, pcm: &Vec<i16>) -> Result<f32> {
         match self.app.process(pcm) {
             Ok(probability) => Ok(probability),
             Err(_) => Err(anyhow!("failed to process audio frame"))
@@ -119,55 +408,148 @@ impl VoiceActivityDetector {
     }
 }
 
-pub(crate) struct SpeechToIntent {
-    pub app: Rhino,
+/// Short-time energy and zero-crossing rate against an adaptive noise floor.
+/// A frame is voiced when its energy is `margin_db` above the noise floor and the zero-crossing rate is not that of hiss.
+/// Speech starts after `min_speech_ms` of voiced frames and is held for `hangover_ms` after the last one.
+pub(crate) struct EnergyVadEngine {
+    margin_db: f32,
+    hangover_ms: u32,
+    min_speech_ms: u32,
+    noise_floor_db: Option<f32>,
+    voiced_ms: u32,
+    unvoiced_ms: u32,
+    speaking: bool,
 }
 
-impl SpeechToIntent {
-    pub fn new(api_key: String, context_path: String, language: LanguageTag) -> Self {
-        match language {
-            LanguageTag::English => {
-                Self {
-                    app: RhinoBuilder::new(api_key, context_path).model_path("picovoice_data/rhino_params_en_v3_0_0.pv").init().unwrap(),
-                }
-            }
-            LanguageTag::Japanese => {
-                Self {
-                    app: RhinoBuilder::new(api_key, context_path).model_path("picovoice_data/rhino_params_ja_v3_0_0.pv").init().unwrap(),
-                }
-            }
-            LanguageTag::Chinese => {
-                Self {
-                    app: RhinoBuilder::new(api_key, context_path).model_path("picovoice_data/rhino_params_zh_v3_0_0.pv").init().unwrap(),
-                }
+impl EnergyVadEngine {
+    /// Weight of the current frame when the noise floor rises.
+    const NOISE_ADAPT_RATE: f32 = 0.05;
+    /// Lowest noise floor, so that digital silence does not make every sound voiced.
+    const MIN_NOISE_FLOOR_DB: f32 = -70.;
+    /// White noise crosses zero about every other sample. Voice stays well below.
+    const MAX_VOICED_ZCR: f32 = 0.35;
+
+    pub fn new(margin_db: f32, hangover_ms: u32, min_speech_ms: u32) -> Self {
+        Self {
+            margin_db,
+            hangover_ms,
+            min_speech_ms,
+            noise_floor_db: None,
+            voiced_ms: 0,
+            unvoiced_ms: 0,
+            speaking: false,
+        }
+    }
+
+    pub fn noise_floor_db(&self) -> Option<f32> {
+        self.noise_floor_db
+    }
+
+    fn is_voiced(&self, energy_db: f32, zcr: f32) -> bool {
+        match self.noise_floor_db {
+            Some(noise_floor_db) => energy_db > noise_floor_db + self.margin_db && zcr < Self::MAX_VOICED_ZCR,
+            None => false,
+        }
+    }
+
+    fn update_noise_floor(&mut self, energy_db: f32) {
+        let energy_db = energy_db.max(Self::MIN_NOISE_FLOOR_DB);
+        self.noise_floor_db = Some(match self.noise_floor_db {
+            // falls immediately, rises slowly
+            Some(noise_floor_db) if energy_db < noise_floor_db => energy_db,
+            Some(noise_floor_db) => noise_floor_db + (energy_db - noise_floor_db) * Self::NOISE_ADAPT_RATE,
+            None => energy_db,
+        });
+    }
+}
+
+impl VadEngine for EnergyVadEngine {
+    fn info(&mut self) -> String {
+        format!("energy vad: margin {}dB, hangover {}ms, min speech {}ms",
+                self.margin_db, self.hangover_ms, self.min_speech_ms).to_string()
+    }
+
+    fn detect(&mut self, pcm: &Vec<i16>) -> Result<f32> {
+        if pcm.is_empty() {
+            return Err(anyhow!("empty audio frame"))
+        }
+        let frame_ms = (pcm.len() as u64 * 1000 / SAMPLE_RATE as u64) as u32;
+        let energy_db = frame_energy_db(pcm);
+        if self.is_voiced(energy_db, zero_crossing_rate(pcm)) {
+            self.voiced_ms += frame_ms;
+            self.unvoiced_ms = 0;
+            if self.voiced_ms >= self.min_speech_ms {
+                self.speaking = true;
             }
-            LanguageTag::Korean => {
-                Self {
-                    app: RhinoBuilder::new(api_key, context_path).model_path("picovoice_data/rhino_params_ko_v3_0_0.pv").init().unwrap(),
-                }
+        } else {
+            self.unvoiced_ms += frame_ms;
+            if !self.speaking || self.unvoiced_ms > self.hangover_ms {
+                self.speaking = false;
+                self.voiced_ms = 0;
             }
+            self.update_noise_floor(energy_db);
         }
+        Ok(if self.speaking { 1. } else { 0. })
     }
+}
 
-    pub fn info(&mut self) -> String {
+/// Picovoice Rhino. Intents and `menu_name` slots of the context are mapped to `IntentContent`.
+pub(crate) struct RhinoIntentEngine {
+    pub app: Rhino,
+}
+
+impl RhinoIntentEngine {
+    /// Bundled parameter file of the language.
+    pub fn default_model_path(language: &LanguageTag) -> String {
+        match language {
+            LanguageTag::English => "picovoice_data/rhino_params_en_v3_0_0.pv",
+            LanguageTag::Japanese => "picovoice_data/rhino_params_ja_v3_0_0.pv",
+            LanguageTag::Chinese => "picovoice_data/rhino_params_zh_v3_0_0.pv",
+            LanguageTag::Korean => "picovoice_data/rhino_params_ko_v3_0_0.pv",
+        }.to_string()
+    }
+
+    pub fn new(api_key: String, context_path: String, model_path: String) -> Result<Self> {
+        Ok(Self {
+            app: RhinoBuilder::new(api_key, context_path).model_path(&model_path).init()
+                .map_err(|e| anyhow!("failed to init rhino: {:?}", e))?,
+        })
+    }
+}
+
+impl IntentEngine for RhinoIntentEngine {
+    fn info(&mut self) -> String {
         self.app.context_info().to_string()
     }
 
-    pub fn get_inference(&mut self) -> Result<Option<RhinoInference>> {
-        // TODO: Change return type as enum
-        if let Ok(inference) = self.app.get_inference() {
-            return if inference.is_understood {
-                dbg!(&inference.intent);
-                dbg!(&inference.slots);
-                Ok(
-                    Some(inference)
-                )
-            } else {
-                Ok(None)
+    fn process(&mut self, pcm: &Vec<i16>) -> Result<bool> {
+        self.app.process(pcm).map_err(|_| anyhow!("failed to process audio frame"))
+    }
+
+    fn get_inference(&mut self) -> Result<Option<IntentContent>> {
+        let inference = match self.app.get_inference() {
+            Ok(inference) => inference,
+            Err(_) => return Ok(None),
+        };
+        if !inference.is_understood {
+            return Ok(None)
+        }
+        dbg!(&inference.intent);
+        dbg!(&inference.slots);
+        let intent = inference.intent.ok_or(anyhow!("failed to parse intent: None"))?;
+        let action = IntentAction::from_str(&intent).map_err(|_| anyhow!("failed to parse intent: {}", intent))?;
+        let mut content = IntentContent::new(action, vec![]);
+        for (key, value) in inference.slots {
+            match key.as_str() {
+                "menu_name" => {
+                    if let Ok(menu) = IntentCookingMenu::from_str(&value) {
+                        content.entities.push(Box::new(menu));
+                    }
+                }
+                &_ => {}
             }
-        } else {
-            Ok(None)
         }
+        Ok(Some(content))
     }
 }
 
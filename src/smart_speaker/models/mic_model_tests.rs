@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod mic_model_tests {
+    use std::f32::consts::PI;
+    use std::str::FromStr;
+    use crate::utils::audio_util::{write_wav, FRAME_LENGTH, SAMPLE_RATE};
+    use crate::utils::camera_util::is_end_of_stream;
+    use super::super::mic_model::*;
+
+    /// Low level noise from a fixed seed.
+    fn noise_frame(seed: &mut u32, amplitude: i16) -> Vec<i16> {
+        (0..FRAME_LENGTH).map(|_| {
+            *seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            ((*seed >> 16) as i32 % (amplitude as i32 * 2 + 1) - amplitude as i32) as i16
+        }).collect()
+    }
+
+    fn tone_frame(index: usize, frequency: f32, amplitude: f32) -> Vec<i16> {
+        (0..FRAME_LENGTH).map(|i| {
+            let t = (index * FRAME_LENGTH + i) as f32 / SAMPLE_RATE as f32;
+            (amplitude * (2. * PI * frequency * t).sin()) as i16
+        }).collect()
+    }
+
+    #[test]
+    fn vad_engine_type_parse_test() {
+        assert_eq!(VadEngineType::from_str("energy").unwrap(), VadEngineType::Energy);
+        assert_eq!(VadEngineType::from_str("PV").unwrap(), VadEngineType::Picovoice);
+        assert!(VadEngineType::from_str("webrtc").is_err());
+    }
+
+    #[test]
+    fn energy_vad_silence_test() {
+        let mut vad = EnergyVadEngine::new(12., 300, 100);
+        let mut seed = 1;
+        for _ in 0..50 {
+            assert_eq!(vad.detect(&noise_frame(&mut seed, 30)).unwrap(), 0.);
+        }
+        assert!(vad.noise_floor_db().unwrap() < -60.);
+    }
+
+    #[test]
+    fn energy_vad_min_speech_and_hangover_test() {
+        // 512 samples are 32ms
+        let mut vad = EnergyVadEngine::new(12., 300, 100);
+        let mut seed = 1;
+        for _ in 0..10 {
+            vad.detect(&noise_frame(&mut seed, 30)).unwrap();
+        }
+        let speech = (0..6).map(|i| vad.detect(&tone_frame(i, 200., 8000.)).unwrap()).collect::<Vec<f32>>();
+        assert_eq!(speech, vec![0., 0., 0., 1., 1., 1.]);
+        let hangover = (0..10).map(|_| vad.detect(&noise_frame(&mut seed, 30)).unwrap()).collect::<Vec<f32>>();
+        assert_eq!(hangover, vec![1., 1., 1., 1., 1., 1., 1., 1., 1., 0.]);
+    }
+
+    #[test]
+    fn energy_vad_short_click_test() {
+        let mut vad = EnergyVadEngine::new(12., 300, 100);
+        let mut seed = 1;
+        for _ in 0..10 {
+            vad.detect(&noise_frame(&mut seed, 30)).unwrap();
+        }
+        assert_eq!(vad.detect(&tone_frame(0, 200., 8000.)).unwrap(), 0.);
+        assert_eq!(vad.detect(&noise_frame(&mut seed, 30)).unwrap(), 0.);
+        assert_eq!(vad.detect(&tone_frame(1, 200., 8000.)).unwrap(), 0.);
+    }
+
+    #[test]
+    fn energy_vad_hiss_test() {
+        let mut vad = EnergyVadEngine::new(12., 300, 100);
+        let mut seed = 1;
+        for _ in 0..10 {
+            vad.detect(&noise_frame(&mut seed, 30)).unwrap();
+        }
+        for _ in 0..10 {
+            assert_eq!(vad.detect(&noise_frame(&mut seed, 8000)).unwrap(), 0.);
+        }
+    }
+
+    #[test]
+    fn voice_activity_detector_threshold_test() {
+        let mut detector = VoiceActivityDetector::new(Box::new(EnergyVadEngine::new(12., 300, 0)), 0.5);
+        let mut seed = 1;
+        assert!(!detector.is_speech(&noise_frame(&mut seed, 30)).unwrap());
+        assert!(detector.is_speech(&tone_frame(0, 200., 8000.)).unwrap());
+    }
+
+    #[test]
+    fn wav_file_audio_source_end_of_stream_test() {
+        let path = std::env::temp_dir().join("vas_wav_file_audio_source.wav");
+        let path = path.to_str().unwrap();
+        write_wav(path, SAMPLE_RATE, &vec![100i16; FRAME_LENGTH * 2 + 10]).unwrap();
+        let mut listener = AudioListener::from_wav_file(path, false).unwrap();
+        listener.start();
+        let mut frames = vec![];
+        let error = loop {
+            match listener.update() {
+                Ok(frame) => frames.push(frame),
+                Err(e) => break e,
+            }
+        };
+        assert!(is_end_of_stream(&error));
+        assert!(is_end_of_stream(&listener.update().unwrap_err()));
+        let tail = (AUDIO_FILE_TAIL.as_secs_f64() * SAMPLE_RATE as f64) as usize;
+        assert_eq!(frames.len(), (FRAME_LENGTH * 2 + 10 + tail + FRAME_LENGTH - 1) / FRAME_LENGTH);
+        assert_eq!(frames[2][9], 100);
+        assert_eq!(frames[2][10], 0);
+        assert!(frames.iter().all(|frame| frame.len() == FRAME_LENGTH));
+        let _ = std::fs::remove_file(path);
+    }
+}
@@ -1,13 +1,23 @@
 pub(crate) mod vision_model;
+pub(crate) mod frame_model;
+pub(crate) mod frame_model_tests;
 pub(crate) mod gaze_model;
+pub(crate) mod gaze_model_tests;
 pub(crate) mod mic_model;
+pub(crate) mod mic_model_tests;
 pub(crate) mod debug_model;
 pub(crate) mod intent_model;
 pub(crate) mod speak_model;
+pub(crate) mod speak_model_tests;
 pub(crate) mod marker_model;
+pub(crate) mod marker_model_tests;
 pub(crate) mod context_model;
 pub(crate) mod core_model;
 pub(crate) mod task_model;
 pub(crate) mod step_model;
 pub(crate) mod message_model;
 pub(crate) mod revision_model;
+pub(crate) mod recipe_model;
+pub(crate) mod recipe_model_tests;
+pub(crate) mod session_model;
+pub(crate) mod session_model_tests;
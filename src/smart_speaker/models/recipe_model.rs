@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use crate::smart_speaker::models::intent_model::IntentCookingMenu;
+use crate::smart_speaker::models::marker_model::MarkerTag;
+use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
+use crate::smart_speaker::models::task_model::cooking_task::{CookingIngredient, CookingIngredientAmount, CookingIngredientName};
+use crate::smart_speaker::models::vision_model::DetectionDetail;
+
+/// Recipes loaded from the recipe directory (one JSON file per dish).
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct RecipeCatalog {
+    pub(crate) recipes: Vec<Recipe>,
+}
+
+impl RecipeCatalog {
+    pub(crate) fn new(recipes: Vec<Recipe>) -> Self {
+        Self {
+            recipes,
+        }
+    }
+
+    /// Load every `*.json` file in `path`. `path` may also point to a single recipe file.
+    pub(crate) fn load(path: &str) -> Result<Self> {
+        let path = Path::new(path);
+        let mut recipes = vec![];
+        if path.is_file() {
+            recipes.push(Recipe::load(path)?);
+        } else {
+            let mut entries = fs::read_dir(path)
+                .map_err(|e| anyhow!("failed to read recipe directory {}: {}", path.display(), e))?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|p| p.extension().map_or(false, |ext| ext == "json"))
+                .collect::<Vec<_>>();
+            entries.sort();
+            for entry in entries {
+                recipes.push(Recipe::load(&entry)?);
+            }
+        }
+        let catalog = Self::new(recipes);
+        catalog.validate()?;
+        Ok(catalog)
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        for (i, recipe) in self.recipes.iter().enumerate() {
+            recipe.validate()?;
+            if self.recipes[..i].iter().any(|r| r.id == recipe.id) {
+                return Err(anyhow!("duplicated recipe id: {}", recipe.id));
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn find(&self, menu: &IntentCookingMenu) -> Option<&Recipe> {
+        self.recipes.iter().find(|recipe| recipe.is_called(&menu.name))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct Recipe {
+    pub(crate) id: String,
+    pub(crate) name: SmartSpeakerI18nText,
+    /// Spoken variants of the menu name, e.g. the `menu_name` slot values of the rhino context.
+    #[serde(default)]
+    pub(crate) aliases: Vec<String>,
+    pub(crate) ingredients: Vec<RecipeIngredient>,
+    pub(crate) steps: Vec<RecipeStep>,
+}
+
+impl Recipe {
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let raw = fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read recipe {}: {}", path.display(), e))?;
+        let recipe: Recipe = serde_json::from_str(&raw)
+            .map_err(|e| anyhow!("failed to parse recipe {}: {}", path.display(), e))?;
+        Ok(recipe)
+    }
+
+    pub(crate) fn is_called(&self, name: &str) -> bool {
+        self.id == name
+            || self.aliases.iter().any(|alias| alias == name)
+            || [&self.name.en, &self.name.ja, &self.name.zh, &self.name.ko].iter().any(|n| n.as_str() == name)
+    }
+
+    pub(crate) fn to_i18n(&self) -> SmartSpeakerI18nText {
+        self.name.clone()
+    }
+
+    pub(crate) fn to_ingredient(&self) -> Result<Vec<CookingIngredient>> {
+        self.ingredients.iter().map(|i| i.to_cooking_ingredient(&[])).collect()
+    }
+
+    /// Resolve an ingredient referenced by a step. References without an amount use the recipe amount.
+    pub(crate) fn resolve_ingredient(&self, ingredient: &RecipeIngredient) -> Result<CookingIngredient> {
+        ingredient.to_cooking_ingredient(&self.to_ingredient()?)
+    }
+
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            return Err(anyhow!("recipe id is empty"));
+        }
+        let ingredients = self.to_ingredient()
+            .map_err(|e| anyhow!("recipe {}: {}", self.id, e))?;
+        for (i, step) in self.steps.iter().enumerate() {
+            step.validate(&ingredients)
+                .map_err(|e| anyhow!("recipe {} step {}: {}", self.id, i, e))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub(crate) struct RecipeIngredient {
+    pub(crate) name: CookingIngredientName,
+    /// Template code of `CookingIngredientAmount` (mg / ml / p / tbsp / tsp / cup).
+    #[serde(default)]
+    pub(crate) unit: Option<String>,
+    /// Amount in `unit`. Approximate units (p / tbsp / tsp / cup) accept quarters, e.g. 0.25.
+    #[serde(default)]
+    pub(crate) amount: Option<f32>,
+}
+
+impl RecipeIngredient {
+    pub(crate) fn to_cooking_ingredient(&self, recipe_ingredients: &[CookingIngredient]) -> Result<CookingIngredient> {
+        match (&self.unit, &self.amount) {
+            (Some(unit), Some(amount)) => {
+                Ok(CookingIngredient::new(
+                    self.name.clone(),
+                    CookingIngredientAmount::from_template_code(unit, *amount)?))
+            }
+            (None, None) => {
+                recipe_ingredients.iter()
+                    .find(|i| i.name == self.name)
+                    .cloned()
+                    .ok_or(anyhow!("ingredient {} is not in the recipe", self.name.to_template_code()))
+            }
+            _ => {
+                Err(anyhow!("ingredient {} needs both unit and amount", self.name.to_template_code()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub(crate) enum RecipeStep {
+    /// `ExplainRecipeAction`
+    Explain {
+        /// Only included when the assistant runs with vision.
+        #[serde(default)]
+        vision: bool,
+        #[serde(default)]
+        ingredients: Vec<RecipeIngredient>,
+        #[serde(default)]
+        detail: RecipeStepDetail,
+        script: SmartSpeakerI18nText,
+    },
+    /// `VisionBasedIngredientMeasureAction`. Always requires vision.
+    MeasureIngredient {
+        ingredients: Vec<RecipeIngredient>,
+        #[serde(default)]
+        detail: RecipeStepDetail,
+        detection: DetectionDetail,
+        script: SmartSpeakerI18nText,
+    },
+    /// `VisionBasedMarkerCheckAction`. Always requires vision. `{{marker_result}}` in the script tells
+    /// whether the marker tagged with `marker` is in view.
+    CheckMarker {
+        marker: MarkerTag,
+        script: SmartSpeakerI18nText,
+    },
+}
+
+impl RecipeStep {
+    pub(crate) fn requires_vision(&self) -> bool {
+        match self {
+            RecipeStep::Explain { vision, .. } => *vision,
+            RecipeStep::MeasureIngredient { .. } => true,
+            RecipeStep::CheckMarker { .. } => true,
+        }
+    }
+
+    fn validate(&self, recipe_ingredients: &[CookingIngredient]) -> Result<()> {
+        let (ingredients, detail) = match self {
+            RecipeStep::Explain { ingredients, detail, .. } => (ingredients, detail),
+            RecipeStep::MeasureIngredient { ingredients, detail, .. } => (ingredients, detail),
+            RecipeStep::CheckMarker { .. } => return Ok(()),
+        };
+        for ingredient in ingredients {
+            ingredient.to_cooking_ingredient(recipe_ingredients)?;
+        }
+        match detail {
+            RecipeStepDetail::ExplainMutableIngredient { main, components } => {
+                for name in std::iter::once(main).chain(components.iter()) {
+                    if !recipe_ingredients.iter().any(|i| &i.name == name) {
+                        return Err(anyhow!("ingredient {} is not in the recipe", name.to_template_code()));
+                    }
+                }
+            }
+            RecipeStepDetail::ExplainMutableTime { base, .. } => {
+                base.to_cooking_ingredient(recipe_ingredients)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// File representation of `CookingActionDetail`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum RecipeStepDetail {
+    #[default]
+    None,
+    ExplainNonMutableIngredient,
+    /// Amounts of `components` follow the revision of `main`.
+    ExplainMutableIngredient {
+        main: CookingIngredientName,
+        components: Vec<CookingIngredientName>,
+    },
+    /// `time` is in 1/10 minutes for `base`. (80 = 8min)
+    ExplainMutableTime {
+        base: RecipeIngredient,
+        time: u32,
+    },
+    MeasureIngredientSize,
+    MeasureCutIngredient,
+}
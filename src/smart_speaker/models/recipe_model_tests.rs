@@ -0,0 +1,80 @@
+#[cfg(test)]
+mod recipe_model_tests {
+    use std::str::FromStr;
+    use crate::smart_speaker::models::intent_model::IntentCookingMenu;
+    use crate::smart_speaker::models::marker_model::{IngredientMarker, MarkerTag};
+    use crate::smart_speaker::models::vision_model::VisionAction;
+    use crate::smart_speaker::models::step_model::cooking_step::CookingStepBuilder;
+    use crate::smart_speaker::models::task_model::cooking_task::CookingIngredientName;
+    use super::super::recipe_model::*;
+
+    fn recipe_path() -> String {
+        format!("{}/resources/recipes", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn recipe_catalog_load_test() {
+        let catalog = RecipeCatalog::load(&recipe_path()).unwrap();
+        assert_eq!(catalog.recipes.len(), 2);
+        let menu = IntentCookingMenu::from_str("ポテサラ").unwrap();
+        let recipe = menu.to_recipe(&catalog).unwrap();
+        assert_eq!(recipe.id, "potato_salad");
+        let ingredients = menu.to_ingredient(&catalog).unwrap();
+        assert_eq!(ingredients[0].name, CookingIngredientName::Potato);
+        assert_eq!(ingredients[0].unit.to_template_code(), "mg");
+        assert_eq!(ingredients[0].unit.get_value(), 1500.0);
+    }
+
+    #[test]
+    fn recipe_catalog_unknown_menu_test() {
+        let catalog = RecipeCatalog::load(&recipe_path()).unwrap();
+        let menu = IntentCookingMenu::from_str("カレー").unwrap();
+        assert!(menu.to_recipe(&catalog).is_err());
+    }
+
+    #[test]
+    fn recipe_step_build_test() {
+        let catalog = RecipeCatalog::load(&recipe_path()).unwrap();
+        let recipe = IntentCookingMenu::from_str("carrot salad").unwrap().to_recipe(&catalog).unwrap().clone();
+        let vision_steps = CookingStepBuilder::new(true).build(&recipe).unwrap();
+        let non_vision_steps = CookingStepBuilder::new(false).build(&recipe).unwrap();
+        // opening, ingredients, recipe steps, plating, closing
+        assert_eq!(vision_steps.len(), 4 + recipe.steps.len());
+        assert_eq!(non_vision_steps.len(), 4 + recipe.steps.iter().filter(|s| !s.requires_vision()).count());
+    }
+
+    #[test]
+    fn recipe_validate_unknown_ingredient_test() {
+        let recipe: Recipe = serde_json::from_str(r#"{
+            "id": "broken",
+            "name": { "en": "broken", "ja": "", "zh": "", "ko": "" },
+            "ingredients": [{ "name": "carrot", "unit": "mg", "amount": 100 }],
+            "steps": [{
+                "action": "explain",
+                "ingredients": [{ "name": "potato" }],
+                "script": { "en": "", "ja": "", "zh": "", "ko": "" }
+            }]
+        }"#).unwrap();
+        assert!(recipe.validate().is_err());
+    }
+
+    #[test]
+    fn recipe_check_marker_step_test() {
+        let recipe: Recipe = serde_json::from_str(r#"{
+            "id": "tray",
+            "name": { "en": "tray", "ja": "", "zh": "", "ko": "" },
+            "ingredients": [{ "name": "carrot", "unit": "mg", "amount": 100 }],
+            "steps": [{
+                "action": "check_marker",
+                "marker": "carrot",
+                "script": { "en": "{{marker_result}}", "ja": "", "zh": "", "ko": "" }
+            }]
+        }"#).unwrap();
+        assert!(recipe.validate().is_ok());
+        assert!(recipe.steps[0].requires_vision());
+        let steps = CookingStepBuilder::new(true).build(&recipe).unwrap();
+        let check = steps.iter().find(|step| step.try_expose_vision_actions().is_ok()).unwrap();
+        assert_eq!(check.try_expose_vision_actions().unwrap(),
+                   vec![VisionAction::MarkerPresence(MarkerTag::Ingredient(IngredientMarker::Carrot))]);
+    }
+}
@@ -0,0 +1,312 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use crate::smart_speaker::models::intent_model::{IntentCookingMenu, IntentPlace};
+use crate::smart_speaker::models::message_model::*;
+use crate::smart_speaker::models::vision_model::{VisionMarker, VisionObject};
+
+/// Longest time a recorded message waits in the buffer of the session file.
+const SESSION_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How frames and audio are written into the session file.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SessionMediaMode {
+    /// Only the size of the media is recorded.
+    Elide,
+    /// Media is appended to a sidecar file and referenced by byte offset.
+    Reference,
+}
+
+impl FromStr for SessionMediaMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "elide" => Ok(SessionMediaMode::Elide),
+            "none" => Ok(SessionMediaMode::Elide),
+            "reference" => Ok(SessionMediaMode::Reference),
+            "ref" => Ok(SessionMediaMode::Reference),
+            _ => Err(anyhow!("invalid session media mode")),
+        }
+    }
+}
+
+/// One line of the session file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct SessionRecord {
+    /// Seconds since the recording started.
+    pub elapsed: f64,
+    pub timestamp: String,
+    pub kind: String,
+    pub send_from: Option<String>,
+    pub send_to: Option<String>,
+    pub payload: Value,
+}
+
+struct SessionMediaFile {
+    name: String,
+    writer: BufWriter<File>,
+    offset: u64,
+}
+
+impl SessionMediaFile {
+    fn create(session_path: &str, suffix: &str) -> Result<Self> {
+        let path = format!("{}.{}", session_path, suffix);
+        let name = std::path::Path::new(&path).file_name()
+            .and_then(|n| n.to_str()).unwrap_or(&path).to_string();
+        Ok(Self {
+            name,
+            writer: BufWriter::new(File::create(&path)
+                .map_err(|e| anyhow!("failed to create session media file {}: {}", path, e))?),
+            offset: 0,
+        })
+    }
+
+    /// The bytes are flushed before the reference is returned, so a record never points past the end of the file.
+    fn append(&mut self, bytes: &[u8]) -> Result<Value> {
+        self.writer.write_all(bytes)?;
+        self.writer.flush()?;
+        let reference = json!({
+            "file": self.name,
+            "offset": self.offset,
+            "length": bytes.len(),
+        });
+        self.offset += bytes.len() as u64;
+        Ok(reference)
+    }
+}
+
+impl Drop for SessionMediaFile {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// Writes every message routed by the core actor to a JSON lines session file.
+/// The file is flushed every `SESSION_FLUSH_INTERVAL`, on `finish` and on drop.
+pub(crate) struct SessionRecorder {
+    writer: BufWriter<File>,
+    started_at: Instant,
+    flushed_at: Instant,
+    audio: Option<SessionMediaFile>,
+    frames: Option<SessionMediaFile>,
+}
+
+impl SessionRecorder {
+    pub(crate) fn new(path: &str, media: SessionMediaMode) -> Result<Self> {
+        let file = File::create(path).map_err(|e| anyhow!("failed to create session file {}: {}", path, e))?;
+        let (audio, frames) = match media {
+            SessionMediaMode::Elide => (None, None),
+            SessionMediaMode::Reference => (
+                Some(SessionMediaFile::create(path, "audio.raw")?),
+                Some(SessionMediaFile::create(path, "frames.raw")?),
+            ),
+        };
+        Ok(Self {
+            writer: BufWriter::new(file),
+            started_at: Instant::now(),
+            flushed_at: Instant::now(),
+            audio,
+            frames,
+        })
+    }
+
+    pub(crate) fn record(&mut self, message: &SmartSpeakerMessage) -> Result<()> {
+        let (kind, send_from, send_to, payload) = self.describe(message)?;
+        let record = SessionRecord {
+            elapsed: self.started_at.elapsed().as_secs_f64(),
+            timestamp: Local::now().to_rfc3339(),
+            kind: kind.to_string(),
+            send_from: send_from.map(|a| a.to_string()),
+            send_to: send_to.map(|a| a.to_string()),
+            payload,
+        };
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        self.flush_if_due()
+    }
+
+    /// Flush when the last flush is `SESSION_FLUSH_INTERVAL` ago. Call it while idle, too.
+    pub(crate) fn flush_if_due(&mut self) -> Result<()> {
+        if self.flushed_at.elapsed() >= SESSION_FLUSH_INTERVAL {
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    /// Write out everything recorded so far.
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        self.flushed_at = Instant::now();
+        self.writer.flush().map_err(|e| anyhow!("failed to write session file: {}", e))
+    }
+
+    fn describe<'a>(&mut self, message: &'a SmartSpeakerMessage)
+        -> Result<(&'static str, Option<&'a SmartSpeakerActors>, Option<&'a SmartSpeakerActors>, Value)> {
+        Ok(match message {
+            SmartSpeakerMessage::StringMessage(StringMessage { send_from, send_to, message }) => {
+                ("StringMessage", Some(send_from), Some(send_to), json!({ "message": message }))
+            }
+            SmartSpeakerMessage::RequestActorGenerate(ActorGenerateMessage { send_from, request }) => {
+                ("RequestActorGenerate", Some(send_from), None, json!({ "request": request.to_string() }))
+            }
+            SmartSpeakerMessage::RequestAudioStream(AudioStreamMessage { send_from, send_to, stream }) => {
+                let mut payload = json!({ "samples": stream.len() });
+                if let Some(audio) = self.audio.as_mut() {
+                    if !stream.is_empty() {
+                        let bytes = stream.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<u8>>();
+                        payload["ref"] = audio.append(&bytes)?;
+                    }
+                }
+                ("RequestAudioStream", Some(send_from), Some(send_to), payload)
+            }
+            SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage { send_from, send_to, frame }) => {
+                let mut payload = match frame {
+                    Some(frame) => {
+                        let header = frame.header();
+                        json!({
+                            "bytes": frame.data().len(),
+                            "width": header.width,
+                            "height": header.height,
+                            "channels": header.channels,
+                            "format": header.format.to_string(),
+                            "source": format!("{:?}", header.source),
+                            "sequence": header.sequence,
+                            // on the same clock as `elapsed`
+                            "captured": header.captured_at.saturating_duration_since(self.started_at).as_secs_f64(),
+                            "source_timestamp": header.source_timestamp,
+                        })
+                    }
+                    None => json!({ "bytes": 0 }),
+                };
+                if let (Some(frames), Some(frame)) = (self.frames.as_mut(), frame) {
+                    payload["ref"] = frames.append(frame.data())?;
+                }
+                ("RequestCameraFrame", Some(send_from), Some(send_to), payload)
+            }
+            SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage { send_from, send_to, gaze_info, fixation }) => {
+                let mut payload = match gaze_info {
+                    Some(sample) => json!({ "x": sample.x, "y": sample.y, "timestamp": sample.timestamp, "confidence": sample.confidence }),
+                    None => json!({}),
+                };
+                if let Some(fixation) = fixation {
+                    payload["fixation"] = json!({
+                        "x": fixation.x,
+                        "y": fixation.y,
+                        "duration": fixation.duration.as_secs_f64(),
+                        "timestamp": fixation.timestamp,
+                        "samples": fixation.samples,
+                    });
+                }
+                ("RequestGazeInfo", Some(send_from), Some(send_to), payload)
+            }
+            SmartSpeakerMessage::ReportTerminated(ReportTerminated { send_from, send_to }) => {
+                ("ReportTerminated", Some(send_from), Some(send_to), json!({}))
+            }
+            SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage { send_from, send_to, actor, cause, degraded }) => {
+                ("ReportActorRestarted", Some(send_from), Some(send_to),
+                 json!({ "actor": actor.to_string(), "cause": cause, "degraded": degraded }))
+            }
+            SmartSpeakerMessage::Heartbeat(HeartbeatMessage { send_from }) => {
+                ("Heartbeat", Some(send_from), None, json!({}))
+            }
+            SmartSpeakerMessage::RequestQuery(QueryMessage { send_from, send_to, message }) => {
+                ("RequestQuery", Some(send_from), Some(send_to), json!({ "message": message }))
+            }
+            SmartSpeakerMessage::RequestShutdown(ShutdownMessage {}) => {
+                ("RequestShutdown", None, None, json!({}))
+            }
+            SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage { send_from, send_to, state }) => {
+                ("RequestStateUpdate", Some(send_from), Some(send_to), json!({ "state": state.to_string() }))
+            }
+            SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage { send_from, send_to, message }) => {
+                let payload = match message {
+                    TextToSpeechMessageType::Normal(text) => i18n_text_to_json(text),
+                    TextToSpeechMessageType::Boilerplate(index) => json!({ "boilerplate": index }),
+                };
+                ("RequestTextToSpeech", Some(send_from), Some(send_to), payload)
+            }
+            SmartSpeakerMessage::RequestVisionAction(VisionActionMessage { send_from, send_to, actions }) => {
+                ("RequestVisionAction", Some(send_from), Some(send_to),
+                 json!({ "actions": actions.iter().map(|a| format!("{:?}", a)).collect::<Vec<String>>() }))
+            }
+            SmartSpeakerMessage::IntentFinalized(IntentFinalizedMessage { send_from, send_to, result, content }) => {
+                ("IntentFinalized", Some(send_from), Some(send_to), json!({
+                    "result": format!("{:?}", result),
+                    "intent": format!("{:?}", content.intent),
+                    "entities": content.entities.iter().map(|e| intent_slot_to_json(e)).collect::<Vec<Value>>(),
+                }))
+            }
+            SmartSpeakerMessage::VisionFinalized(VisionFinalizedMessage { send_from, send_to, result, contents }) => {
+                ("VisionFinalized", Some(send_from), Some(send_to), json!({
+                    "result": format!("{:?}", result),
+                    "contents": contents.iter().map(|c| json!({
+                        "action": format!("{:?}", c.action),
+                        "entities": c.entities.iter().map(|e| vision_slot_to_json(e)).collect::<Vec<Value>>(),
+                    })).collect::<Vec<Value>>(),
+                }))
+            }
+            SmartSpeakerMessage::TextToSpeechFinished(StringMessage { send_from, send_to, message }) => {
+                ("TextToSpeechFinished", Some(send_from), Some(send_to), json!({ "message": message }))
+            }
+            SmartSpeakerMessage::SubTaskStart(_) => {
+                ("SubTaskStart", None, None, json!({}))
+            }
+            SmartSpeakerMessage::SubTaskFinished(_) => {
+                ("SubTaskFinished", None, None, json!({}))
+            }
+            SmartSpeakerMessage::WriteLog(LogMessage { send_from, send_to, message }) => {
+                let (level, text) = match message {
+                    SmartSpeakerLogMessageType::Debug(text) => ("debug", text),
+                    SmartSpeakerLogMessageType::Info(text) => ("info", text),
+                    SmartSpeakerLogMessageType::Warn(text) => ("warn", text),
+                    SmartSpeakerLogMessageType::Error(text) => ("error", text),
+                };
+                ("WriteLog", Some(send_from), Some(send_to), json!({ "level": level, "message": text }))
+            }
+        })
+    }
+}
+
+impl Drop for SessionRecorder {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn i18n_text_to_json(text: &SmartSpeakerI18nText) -> Value {
+    json!({ "en": text.en, "ja": text.ja, "zh": text.zh, "ko": text.ko })
+}
+
+fn intent_slot_to_json(slot: &Box<dyn crate::smart_speaker::models::intent_model::IntentSlot>) -> Value {
+    if let Some(menu) = slot.as_any().downcast_ref::<IntentCookingMenu>() {
+        json!({ "menu_name": menu.name })
+    } else if let Some(place) = slot.as_any().downcast_ref::<IntentPlace>() {
+        json!({ "place": format!("{:?}", place) })
+    } else {
+        json!({})
+    }
+}
+
+fn vision_slot_to_json(slot: &Box<dyn crate::smart_speaker::models::vision_model::VisionSlot>) -> Value {
+    if let Some(object) = slot.as_any().downcast_ref::<VisionObject>() {
+        json!({
+            "object_type": format!("{:?}", object.object_type),
+            "shape": format!("{:?}", object.shape),
+            "area": object.size.area,
+            "width": object.size.width,
+            "height": object.size.height,
+        })
+    } else if let Some(marker) = slot.as_any().downcast_ref::<VisionMarker>() {
+        json!({
+            "marker": marker.tag,
+            "presence": marker.presence,
+        })
+    } else {
+        json!({})
+    }
+}
@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod session_model_tests {
+    use crate::smart_speaker::models::message_model::*;
+    use crate::smart_speaker::models::core_model::SmartSpeakerState;
+    use crate::smart_speaker::models::frame_model::{FrameHeader, PixelFormat, SharedFrame};
+    use crate::utils::vision_util::VisionType;
+    use super::super::session_model::*;
+
+    fn read_records(path: &str) -> Vec<SessionRecord> {
+        std::fs::read_to_string(path).unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<SessionRecord>(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn session_record_elide_test() {
+        let path = std::env::temp_dir().join("vas_session_elide.jsonl");
+        let path = path.to_str().unwrap();
+        let mut recorder = SessionRecorder::new(path, SessionMediaMode::Elide).unwrap();
+        recorder.record(&SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
+            send_from: SmartSpeakerActors::CameraActor,
+            send_to: SmartSpeakerActors::VisionActor,
+            frame: Some(SharedFrame::from_bytes(
+                FrameHeader::new(10, 10, PixelFormat::Bgr, VisionType::Pupil, 1, Some(12.5)),
+                vec![0; 300]).unwrap()),
+        })).unwrap();
+        recorder.record(&SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage {
+            send_from: SmartSpeakerActors::ContextActor,
+            send_to: SmartSpeakerActors::CoreActor,
+            state: SmartSpeakerState::Idle,
+        })).unwrap();
+        recorder.record(&SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage {
+            send_from: SmartSpeakerActors::ContextActor,
+            send_to: SmartSpeakerActors::MachineSpeechActor,
+            message: TextToSpeechMessageType::Normal(SmartSpeakerI18nText::new().en("hello").ja("こんにちは")),
+        })).unwrap();
+        recorder.finish().unwrap();
+        let records = read_records(path);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].kind, "RequestCameraFrame");
+        assert_eq!(records[0].send_from, Some("CameraActor".to_string()));
+        assert_eq!(records[0].payload["bytes"], 300);
+        assert_eq!(records[0].payload["format"], "bgr");
+        assert_eq!(records[0].payload["sequence"], 1);
+        assert_eq!(records[0].payload["source_timestamp"], 12.5);
+        assert!(records[0].payload.get("ref").is_none());
+        assert_eq!(records[1].payload["state"], SmartSpeakerState::Idle.to_string());
+        assert_eq!(records[2].payload["ja"], "こんにちは");
+        assert!(records[0].elapsed <= records[2].elapsed);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn session_record_reference_test() {
+        let path = std::env::temp_dir().join("vas_session_reference.jsonl");
+        let path = path.to_str().unwrap();
+        let mut recorder = SessionRecorder::new(path, SessionMediaMode::Reference).unwrap();
+        for _ in 0..2 {
+            recorder.record(&SmartSpeakerMessage::RequestAudioStream(AudioStreamMessage {
+                send_from: SmartSpeakerActors::AudioActor,
+                send_to: SmartSpeakerActors::WakeWordActor,
+                stream: vec![1; 512],
+            })).unwrap();
+        }
+        // the referenced bytes are in the file while recording
+        let audio = format!("{}.audio.raw", path);
+        assert_eq!(std::fs::metadata(&audio).unwrap().len(), 2048);
+        drop(recorder);
+        let records = read_records(path);
+        assert_eq!(records[1].payload["ref"]["offset"], 1024);
+        assert_eq!(records[1].payload["ref"]["length"], 1024);
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(&audio);
+        let _ = std::fs::remove_file(format!("{}.frames.raw", path));
+    }
+}
@@ -1,37 +1,128 @@
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
 use anyhow::{anyhow, Result};
-use tts::{Tts, Features, Voice, UtteranceId, Error};
-use crate::utils::config_util::LanguageTag;
-#[cfg(target_os = "macos")]
-use cocoa_foundation::base::id;
-#[cfg(target_os = "macos")]
-use cocoa_foundation::foundation::{NSRunLoop, NSDefaultRunLoopMode};
-#[cfg(target_os = "macos")]
-use objc::{msg_send, sel, sel_impl, class};
+use tts::{Tts, Features, Voice, UtteranceId};
+use crate::utils::config_util::{Config, LanguageTag};
 use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
 
+/// Where the machine speech goes. `Console` and `Transcript` do not need a speech synthesizer.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SpeechOutputType {
+    Tts,
+    Console,
+    Transcript,
+}
+
+impl FromStr for SpeechOutputType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "tts" => Ok(SpeechOutputType::Tts),
+            "console" => Ok(SpeechOutputType::Console),
+            "transcript" => Ok(SpeechOutputType::Transcript),
+            _ => Err(anyhow!("invalid speech output")),
+        }
+    }
+}
+
+pub(crate) trait SpeechOutput {
+    fn init(&mut self, language: &LanguageTag) -> Result<String>;
+    fn info(&self, language: &LanguageTag) -> String;
+    /// Speak the text. `callback_sender` receives a value when the utterance ends, even if the output failed.
+    fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, language: &LanguageTag, callback_sender: mpsc::Sender<usize>) -> Result<()>;
+}
+
 pub(crate) struct MachineSpeech {
-    app: Tts,
+    output: Box<dyn SpeechOutput + Send + 'static>,
     pub language: LanguageTag // BCP 47
 }
 
 impl MachineSpeech {
     pub(crate) fn new(language: LanguageTag) -> Self {
+        Self::with_output(Box::new(TtsSpeechOutput::new()), language)
+    }
+
+    pub(crate) fn with_output(output: Box<dyn SpeechOutput + Send + 'static>, language: LanguageTag) -> Self {
         Self {
-            app: Tts::default().unwrap(),
+            output,
             language
         }
     }
 
+    pub(crate) fn from_config(config: &Config) -> Result<Self> {
+        let output: Box<dyn SpeechOutput + Send + 'static> = match config.speech_output {
+            SpeechOutputType::Tts => Box::new(TtsSpeechOutput::new()),
+            SpeechOutputType::Console => Box::new(ConsoleSpeechOutput::new(config.speech_output_realtime)),
+            SpeechOutputType::Transcript => Box::new(TranscriptSpeechOutput::new(&config.speech_output_path, config.speech_output_realtime)?),
+        };
+        Ok(Self::with_output(output, config.language.clone()))
+    }
+
     pub(crate) fn init(&mut self) -> Result<String> {
-        let voice_participants = self.app.voices().unwrap().into_iter().filter(|v| v.language() == self.language.to_str().to_string()).collect::<Vec<Voice>>();
+        self.output.init(&self.language)
+    }
+
+    pub(crate) fn info(&self) -> String {
+        self.output.info(&self.language)
+    }
+
+    pub(crate) fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, callback_sender: mpsc::Sender<usize>) -> Result<()> {
+        self.output.speak_with_callback(i18n_text, &self.language, callback_sender)
+    }
+}
+
+/// Rough speaking time of a synthesizer at the normal rate, with a short lead-in.
+pub(crate) fn estimate_speech_duration(text: &str, language: &LanguageTag) -> Duration {
+    let characters_per_sec = match language {
+        LanguageTag::English => 14.,
+        LanguageTag::Japanese => 7.,
+        LanguageTag::Chinese => 4.5,
+        LanguageTag::Korean => 6.,
+    };
+    let characters = text.chars().filter(|c| !c.is_whitespace()).count();
+    Duration::from_secs_f64(0.3 + characters as f64 / characters_per_sec)
+}
+
+/// Send the finished callback after the utterance would have ended, or right away if not realtime.
+fn finish_after(duration: Duration, realtime: bool, callback_sender: mpsc::Sender<usize>) {
+    if realtime {
+        thread::spawn(move || {
+            thread::sleep(duration);
+            let _ = callback_sender.send(0);
+        });
+    } else {
+        let _ = callback_sender.send(0);
+    }
+}
+
+/// Speech synthesizer of the platform.
+pub(crate) struct TtsSpeechOutput {
+    app: Tts,
+}
+
+impl TtsSpeechOutput {
+    pub(crate) fn new() -> Self {
+        Self {
+            app: Tts::default().unwrap(),
+        }
+    }
+}
+
+impl SpeechOutput for TtsSpeechOutput {
+    fn init(&mut self, language: &LanguageTag) -> Result<String> {
+        let voice_participants = self.app.voices().unwrap().into_iter().filter(|v| v.language() == language.to_str().to_string()).collect::<Vec<Voice>>();
         let voice = voice_participants.first().ok_or(anyhow!("no voice found"))?;
         self.app.set_voice(voice)?;
 
         Ok(format!("{:?}", &voice_participants))
     }
 
-    pub(crate) fn info(&self) -> String {
+    #[cfg_attr(not(target_os = "macos"), allow(unused_variables))]
+    fn info(&self, language: &LanguageTag) -> String {
         let Features {
             get_voice,
             ..
@@ -46,7 +137,7 @@ impl MachineSpeech {
                 Err(err) => {
                     #[cfg(target_os = "macos")]
                     {
-                        return format!("Voice: {:?}", self.app.voices().unwrap().into_iter().filter(|v| v.language() == self.language.to_str().to_string()).collect::<Vec<Voice>>().first()).to_string()
+                        return format!("Voice: {:?}", self.app.voices().unwrap().into_iter().filter(|v| v.language() == language.to_str().to_string()).collect::<Vec<Voice>>().first()).to_string()
                     }
                     format!("Voice: {:?}", err).to_string()
                 }
@@ -54,7 +145,7 @@ impl MachineSpeech {
         }
     }
 
-    pub(crate) fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, callback_sender: mpsc::Sender<usize>) {
+    fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, language: &LanguageTag, callback_sender: mpsc::Sender<usize>) -> Result<()> {
         let Features {
             rate,
             utterance_callbacks,
@@ -62,7 +153,7 @@ impl MachineSpeech {
         } = self.app.supported_features();
         if rate {
             let normal_rate = self.app.normal_rate();
-            match &self.language {
+            match language {
                 LanguageTag::English => {
                     if &i18n_text.en.chars().count() > &100 {
                         self.app.set_rate(normal_rate - 0.1).unwrap();
@@ -93,23 +184,100 @@ impl MachineSpeech {
                 }
             }
         }
-        let _ = self.app.speak(i18n_text.get(&self.language), false);
+        let _ = self.app.speak(i18n_text.get(language), false);
         if utterance_callbacks {
-            self.app.on_utterance_end(Some(Box::new(move |utterance_id: UtteranceId| {
+            self.app.on_utterance_end(Some(Box::new(move |_utterance_id: UtteranceId| {
                 let _ = callback_sender.send(0);
             }))).unwrap();
         } else {
             let _ = callback_sender.send(0);
         }
-        //
-        // #[cfg(target_os = "macos")]
-        // {
-        //     let run_loop: id = unsafe { NSRunLoop::currentRunLoop() };
-        //     unsafe {
-        //         let date: id = msg_send![class!(NSDate), distantFuture];
-        //         let _: () = msg_send![run_loop, runMode:NSDefaultRunLoopMode beforeDate:date];
-        //     }
-        // }
+        Ok(())
+    }
+}
+
+/// Voices of the platform speech synthesizer for the language.
+pub(crate) fn list_voices(language: &LanguageTag) -> Result<Vec<Voice>> {
+    let tts = Tts::default().map_err(|e| anyhow!("failed to init tts: {}", e))?;
+    let voices = tts.voices().map_err(|e| anyhow!("failed to list voices: {}", e))?;
+    Ok(voices.into_iter().filter(|v| v.language() == language.to_str().to_string()).collect())
+}
+
+/// Prints the text instead of speaking it.
+pub(crate) struct ConsoleSpeechOutput {
+    realtime: bool,
+}
+
+impl ConsoleSpeechOutput {
+    pub(crate) fn new(realtime: bool) -> Self {
+        Self {
+            realtime,
+        }
+    }
+}
+
+impl SpeechOutput for ConsoleSpeechOutput {
+    fn init(&mut self, _language: &LanguageTag) -> Result<String> {
+        Ok("console".to_string())
+    }
+
+    fn info(&self, language: &LanguageTag) -> String {
+        format!("Voice: console ({}), realtime: {}", language.to_str(), self.realtime).to_string()
+    }
+
+    fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, language: &LanguageTag, callback_sender: mpsc::Sender<usize>) -> Result<()> {
+        let text = i18n_text.get(language);
+        let duration = estimate_speech_duration(&text, language);
+        println!("[speech {:.1}s] {}", duration.as_secs_f64(), text);
+        finish_after(duration, self.realtime, callback_sender);
+        Ok(())
+    }
+}
+
+/// Writes the text of each utterance to `<dir>/utterance_0001.txt`. No audio is synthesized or written.
+/// The utterance finishes after the estimated speaking time, like the console output.
+pub(crate) struct TranscriptSpeechOutput {
+    dir: PathBuf,
+    realtime: bool,
+    count: usize,
+}
+
+impl TranscriptSpeechOutput {
+    pub(crate) fn new(dir: &str, realtime: bool) -> Result<Self> {
+        if dir.is_empty() {
+            return Err(anyhow!("speech output path is required for transcript output"));
+        }
+        std::fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            realtime,
+            count: 0,
+        })
+    }
+
+    fn write(&mut self, text: &str) -> Result<PathBuf> {
+        self.count += 1;
+        let path = self.dir.join(format!("utterance_{:04}.txt", self.count));
+        std::fs::write(&path, text)
+            .map_err(|e| anyhow!("failed to write transcript {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+impl SpeechOutput for TranscriptSpeechOutput {
+    fn init(&mut self, _language: &LanguageTag) -> Result<String> {
+        Ok(format!("transcript: {}", self.dir.display()))
+    }
+
+    fn info(&self, language: &LanguageTag) -> String {
+        format!("Voice: transcript ({}) to {}, realtime: {}", language.to_str(), self.dir.display(), self.realtime).to_string()
+    }
+
+    fn speak_with_callback(&mut self, i18n_text: SmartSpeakerI18nText, language: &LanguageTag, callback_sender: mpsc::Sender<usize>) -> Result<()> {
+        let text = i18n_text.get(language);
+        let written = self.write(&text);
+        finish_after(estimate_speech_duration(&text, language), self.realtime, callback_sender);
+        written.map(|_| ())
     }
 }
 
@@ -121,6 +289,9 @@ pub(crate) enum MachineSpeechBoilerplate {
     Aborted,
     IntentFailed,
     VisionFailed,
+    ActorRestarted,
+    ActorDegraded,
+    VisionUnsure,
 }
 
 impl MachineSpeechBoilerplate {
@@ -133,6 +304,9 @@ impl MachineSpeechBoilerplate {
             4 => Ok(Self::Aborted),
             5 => Ok(Self::IntentFailed),
             6 => Ok(Self::VisionFailed),
+            7 => Ok(Self::ActorRestarted),
+            8 => Ok(Self::ActorDegraded),
+            9 => Ok(Self::VisionUnsure),
             _ => Err(anyhow!("invalid index"))
         }
     }
@@ -174,6 +348,21 @@ impl MachineSpeechBoilerplate {
                 .ja("すみません。よく見えないです。もう一度見せてください。")
                 .zh("对不起。我看不清楚。请再给我看一遍。")
                 .ko("죄송합니다. 잘 보이지 않습니다. 다시 보여주세요."),
+            Self::ActorRestarted => SmartSpeakerI18nText::new()
+                .en("Sorry. Something went wrong, but I recovered.")
+                .ja("すみません。問題が発生しましたが、復旧しました。")
+                .zh("对不起。出现了问题，但已经恢复了。")
+                .ko("죄송합니다. 문제가 발생했지만 복구했습니다."),
+            Self::ActorDegraded => SmartSpeakerI18nText::new()
+                .en("Sorry. Some features stopped working. I will continue without them.")
+                .ja("すみません。一部の機能が使えなくなりました。それ以外で続けます。")
+                .zh("对不起。部分功能无法使用。我会在没有它们的情况下继续。")
+                .ko("죄송합니다. 일부 기능을 사용할 수 없게 되었습니다. 나머지 기능으로 계속하겠습니다."),
+            Self::VisionUnsure => SmartSpeakerI18nText::new()
+                .en("Sorry. I'm not sure which one you mean. Please keep looking at it.")
+                .ja("すみません。どれのことか分かりませんでした。そのまま見つめてください。")
+                .zh("对不起。我不确定您指的是哪一个。请继续看着它。")
+                .ko("죄송합니다. 어느 것인지 잘 모르겠습니다. 계속 바라봐 주세요."),
         }
     }
 }
@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod speak_model_tests {
+    use std::str::FromStr;
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+    use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
+    use crate::utils::config_util::LanguageTag;
+    use super::super::speak_model::*;
+
+    #[test]
+    fn speech_output_type_parse_test() {
+        assert_eq!(SpeechOutputType::from_str("Console").unwrap(), SpeechOutputType::Console);
+        assert_eq!(SpeechOutputType::from_str("transcript").unwrap(), SpeechOutputType::Transcript);
+        assert!(SpeechOutputType::from_str("speaker").is_err());
+    }
+
+    #[test]
+    fn estimate_speech_duration_test() {
+        assert!((estimate_speech_duration("", &LanguageTag::English).as_secs_f64() - 0.3).abs() < 1e-6);
+        assert!((estimate_speech_duration("わかりました。", &LanguageTag::Japanese).as_secs_f64() - 1.3).abs() < 1e-6);
+        let short = estimate_speech_duration("Ok.", &LanguageTag::English);
+        let long = estimate_speech_duration("Sorry. I can't hear you very well. Please repeat your message", &LanguageTag::English);
+        assert!(short < long);
+    }
+
+    #[test]
+    fn console_speech_output_test() {
+        let mut speech = MachineSpeech::with_output(Box::new(ConsoleSpeechOutput::new(false)), LanguageTag::English);
+        speech.init().unwrap();
+        let (tx, rx) = mpsc::channel();
+        speech.speak_with_callback(SmartSpeakerI18nText::new().en("Ok.").ja("わかりました。"), tx).unwrap();
+        assert_eq!(rx.try_recv().unwrap(), 0);
+    }
+
+    #[test]
+    fn console_speech_output_realtime_test() {
+        let mut speech = MachineSpeech::with_output(Box::new(ConsoleSpeechOutput::new(true)), LanguageTag::English);
+        let (tx, rx) = mpsc::channel();
+        let started_at = Instant::now();
+        speech.speak_with_callback(SmartSpeakerI18nText::new().en("Ok."), tx).unwrap();
+        assert!(rx.try_recv().is_err());
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(started_at.elapsed() >= estimate_speech_duration("Ok.", &LanguageTag::English));
+    }
+
+    #[test]
+    fn transcript_speech_output_test() {
+        let dir = std::env::temp_dir().join("vas_transcript_speech_output");
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut speech = MachineSpeech::with_output(
+            Box::new(TranscriptSpeechOutput::new(dir.to_str().unwrap(), false).unwrap()), LanguageTag::Japanese);
+        let (tx, rx) = mpsc::channel();
+        speech.speak_with_callback(SmartSpeakerI18nText::new().en("Ok.").ja("わかりました。"), tx.clone()).unwrap();
+        speech.speak_with_callback(SmartSpeakerI18nText::new().en("Aborted.").ja("中止します。"), tx.clone()).unwrap();
+        assert_eq!(rx.try_iter().count(), 2);
+        assert_eq!(std::fs::read_to_string(dir.join("utterance_0001.txt")).unwrap(), "わかりました。");
+        assert_eq!(std::fs::read_to_string(dir.join("utterance_0002.txt")).unwrap(), "中止します。");
+        assert!(!dir.join("utterance_0001.wav").exists());
+        // a failed write is reported, and the utterance still finishes
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(speech.speak_with_callback(SmartSpeakerI18nText::new().en("Ok.").ja("わかりました。"), tx).is_err());
+        assert_eq!(rx.try_recv().unwrap(), 0);
+    }
+
+    #[test]
+    fn transcript_speech_output_requires_path_test() {
+        assert!(TranscriptSpeechOutput::new("", false).is_err());
+    }
+}
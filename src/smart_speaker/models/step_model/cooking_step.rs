@@ -2,14 +2,26 @@ use std::fmt::{Debug};
 use anyhow::{anyhow, Result};
 use handlebars::Handlebars;
 use serde_json::json;
-use crate::smart_speaker::models::intent_model::IntentCookingMenu;
+use crate::smart_speaker::models::recipe_model::{Recipe, RecipeStep, RecipeStepDetail, RecipeIngredient};
 use crate::smart_speaker::models::step_model::generic_step::{ActionExecutable, ActionTriggerType};
 use crate::smart_speaker::models::task_model::{SmartSpeakerTaskResult, SmartSpeakerTaskResultCode};
-use crate::smart_speaker::models::vision_model::{DetectableObject, DetectionDetail, DetectionMode, VisionAction, VisionObject};
+use crate::smart_speaker::models::marker_model::{ko_with_particle, MarkerTag};
+use crate::smart_speaker::models::vision_model::{DetectableObject, MarkerPresence, VisionAction, VisionMarker, VisionObject};
 use crate::smart_speaker::models::message_model::*;
 use crate::smart_speaker::models::revision_model::cooking_revision::{CookingRevision, CookingRevisionEntity, CookingRevisionEntityProperty};
 use crate::smart_speaker::models::revision_model::Revision;
-use crate::smart_speaker::models::task_model::cooking_task::{CookingIngredient, CookingIngredientAmount, CookingIngredientLinkComponent, CookingIngredientName, CookingIngredientTime, SmartSpeakerMaterialProperty};
+use crate::smart_speaker::models::task_model::cooking_task::{CookingIngredient, CookingIngredientLinkComponent, CookingIngredientName, CookingIngredientTime, SmartSpeakerMaterialProperty};
+use crate::utils::config_util::LanguageTag;
+
+/// Handlebars data for ingredient placeholders. Each ingredient is exposed by its template code. (e.g. {{sesame_oil}})
+fn ingredient_template_data(ingredients: &Vec<CookingIngredient>, language: &LanguageTag) -> serde_json::Value {
+    let mut data = serde_json::Map::new();
+    for ingredient in ingredients {
+        data.entry(ingredient.name.to_template_code())
+            .or_insert(json!(ingredient.to_approx_unit_i18n().get(language)));
+    }
+    serde_json::Value::Object(data)
+}
 
 #[derive(Debug, Clone)]
 pub(crate) enum CookingActionDetail {
@@ -109,46 +121,14 @@ impl ActionExecutable for ExplainRecipeAction {
                     CookingActionDetail::ExplainMutableIngredient(link) => {
                         match &self.current_revision {
                             None => {
-                                tts_script.en = reg.render_template(&self.tts_script.en, &json!({
-                                    "salt": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "pepper": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "sugar": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "soy_sauce": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "sesame": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "sesame_oil": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "carrot": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    "mayonnaise": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                tts_script.ja = reg.render_template(&self.tts_script.ja, &json!({
-                                    "salt": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "pepper": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "sugar": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "soy_sauce": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "sesame": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "sesame_oil": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "carrot": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    "mayonnaise": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                tts_script.zh = reg.render_template(&self.tts_script.zh, &json!({
-                                    "salt": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "pepper": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "sugar": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "soy_sauce": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "sesame": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "sesame_oil": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "carrot": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    "mayonnaise": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                tts_script.ko = reg.render_template(&self.tts_script.ko, &json!({
-                                    "salt": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "pepper": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "sugar": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "soy_sauce": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "sesame": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "sesame_oil": self.ingredients.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "carrot": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    "mayonnaise": self.ingredients.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                tts_script.en = reg.render_template(&self.tts_script.en, &ingredient_template_data(&self.ingredients, &LanguageTag::English))
+                                    .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                tts_script.ja = reg.render_template(&self.tts_script.ja, &ingredient_template_data(&self.ingredients, &LanguageTag::Japanese))
+                                    .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                tts_script.zh = reg.render_template(&self.tts_script.zh, &ingredient_template_data(&self.ingredients, &LanguageTag::Chinese))
+                                    .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                tts_script.ko = reg.render_template(&self.tts_script.ko, &ingredient_template_data(&self.ingredients, &LanguageTag::Korean))
+                                    .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
                             }
                             Some(rev) => {
                                 let mut ingredients_updated = vec![];
@@ -170,46 +150,14 @@ impl ActionExecutable for ExplainRecipeAction {
                                         Box::new(rev.clone()),
                                     ));
                                 } else {
-                                    tts_script.en = reg.render_template(&self.tts_script.en, &json!({
-                                        "salt": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "pepper": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "sugar": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "soy_sauce": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "sesame": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "sesame_oil": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "carrot": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                        "mayonnaise": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().en)),
-                                    })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                    tts_script.ja = reg.render_template(&self.tts_script.ja, &json!({
-                                        "salt": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "pepper": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "sugar": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "soy_sauce": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "sesame": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "sesame_oil": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "carrot": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                        "mayonnaise": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().ja)),
-                                    })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                    tts_script.zh = reg.render_template(&self.tts_script.zh, &json!({
-                                        "salt": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "pepper": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "sugar": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "soy_sauce": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "sesame": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "sesame_oil": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "carrot": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                        "mayonnaise": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().zh)),
-                                    })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
-                                    tts_script.ko = reg.render_template(&self.tts_script.ko, &json!({
-                                        "salt": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Salt).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "pepper": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Pepper).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "sugar": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sugar).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "soy_sauce": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SoySauce).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "sesame": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Sesame).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "sesame_oil": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::SesameOil).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "carrot": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Carrot).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                        "mayonnaise": ingredients_updated.iter().find(|i| i.name == CookingIngredientName::Mayonnaise).and_then(|object| Some(object.to_approx_unit_i18n().ko)),
-                                    })).map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                    tts_script.en = reg.render_template(&self.tts_script.en, &ingredient_template_data(&ingredients_updated, &LanguageTag::English))
+                                        .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                    tts_script.ja = reg.render_template(&self.tts_script.ja, &ingredient_template_data(&ingredients_updated, &LanguageTag::Japanese))
+                                        .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                    tts_script.zh = reg.render_template(&self.tts_script.zh, &ingredient_template_data(&ingredients_updated, &LanguageTag::Chinese))
+                                        .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
+                                    tts_script.ko = reg.render_template(&self.tts_script.ko, &ingredient_template_data(&ingredients_updated, &LanguageTag::Korean))
+                                        .map_err(|e| anyhow!("failed to render template: {}", e)).unwrap();
                                     return Ok(SmartSpeakerTaskResult::with_tts_and_revision(
                                         self.get_action_trigger_type().to_task_type(),
                                         SmartSpeakerTaskResultCode::StepSuccess,
@@ -413,7 +361,7 @@ impl VisionBasedIngredientMeasureAction {
                 match first.object_type {
                     DetectableObject::Carrot => {
                         let target = self.ingredients.iter().find(|i| i.name == CookingIngredientName::Carrot).unwrap();
-                        let weight_approx = target.name.get_weight_per_perimeter(first.size.perimeter);
+                        let weight_approx = target.name.get_weight_per_area(first.size.area);
                         let mut diff = weight_approx.sub(target.unit).unwrap();
                         // match last_revision {
                         //     None => {}
@@ -424,7 +372,7 @@ impl VisionBasedIngredientMeasureAction {
                         //                     let previous_weight = ingredient.unit.get_value() + 1000.0;
                         //                     if weight_approx.get_value() > previous_weight {
                         //                         let spread = weight_approx.get_value() / previous_weight;
-                        //                         let pieces_approx = (previous_weight * spread) / target.name.get_weight_per_perimeter();
+                        //                         let pieces_approx = (previous_weight * spread) / target.name.get_weight_per_area();
                         //                         let approx_weight_per_piece = previous_weight / pieces_approx;
                         //                     }
                         //                 }
@@ -434,7 +382,7 @@ impl VisionBasedIngredientMeasureAction {
                         //                     let previous_weight = 1000.0 - ingredient.unit.get_value();
                         //                     if weight_approx.get_value() > previous_weight {
                         //                         let spread = weight_approx.get_value() / previous_weight;
-                        //                         let pieces_approx = (previous_weight * spread) / target.name.get_weight_per_perimeter(target.name.get_perimeter_per_piece());
+                        //                         let pieces_approx = (previous_weight * spread) / target.name.get_weight_per_area(target.name.get_perimeter_per_piece());
                         //                     }
                         //                 }
                         //             }
@@ -497,7 +445,7 @@ impl VisionBasedIngredientMeasureAction {
                     }
                     DetectableObject::Potato => {
                         let target = self.ingredients.iter().find(|i| i.name == CookingIngredientName::Potato).unwrap();
-                        let weight_approx = target.name.get_weight_per_perimeter(first.size.perimeter);
+                        let weight_approx = target.name.get_weight_per_area(first.size.area);
                         let diff = weight_approx.sub(target.unit).unwrap();
                         if diff.get_value().is_sign_positive() {
                             revisions.push(CookingRevisionEntity::new(
@@ -600,6 +548,9 @@ impl ActionExecutable for VisionBasedIngredientMeasureAction {
                         self.handle_vision_contents(
                             &content.entities.iter().map(|c| c.as_any().downcast_ref::<VisionObject>().unwrap().clone()).collect::<Vec<VisionObject>>())
                     }
+                    VisionAction::MarkerPresence(_) => {
+                        Err(anyhow!("unexpected vision content: {:?}", content.action))
+                    }
                 }
             }
         }
@@ -661,6 +612,140 @@ impl ActionExecutable for VisionBasedIngredientMeasureAction {
     }
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct VisionBasedMarkerCheckAction {
+    pub(crate) marker: MarkerTag,
+    pub(crate) tts_script: SmartSpeakerI18nText,
+    pub(crate) current_content: Option<VisionContent>,
+    cancelled: bool,
+    repeat_requested: bool,
+}
+
+impl VisionBasedMarkerCheckAction {
+    pub(crate) fn new(marker: MarkerTag, text: SmartSpeakerI18nText) -> Self {
+        VisionBasedMarkerCheckAction {
+            marker,
+            tts_script: text,
+            current_content: None,
+            cancelled: false,
+            repeat_requested: false,
+        }
+    }
+
+    /// Sentence for `{{marker_result}}`.
+    fn presence_i18n(&self, presence: MarkerPresence) -> SmartSpeakerI18nText {
+        let marker = self.marker.to_i18n();
+        match presence {
+            MarkerPresence::Missing => {
+                SmartSpeakerI18nText::new()
+                    .en(&format!("I can't see {}. Please put it where I can see it.", marker.en))
+                    .ja(&format!("{}が見えません。見える場所に置いてください。", marker.ja))
+                    .zh(&format!("我看不到{}。请把它放在我能看到的地方。", marker.zh))
+                    .ko(&format!("{} 보이지 않습니다. 보이는 곳에 놓아주세요.", ko_with_particle(&marker.ko, "이", "가")))
+            }
+            MarkerPresence::Present => {
+                SmartSpeakerI18nText::new()
+                    .en(&format!("I can see {}.", marker.en))
+                    .ja(&format!("{}が見えます。", marker.ja))
+                    .zh(&format!("我看到了{}。", marker.zh))
+                    .ko(&format!("{} 보입니다.", ko_with_particle(&marker.ko, "이", "가")))
+            }
+            MarkerPresence::NearGaze => {
+                SmartSpeakerI18nText::new()
+                    .en(&format!("You are looking at {}.", marker.en))
+                    .ja(&format!("{}を見ていますね。", marker.ja))
+                    .zh(&format!("你正在看{}。", marker.zh))
+                    .ko(&format!("{} 보고 계시네요.", ko_with_particle(&marker.ko, "을", "를")))
+            }
+        }
+    }
+}
+
+impl ActionExecutable for VisionBasedMarkerCheckAction {
+    fn execute(&self) -> Result<SmartSpeakerTaskResult> {
+        if self.has_cancelled() {
+            return Ok(SmartSpeakerTaskResult::new(
+                self.get_action_trigger_type().to_task_type(),
+                SmartSpeakerTaskResultCode::Cancelled));
+        }
+        if self.has_request_repeat() {
+            return Ok(SmartSpeakerTaskResult::new(
+                self.get_action_trigger_type().to_task_type(),
+                SmartSpeakerTaskResultCode::RepeatPrevious));
+        }
+        let content = self.current_content.as_ref().ok_or(anyhow!("no vision content"))?;
+        // the vision actor answers with the presence of the requested tag only
+        let presence = content.entities.iter()
+            .filter_map(|entity| entity.as_any().downcast_ref::<VisionMarker>())
+            .find(|marker| marker.tag == self.marker)
+            .map(|marker| marker.presence)
+            .unwrap_or(MarkerPresence::Missing);
+        let result = self.presence_i18n(presence);
+        let reg = Handlebars::new();
+        let mut tts_script = self.tts_script.clone();
+        tts_script.en = reg.render_template(&self.tts_script.en, &json!({ "marker_result": result.en }))
+            .map_err(|e| anyhow!("failed to render template: {}", e))?;
+        tts_script.ja = reg.render_template(&self.tts_script.ja, &json!({ "marker_result": result.ja }))
+            .map_err(|e| anyhow!("failed to render template: {}", e))?;
+        tts_script.zh = reg.render_template(&self.tts_script.zh, &json!({ "marker_result": result.zh }))
+            .map_err(|e| anyhow!("failed to render template: {}", e))?;
+        tts_script.ko = reg.render_template(&self.tts_script.ko, &json!({ "marker_result": result.ko }))
+            .map_err(|e| anyhow!("failed to render template: {}", e))?;
+        Ok(SmartSpeakerTaskResult::with_tts(
+            self.get_action_trigger_type().to_task_type(),
+            SmartSpeakerTaskResultCode::StepSuccess,
+            tts_script,
+        ))
+    }
+
+    fn feed(&mut self, content: Box<dyn Content>, _: Option<Box<dyn Revision>>) -> Result<()> {
+        self.check_cancelled(&content)?;
+        self.check_request_repeat(&content)?;
+        if let Some(content) = content.as_any().downcast_ref::<VisionContent>() {
+            self.current_content = Some(content.clone());
+        }
+        Ok(())
+    }
+
+    fn clone_box(&self) -> Box<dyn ActionExecutable> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn get_action_trigger_type(&self) -> ActionTriggerType {
+        ActionTriggerType::Vision(vec![VisionAction::MarkerPresence(self.marker)])
+    }
+
+    fn get_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    fn set_cancelled(&mut self) -> Result<()> {
+        self.cancelled = true;
+        Ok(())
+    }
+
+    fn get_repeated(&self) -> bool {
+        self.repeat_requested
+    }
+
+    fn set_repeated(&mut self) -> Result<()> {
+        self.repeat_requested = true;
+        Ok(())
+    }
+
+    fn expose_tts_script(&self) -> Result<SmartSpeakerI18nText> {
+        Ok(self.tts_script.clone())
+    }
+
+    fn try_expose_vision_actions(&self) -> Result<Vec<VisionAction>> {
+        Ok(vec![VisionAction::MarkerPresence(self.marker)])
+    }
+}
+
 pub(crate) struct CookingStepBuilder {
     vision: bool
 }
@@ -672,289 +757,82 @@ impl CookingStepBuilder {
         }
     }
 
-    fn build_carrot_salad(&self, menu: &IntentCookingMenu, steps: &mut Vec<Box<dyn ActionExecutable>>) {
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![],
-                CookingActionDetail::None,
-                SmartSpeakerI18nText::new()
-                    .ko("먼저 당근을 준비합니다.")
-                    .en("First, prepare the carrots.")
-                    .ja("まず人参　を用意します。")
-                    .zh("首先准备胡萝卜。")
-            )));
-        if self.vision {
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("도마 위에 당근이 준비되었다면, 저도 볼 수 있게 손을 치워주실 수 있나요? 준비가 되면 알려주세요.")
-                        .en("If the carrots are ready on the chopping board, can you move your hands back so that I can see? Let me know when you are ready.")
-                        .ja("まな板の上に人参が用意できたら、私にも見えるように手をどけてくれますか？ 準備ができたら教えてください。")
-                        .zh("如果胡萝卜准备好了，你能把手拿开让我看看吗？ 准备好后请告诉我。")
-                ))
-            );
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("감사합니다.")
-                        .en("Thank you.")
-                        .ja("ありがとうございます。")
-                        .zh("谢谢。")
-                ))
-            );
-            steps.push(
-                Box::new(VisionBasedIngredientMeasureAction::new(
-                    vec![menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Carrot).unwrap().clone()],
-                    CookingActionDetail::MeasureIngredientSize,
-                    VisionAction::ObjectDetection(DetectionDetail::new(
-                        DetectionMode::Aruco,
-                        DetectableObject::Carrot,
-                        true,
-                    )),
-                    SmartSpeakerI18nText::new()
-                        .ko("{{measure_result}} 이후의 설명에 참고하도록 하겠습니다.")
-                        .en("{{measure_result}} I'll keep that in mind for the rest of the instructions.")
-                        .ja("{{measure_result}} 残りの説明のために覚えておきます。")
-                        .zh("{{measure_result}} 我会记住剩下的说明。")
-                ))
-            );
-        }
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![],
-                CookingActionDetail::None,
-                SmartSpeakerI18nText::new()
-                    .ko("계속해서 당근을 먹기 좋은 크기로 썰어주세요.")
-                    .en("Please continue to cut the carrots into bite-sized pieces.")
-                    .ja("続いて、人参を食べやすい大きさに切ってください。")
-                    .zh("请继续把胡萝卜切成一口大小。")
-            )));
-        if self.vision {
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("당근을 어떻게 자르셨는지 저도 볼 수 있게 한 조각만 보여주실 수 있나요? 준비가 되면 당근을 바라본 채 알려주세요.")
-                        .en("Can you show me just one piece so I can see how you cut the carrot? When you're ready, look at the carrot and let me know.")
-                        .ja("人参をどのように切ったか、私にも1こだけを見せてもらえますか？ 準備ができたら、人参を見て教えてください。")
-                        .zh("你能给我看一块胡萝卜吗？ 准备好后，请看着胡萝卜告诉我。")
-                ))
-            );
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("감사합니다.")
-                        .en("Thank you.")
-                        .ja("ありがとうございます。")
-                        .zh("谢谢。")
-                ))
-            );
-            steps.push(
-                Box::new(VisionBasedIngredientMeasureAction::new(
-                    vec![CookingIngredient::new(
-                        CookingIngredientName::Carrot,
-                        CookingIngredientAmount::MilliGram(100))],
-                    CookingActionDetail::MeasureIngredientSize,
-                    VisionAction::ObjectDetection(DetectionDetail::new(
-                        DetectionMode::Aruco,
-                        DetectableObject::Carrot,
-                        true,
-                    )),
-                    SmartSpeakerI18nText::new()
-                        .ko("{{measure_result}} 이후의 설명에 참고하도록 하겠습니다.")
-                        .en("{{measure_result}} I'll keep that in mind for the rest of the instructions.")
-                        .ja("{{measure_result}} 残りの説明のために覚えておきます。")
-                        .zh("{{measure_result}} 我会记住剩下的说明。")
-                ))
-            );
-        }
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Carrot).unwrap().clone()],
-                CookingActionDetail::ExplainMutableTime(
-                    CookingIngredientTime::new(
-                        CookingIngredient::new(
-                            CookingIngredientName::Carrot,
-                            CookingIngredientAmount::MilliGram(150)),
-                        80)),
-                SmartSpeakerI18nText::new()
-                    .ko("손질한 당근을 끓는 물에 약 {{time}}간 삶아주세요.")
-                    .en("Boil the carrots in boiling water for about {{time}}.")
-                    .ja("人参を沸いた水に、。。。約、。。。{{time}}間、。。。茹でます。")
-                    .zh("把胡萝卜放在沸水里煮、。。。约、。。。{{time}}钟。")
-            )));
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                menu.to_ingredient(),
-                CookingActionDetail::ExplainMutableIngredient(
-                    CookingIngredientLinkComponent::new(
-                        menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Carrot).unwrap().clone(),
-                        menu.to_ingredient().iter().filter(|ing| matches!(ing.name, CookingIngredientName::Salt|CookingIngredientName::Pepper|CookingIngredientName::SesameOil)).map(|ing| ing.clone()).collect::<Vec<CookingIngredient>>()
-                    )),
-                SmartSpeakerI18nText::new()
-                    .ko("삶은 당근을 보울에 담아 소금 {{salt}},    후추 {{pepper}},    참기름 {{sesame_oil}}을 넣고 섞어주세요.")
-                    .en("Put the boiled carrots in a bowl and add {{salt}} of salt,    {{pepper}} of pepper,    and {{sesame_oil}} of sesame oil.")
-                    .ja("茹でた人参をボウルに入れて塩　{{salt}}、　　　コショウ　{{pepper}}、　　　ごま油　{{sesame_oil}}　　　を入れて混ぜます。")
-                    .zh("把煮好的胡萝卜放在碗里，加{{salt}}的盐，   {{pepper}}的胡椒粉，   {{sesame_oil}}的芝麻油，并搅拌。")
-            )));
+    fn build_ingredients(&self, recipe: &Recipe, ingredients: &Vec<RecipeIngredient>) -> Result<Vec<CookingIngredient>> {
+        ingredients.iter().map(|i| recipe.resolve_ingredient(i)).collect()
     }
 
-    fn build_potato_salad(&self, menu: &IntentCookingMenu, steps: &mut Vec<Box<dyn ActionExecutable>>) {
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![],
-                CookingActionDetail::None,
-                SmartSpeakerI18nText::new()
-                    .ko("먼저 감자를 준비합니다.")
-                    .en("First, prepare the potatoes.")
-                    .ja("まずじゃがいも　を用意します。")
-                    .zh("首先准备土豆。")
-            )));
-        if self.vision {
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("도마 위에 감자가 준비되었다면, 저도 볼 수 있게 손을 치워주실 수 있나요? 준비가 되면 알려주세요.")
-                        .en("If the potatoes are ready on the chopping board, can you move your hands back so that I can see? Let me know when you are ready.")
-                        .ja("まな板の上にじゃがいもが用意できたら、私にも見えるように手をどけてくれますか？ 準備ができたら教えてください。")
-                        .zh("如果土豆准备好了，你能把手拿开让我看看吗？ 准备好后请告诉我。")
-                ))
-            );
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("감사합니다.")
-                        .en("Thank you.")
-                        .ja("ありがとうございます。")
-                        .zh("谢谢。")
-                ))
-            );
-            steps.push(
-                Box::new(VisionBasedIngredientMeasureAction::new(
-                    vec![menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Potato).unwrap().clone()],
-                    CookingActionDetail::MeasureIngredientSize,
-                    VisionAction::ObjectDetection(DetectionDetail::new(
-                        DetectionMode::Aruco,
-                        DetectableObject::Potato,
-                        true,
-                    )),
-                    SmartSpeakerI18nText::new()
-                        .ko("{{measure_result}} 이후의 설명에 참고하도록 하겠습니다.")
-                        .en("{{measure_result}} I'll keep that in mind for the rest of the instructions.")
-                        .ja("{{measure_result}} 残りの説明のために覚えておきます。")
-                        .zh("{{measure_result}} 我会记住剩下的说明。")
-                ))
-            );
+    fn build_detail(&self, recipe: &Recipe, detail: &RecipeStepDetail) -> Result<CookingActionDetail> {
+        match detail {
+            RecipeStepDetail::None => {
+                Ok(CookingActionDetail::None)
+            }
+            RecipeStepDetail::ExplainNonMutableIngredient => {
+                Ok(CookingActionDetail::ExplainNonMutableIngredient)
+            }
+            RecipeStepDetail::ExplainMutableIngredient { main, components } => {
+                let ingredients = recipe.to_ingredient()?;
+                Ok(CookingActionDetail::ExplainMutableIngredient(
+                    CookingIngredientLinkComponent::new(
+                        ingredients.iter().find(|i| &i.name == main)
+                            .ok_or(anyhow!("ingredient {} is not in the recipe", main.to_template_code()))?.clone(),
+                        ingredients.iter().filter(|i| components.contains(&i.name)).map(|i| i.clone()).collect::<Vec<CookingIngredient>>()
+                    )))
+            }
+            RecipeStepDetail::ExplainMutableTime { base, time } => {
+                Ok(CookingActionDetail::ExplainMutableTime(
+                    CookingIngredientTime::new(recipe.resolve_ingredient(base)?, *time)))
+            }
+            RecipeStepDetail::MeasureIngredientSize => {
+                Ok(CookingActionDetail::MeasureIngredientSize)
+            }
+            RecipeStepDetail::MeasureCutIngredient => {
+                Ok(CookingActionDetail::MeasureCutIngredient)
+            }
         }
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![],
-                CookingActionDetail::None,
-                SmartSpeakerI18nText::new()
-                    .ko("계속해서 감자를 먹기 좋은 크기로 썰어주세요.")
-                    .en("Please continue to cut the potatoes into bite-sized pieces.")
-                    .ja("続いて、じゃがいもを食べやすい大きさに切ってください。")
-                    .zh("请继续把土豆切成一口大小。")
-            )));
-        if self.vision {
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("감자를 어떻게 자르셨는지 저도 볼 수 있게 한 조각만 보여주실 수 있나요? 준비가 되면 감자를 바라본 채 알려주세요.")
-                        .en("Can you show me just one piece so I can see how you cut the potato? When you're ready, look at the potato and let me know.")
-                        .ja("じゃがいもをどのように切ったか、私にも1こだけを見せてもらえますか？ 準備ができたら、じゃがいもを見て教えてください。")
-                        .zh("你能给我看一块土豆吗？ 准备好后，请看着土豆告诉我。")
-                ))
-            );
-            steps.push(
-                Box::new(ExplainRecipeAction::new(
-                    vec![],
-                    CookingActionDetail::None,
-                    SmartSpeakerI18nText::new()
-                        .ko("감사합니다.")
-                        .en("Thank you.")
-                        .ja("ありがとうございます。")
-                        .zh("谢谢。")
-                ))
-            );
-            steps.push(
-                Box::new(VisionBasedIngredientMeasureAction::new(
-                    vec![CookingIngredient::new(
-                        CookingIngredientName::Potato,
-                        CookingIngredientAmount::MilliGram(150))],
-                    CookingActionDetail::MeasureIngredientSize,
-                    VisionAction::ObjectDetection(DetectionDetail::new(
-                        DetectionMode ::Aruco,
-                        DetectableObject::Potato,
-                        true,
-                    )),
-                    SmartSpeakerI18nText::new()
-                        .ko("{{measure_result}} 이후의 설명에 참고하도록 하겠습니다.")
-                        .en("{{measure_result}} I'll keep that in mind for the rest of the instructions.")
-                        .ja("{{measure_result}} 残りの説明のために覚えておきます。")
-                        .zh("{{measure_result}} 我会记住剩下的说明。")
-                ))
-            );
+    }
+
+    fn build_step(&self, recipe: &Recipe, step: &RecipeStep) -> Result<Box<dyn ActionExecutable>> {
+        match step {
+            RecipeStep::Explain { vision: _, ingredients, detail, script } => {
+                Ok(Box::new(ExplainRecipeAction::new(
+                    self.build_ingredients(recipe, ingredients)?,
+                    self.build_detail(recipe, detail)?,
+                    script.clone(),
+                )))
+            }
+            RecipeStep::MeasureIngredient { ingredients, detail, detection, script } => {
+                Ok(Box::new(VisionBasedIngredientMeasureAction::new(
+                    self.build_ingredients(recipe, ingredients)?,
+                    self.build_detail(recipe, detail)?,
+                    VisionAction::ObjectDetection(detection.clone()),
+                    script.clone(),
+                )))
+            }
+            RecipeStep::CheckMarker { marker, script } => {
+                Ok(Box::new(VisionBasedMarkerCheckAction::new(
+                    *marker,
+                    script.clone(),
+                )))
+            }
         }
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                vec![menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Potato).unwrap().clone()],
-                CookingActionDetail::ExplainMutableTime(
-                    CookingIngredientTime::new(
-                        CookingIngredient::new(
-                            CookingIngredientName::Potato,
-                            CookingIngredientAmount::MilliGram(100)),
-                        80)),
-                SmartSpeakerI18nText::new()
-                    .ko("손질한 감자를 끓는 물에 약 {{time}}간 삶아주세요.")
-                    .en("Boil the potatoes in boiling water for about {{time}}.")
-                    .ja("じゃがいもを沸いた水に、。。。約、。。。{{time}}間、。。。茹でます。")
-                    .zh("把土豆放在沸水里煮、。。。约、。。。{{time}}钟。")
-            )));
-        steps.push(
-            Box::new(ExplainRecipeAction::new(
-                menu.to_ingredient(),
-                CookingActionDetail::ExplainMutableIngredient(
-                    CookingIngredientLinkComponent::new(
-                        menu.to_ingredient().iter().find(|i| i.name == CookingIngredientName::Potato).unwrap().clone(),
-                        menu.to_ingredient().iter().filter(|ing| matches!(ing.name, CookingIngredientName::Salt|CookingIngredientName::Pepper|CookingIngredientName::Mayonnaise)).map(|ing| ing.clone()).collect::<Vec<CookingIngredient>>()
-                    )),
-                SmartSpeakerI18nText::new()
-                    .ko("삶은 감자를 보울에 담아 소금 {{salt}},    후추 {{pepper}},    마요네즈 {{mayonnaise}}을 넣고 섞어주세요.")
-                    .en("Put the boiled potatoes in a bowl and add {{salt}} of salt,    {{pepper}} of pepper,    and {{mayonnaise}} of mayonnaise.")
-                    .ja("茹でたじゃがいもをボウルに入れて塩　{{salt}}、　　　コショウ　{{pepper}}、　　　マヨネーズ　{{mayonnaise}}　　　を入れて混ぜます。")
-                    .zh("把煮好的土豆放在碗里，加{{salt}}的盐，   {{pepper}}的胡椒粉，   {{mayonnaise}}的蛋黄酱，并搅拌。")
-            )));
     }
 
-    pub(crate) fn build(&self, menu: IntentCookingMenu) -> Vec<Box<dyn ActionExecutable>> {
+    pub(crate) fn build(&self, recipe: &Recipe) -> Result<Vec<Box<dyn ActionExecutable>>> {
+        let menu = recipe.to_i18n();
         let mut steps: Vec<Box<dyn ActionExecutable>> = vec![];
         steps.push(
             Box::new(ExplainRecipeAction::new(
                 vec![],
                 CookingActionDetail::None,
                 SmartSpeakerI18nText::new()
-                    .ko(&format!("{} 요리를 시작합니다. 지금부터는 '헤이 링고' 로 저를 부르지 않아도 됩니다. 다음 작업으로 넘어가려면 '오케이' 또는 '다음' 과 같은 대답으로 알려주세요.", menu.to_i18n().ko))
-                    .en(&format!("Let's start {} cooking. You don't have to call me 'Hey Ringo' from now on. Please let me know if you want to proceed to the next step by answering 'OK' or 'Next'.", menu.to_i18n().en))
-                    .ja(&format!("{} 料理を始めます。 これからは「ヘイ、リンゴ」と呼ばなくてもいいです。 次の作業に進みたい場合は、「OK」や「次」などの答えで教えてください。", menu.to_i18n().ja))
-                    .zh(&format!("开始{}烹饪。 从现在开始，你不必叫我“嘿，拎郭”。 如果你想继续下一步，请回答　“可以”　或　“接下来”。", menu.to_i18n().zh))
+                    .ko(&format!("{} 요리를 시작합니다. 지금부터는 '헤이 링고' 로 저를 부르지 않아도 됩니다. 다음 작업으로 넘어가려면 '오케이' 또는 '다음' 과 같은 대답으로 알려주세요.", menu.ko))
+                    .en(&format!("Let's start {} cooking. You don't have to call me 'Hey Ringo' from now on. Please let me know if you want to proceed to the next step by answering 'OK' or 'Next'.", menu.en))
+                    .ja(&format!("{} 料理を始めます。 これからは「ヘイ、リンゴ」と呼ばなくてもいいです。 次の作業に進みたい場合は、「OK」や「次」などの答えで教えてください。", menu.ja))
+                    .zh(&format!("开始{}烹饪。 从现在开始，你不必叫我“嘿，拎郭”。 如果你想继续下一步，请回答　“可以”　或　“接下来”。", menu.zh))
             )));
         steps.push(
             Box::new(ExplainRecipeAction::new(
-                menu.to_ingredient(),
+                recipe.to_ingredient()?,
                 CookingActionDetail::ExplainNonMutableIngredient,
                 SmartSpeakerI18nText::new()
                     .ko("요리 재료 설명을 시작합니다. {{additional_explain}} 가 필요합니다. 다시 한 번 들으시려면 '다시 알려 줘' 라고 말씀해주세요.")
@@ -962,13 +840,11 @@ impl CookingStepBuilder {
                     .ja("食材の説明を始めます。{{additional_explain}} が必要です。もう一度聞きたい場合は、「もう一度教えて」と言ってください。")
                     .zh("让我们开始解释食材。{{additional_explain}} 是必需的。如果你想再听一遍，请说“再告诉我一遍”。")
             )));
-        match menu {
-            IntentCookingMenu::CarrotSalad => {
-                self.build_carrot_salad(&menu, &mut steps);
-            }
-            IntentCookingMenu::PotatoSalad => {
-                self.build_potato_salad(&menu, &mut steps);
+        for step in recipe.steps.iter() {
+            if step.requires_vision() && !self.vision {
+                continue;
             }
+            steps.push(self.build_step(recipe, step)?);
         }
 
         steps.push(
@@ -994,6 +870,6 @@ impl CookingStepBuilder {
             ))
         );
 
-        steps
+        Ok(steps)
     }
 }
@@ -1,10 +1,12 @@
 use std::ops::Div;
 use anyhow::{anyhow, Result};
+use serde::Deserialize;
 use crate::smart_speaker::models::core_model::WaitingInteraction;
 use crate::smart_speaker::models::intent_model::{IntentAction, IntentCookingMenu};
 use crate::smart_speaker::models::step_model::generic_step::{ActionExecutable, ActionTriggerType};
 use crate::smart_speaker::models::task_model::{SmartSpeakerTaskResult, SmartSpeakerTaskResultCode, SmartSpeakerTaskType, Task};
 use crate::smart_speaker::models::message_model::*;
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
 use crate::smart_speaker::models::revision_model::cooking_revision::{CookingRevisionEntity, CookingRevisionEntityProperty};
 use crate::smart_speaker::models::revision_model::Revision;
 use crate::smart_speaker::models::speak_model::MachineSpeechBoilerplate;
@@ -263,7 +265,8 @@ impl CookingIngredient {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum CookingIngredientName {
     Salt,
     Pepper,
@@ -482,18 +485,18 @@ impl CookingIngredientName {
         }
     }
 
-    pub(crate) fn get_weight_per_perimeter(&self, perimeter: f32) -> CookingIngredientAmount {
-        // perimeter error bound: 10%
-        // criteria value: (perimeter, weight(mg))
+    pub(crate) fn get_weight_per_area(&self, area: f32) -> CookingIngredientAmount {
+        // area error bound: 10%
+        // criteria value: (area in cm^2, weight(mg))
         // Returns the value in 25g increments with a margin of error.
         match self {
             CookingIngredientName::Carrot => {
                 let criteria = (60.0, 1000);
-                let adjusted_perimeter = perimeter * 1.05;
-                let calculated_weight = (adjusted_perimeter / criteria.0) * criteria.1 as f32;
+                let adjusted_area = area * 1.05;
+                let calculated_weight = (adjusted_area / criteria.0) * criteria.1 as f32;
                 let weight_with_error = calculated_weight * 0.95;
 
-                let rounded_weight = if perimeter >= 50.0 {
+                let rounded_weight = if area >= 50.0 {
                     ((weight_with_error / 25.0).round()) as i32 * 25
                 } else {
                     ((weight_with_error / 2.5).round()) as i32 * 2
@@ -502,11 +505,11 @@ impl CookingIngredientName {
             }
             CookingIngredientName::Potato => {
                 let criteria = (60.0, 700);
-                let adjusted_perimeter = perimeter * 1.05;
-                let calculated_weight = (adjusted_perimeter / criteria.0) * criteria.1 as f32;
+                let adjusted_area = area * 1.05;
+                let calculated_weight = (adjusted_area / criteria.0) * criteria.1 as f32;
                 let weight_with_error = calculated_weight * 0.95;
 
-                let rounded_weight = if perimeter >= 50.0 {
+                let rounded_weight = if area >= 50.0 {
                     ((weight_with_error / 25.0).round()) as i32 * 25
                 } else {
                     ((weight_with_error / 2.5).round()) as i32 * 2
@@ -696,6 +699,19 @@ impl CookingIngredientAmount {
         }
     }
 
+    pub(crate) fn from_template_code(code: &str, amount: f32) -> Result<CookingIngredientAmount> {
+        let quarter = CookingIngredientAmountQuarter::new((amount * 4.).round() as i32);
+        match code {
+            "mg" => Ok(CookingIngredientAmount::MilliGram(amount.round() as i32)),
+            "ml" => Ok(CookingIngredientAmount::MilliLiter(amount.round() as i32)),
+            "p" => Ok(CookingIngredientAmount::Piece(quarter)),
+            "tbsp" => Ok(CookingIngredientAmount::Tbsp(quarter)),
+            "tsp" => Ok(CookingIngredientAmount::Tsp(quarter)),
+            "cup" => Ok(CookingIngredientAmount::Cup(quarter)),
+            _ => Err(anyhow!("unknown ingredient unit: {}", code)),
+        }
+    }
+
     pub(crate) fn add(&self, rhs: CookingIngredientAmount) -> Result<CookingIngredientAmount> {
         // add with same unit. if not return error
         match self {
@@ -846,14 +862,16 @@ pub(crate) struct CookingTask {
 }
 
 impl CookingTask {
-    pub(crate) fn new(content: IntentContent, vision: bool) -> Result<Self> {
+    pub(crate) fn new(content: IntentContent, vision: bool, recipes: &RecipeCatalog) -> Result<Self> {
         match content.entities.get(0) {
             None => { Err(anyhow!("failed")) }
             Some(entity) => {
-                let menu = entity.as_any().downcast_ref::<IntentCookingMenu>().unwrap().clone();
+                let menu = entity.as_any().downcast_ref::<IntentCookingMenu>()
+                    .ok_or(anyhow!("no menu given"))?.clone();
+                let step = CookingStepBuilder::new(vision).build(menu.to_recipe(recipes)?)?;
                 Ok(CookingTask {
                     menu,
-                    step: CookingStepBuilder::new(vision).build(menu),
+                    step,
                     current_step: 0,
                     last_revision: None,
                     previous_success_result: None,
@@ -1,9 +1,13 @@
 use std::fmt;
 use std::fmt::{Debug, Formatter};
+use std::time::Duration;
 use opencv::core::Mat;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use crate::smart_speaker::models::frame_model::PixelFormat;
+use crate::smart_speaker::models::marker_model::MarkerTag;
 use crate::smart_speaker::models::message_model::SmartSpeakerI18nText;
-use crate::utils::camera_util::Camera;
+use crate::utils::camera_util::{Camera, ImageSequence, VideoFile};
 use crate::utils::pupil_util::Pupil;
 use crate::utils::vision_util::VisionType;
 
@@ -54,20 +58,42 @@ impl Capture {
             }
         }
     }
+
+    pub fn get_frame_interval(&self) -> Option<Duration> {
+        self.source.as_ref().and_then(|source| source.get_frame_interval())
+    }
 }
 
 pub(crate) trait CaptureSource {
     fn get_vision_type(&self) -> VisionType;
     fn get_frame(&mut self) -> Result<Mat>;
+    /// Pixel format of the last frame. opencv reads cameras and recordings as BGR.
+    fn get_pixel_format(&self) -> PixelFormat {
+        PixelFormat::Bgr
+    }
+    /// Source clock of the last frame in seconds. None if the source has no clock.
+    fn get_source_timestamp(&self) -> Option<f64> {
+        None
+    }
+    /// Time between two frames of the source. None if unknown.
+    fn get_frame_interval(&self) -> Option<Duration> {
+        None
+    }
 }
 
 pub(crate) struct PupilCaptureSource {
     pub pupil: Pupil,
+    format: PixelFormat,
+    timestamp: Option<f64>,
 }
 
 impl PupilCaptureSource {
     pub fn new(pupil: Pupil) -> Self {
-        Self { pupil }
+        Self {
+            pupil,
+            format: PixelFormat::Bgr,
+            timestamp: None,
+        }
     }
 }
 
@@ -76,7 +102,16 @@ impl CaptureSource for PupilCaptureSource {
         VisionType::Pupil
     }
     fn get_frame(&mut self) -> Result<Mat> {
-        self.pupil.get_frame()
+        let (frame, format, timestamp) = self.pupil.get_frame_with_info()?;
+        self.format = format;
+        self.timestamp = Some(timestamp);
+        Ok(frame)
+    }
+    fn get_pixel_format(&self) -> PixelFormat {
+        self.format
+    }
+    fn get_source_timestamp(&self) -> Option<f64> {
+        self.timestamp
     }
 }
 
@@ -97,9 +132,65 @@ impl CaptureSource for CameraCaptureSource {
     fn get_frame(&mut self) -> Result<Mat> {
         self.camera.get_frame()
     }
+    fn get_frame_interval(&self) -> Option<Duration> {
+        self.camera.frame_interval()
+    }
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+/// Replays a recorded video file.
+pub(crate) struct VideoFileCaptureSource {
+    pub video: VideoFile,
+}
+
+impl VideoFileCaptureSource {
+    pub fn new(video: VideoFile) -> Self {
+        Self { video }
+    }
+}
+
+impl CaptureSource for VideoFileCaptureSource {
+    fn get_vision_type(&self) -> VisionType {
+        VisionType::Recording
+    }
+    fn get_frame(&mut self) -> Result<Mat> {
+        self.video.get_frame()
+    }
+    fn get_source_timestamp(&self) -> Option<f64> {
+        self.video.position_secs()
+    }
+}
+
+/// Replays a directory of recorded frames.
+pub(crate) struct ImageSequenceCaptureSource {
+    pub images: ImageSequence,
+}
+
+impl ImageSequenceCaptureSource {
+    pub fn new(images: ImageSequence) -> Self {
+        Self { images }
+    }
+}
+
+impl CaptureSource for ImageSequenceCaptureSource {
+    fn get_vision_type(&self) -> VisionType {
+        VisionType::Recording
+    }
+    fn get_frame(&mut self) -> Result<Mat> {
+        self.images.get_frame()
+    }
+}
+
+/// Object chosen by how long the user looked at it.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DwellSelection {
+    Selected(usize),
+    /// No object was looked at.
+    None,
+    /// No object was looked at clearly longer than the others. The candidates, longest dwell first.
+    Unsure(Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Copy, Deserialize)]
 pub(crate) struct DetectionDetail {
     pub(crate) detection_mode: DetectionMode,
     pub(crate) detectable: DetectableObject,
@@ -116,13 +207,15 @@ impl DetectionDetail {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum DetectionMode {
     None,
     Aruco,
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum DetectableObject {
     Carrot,
     Potato,
@@ -161,6 +254,8 @@ impl DetectableObject {
 pub(crate) enum VisionAction {
     None,
     ObjectDetection(DetectionDetail),
+    /// Whether the marker tagged with this ingredient or tool is in view.
+    MarkerPresence(MarkerTag),
 }
 
 impl VisionAction {
@@ -180,6 +275,9 @@ impl VisionAction {
                     .zh(&format!("使用 aruco 检测{}", object.detectable.to_i18n().zh))
                     .ko(&format!("aruco로 {}를 감지", object.detectable.to_i18n().ko))
             }
+            VisionAction::MarkerPresence(tag) => {
+                tag.to_i18n()
+            }
         }
     }
 
@@ -187,6 +285,7 @@ impl VisionAction {
         match self {
             VisionAction::None => { None }
             VisionAction::ObjectDetection(detail) => { Some(detail.detectable.clone()) }
+            VisionAction::MarkerPresence(_) => { None }
         }
     }
 }
@@ -241,24 +340,63 @@ impl VisionSlot for VisionObject {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MarkerPresence {
+    Missing,
+    /// Detected in the recent frames.
+    Present,
+    /// Present and looked at.
+    NearGaze,
+}
+
+/// Presence of a tagged marker.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct VisionMarker {
+    pub(crate) tag: MarkerTag,
+    pub(crate) presence: MarkerPresence,
+}
+
+impl VisionMarker {
+    pub(crate) fn new(tag: MarkerTag, presence: MarkerPresence) -> Self {
+        Self {
+            tag,
+            presence,
+        }
+    }
+}
+
+impl VisionSlot for VisionMarker {
+    fn clone_box(&self) -> Box<dyn VisionSlot> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) struct VisionObjectSize {
-    pub(crate) perimeter: f32,
+    /// Area on the marker plane in cm^2.
+    pub(crate) area: f32,
+    /// Longer side of the bounding rect on the marker plane in cm.
     pub(crate) width: f32,
     pub(crate) height: f32,
 }
 
 impl VisionObjectSize {
-    pub(crate) fn new(perimeter: f32, width: f32, height: f32) -> Self {
+    pub(crate) fn new(area: f32, width: f32, height: f32) -> Self {
         Self {
-            perimeter,
+            area,
             width,
             height,
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub(crate) enum VisionObjectShape {
     Triangle,
     Square,
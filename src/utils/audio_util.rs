@@ -0,0 +1,129 @@
+use std::fs;
+use std::io::Write;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+
+/// Sample rate required by the picovoice engines.
+pub(crate) const SAMPLE_RATE: u32 = 16000;
+/// Samples per frame. Same as the default frame length of pv_recorder.
+pub(crate) const FRAME_LENGTH: usize = 512;
+/// Duration of one frame. Audio is read and requested at this pace.
+pub(crate) const FRAME_DURATION: Duration = Duration::from_millis(FRAME_LENGTH as u64 * 1000 / SAMPLE_RATE as u64);
+
+/// Short-time energy of the frame in dBFS. Silence is clamped to -100dB.
+pub(crate) fn frame_energy_db(pcm: &[i16]) -> f32 {
+    if pcm.is_empty() {
+        return -100.
+    }
+    let mean_square = pcm.iter()
+        .map(|s| { let s = *s as f64 / i16::MAX as f64; s * s })
+        .sum::<f64>() / pcm.len() as f64;
+    (10. * (mean_square + 1e-10).log10()).max(-100.) as f32
+}
+
+/// Ratio of sign changes between neighbouring samples. 0.0 to 1.0.
+pub(crate) fn zero_crossing_rate(pcm: &[i16]) -> f32 {
+    if pcm.len() < 2 {
+        return 0.
+    }
+    let crossings = pcm.windows(2)
+        .filter(|w| (w[0] >= 0) != (w[1] >= 0))
+        .count();
+    crossings as f32 / (pcm.len() - 1) as f32
+}
+
+/// 16-bit PCM wav file. Multichannel files are downmixed to mono.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct WavFile {
+    pub sample_rate: u32,
+    pub samples: Vec<i16>,
+}
+
+impl WavFile {
+    pub fn open(path: &str) -> Result<Self> {
+        let bytes = fs::read(path).map_err(|e| anyhow!("failed to read wav file {}: {}", path, e))?;
+        Self::from_bytes(&bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(anyhow!("not a wav file"));
+        }
+        let mut format: Option<(u16, u16, u32, u16)> = None;
+        let mut data: Option<&[u8]> = None;
+        let mut offset = 12;
+        while offset + 8 <= bytes.len() {
+            let id = &bytes[offset..offset + 4];
+            let size = u32::from_le_bytes(bytes[offset + 4..offset + 8].try_into()?) as usize;
+            let body = &bytes[offset + 8..(offset + 8 + size).min(bytes.len())];
+            match id {
+                b"fmt " => {
+                    if body.len() < 16 {
+                        return Err(anyhow!("broken fmt chunk"));
+                    }
+                    format = Some((
+                        u16::from_le_bytes(body[0..2].try_into()?),
+                        u16::from_le_bytes(body[2..4].try_into()?),
+                        u32::from_le_bytes(body[4..8].try_into()?),
+                        u16::from_le_bytes(body[14..16].try_into()?),
+                    ));
+                }
+                b"data" => {
+                    data = Some(body);
+                }
+                _ => {}
+            }
+            // chunks are word aligned
+            offset += 8 + size + (size % 2);
+        }
+        let (audio_format, channels, sample_rate, bits_per_sample) = format.ok_or(anyhow!("no fmt chunk found"))?;
+        let data = data.ok_or(anyhow!("no data chunk found"))?;
+        if audio_format != 1 || bits_per_sample != 16 {
+            return Err(anyhow!("only 16-bit PCM wav is supported"));
+        }
+        if channels == 0 {
+            return Err(anyhow!("wav file has no channel"));
+        }
+        let samples = data.chunks_exact(2 * channels as usize)
+            .map(|frame| {
+                let sum = frame.chunks_exact(2)
+                    .map(|s| i16::from_le_bytes([s[0], s[1]]) as i32)
+                    .sum::<i32>();
+                (sum / channels as i32) as i16
+            })
+            .collect::<Vec<i16>>();
+        Ok(Self {
+            sample_rate,
+            samples,
+        })
+    }
+
+    pub fn duration_secs(&self) -> f32 {
+        self.samples.len() as f32 / self.sample_rate as f32
+    }
+}
+
+/// Write mono 16-bit PCM wav file.
+pub(crate) fn write_wav(path: &str, sample_rate: u32, samples: &[i16]) -> Result<()> {
+    let data_size = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    bytes.extend_from_slice(&1u16.to_le_bytes()); // mono
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes()); // byte rate
+    bytes.extend_from_slice(&2u16.to_le_bytes()); // block align
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for sample in samples {
+        bytes.extend_from_slice(&sample.to_le_bytes());
+    }
+    let mut file = fs::File::create(path).map_err(|e| anyhow!("failed to create wav file {}: {}", path, e))?;
+    file.write_all(&bytes)?;
+    Ok(())
+}
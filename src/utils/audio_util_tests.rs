@@ -0,0 +1,37 @@
+#[cfg(test)]
+mod audio_util_tests {
+    use super::super::audio_util::*;
+
+    #[test]
+    fn wav_round_trip_test() {
+        let path = std::env::temp_dir().join("vas_audio_util_round_trip.wav");
+        let path = path.to_str().unwrap();
+        let samples = (0..1000).map(|i| (i * 10) as i16).collect::<Vec<i16>>();
+        write_wav(path, SAMPLE_RATE, &samples).unwrap();
+        let wav = WavFile::open(path).unwrap();
+        assert_eq!(wav.sample_rate, SAMPLE_RATE);
+        assert_eq!(wav.samples, samples);
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn wav_not_riff_test() {
+        assert!(WavFile::from_bytes(b"not a wav file at all").is_err());
+    }
+
+    #[test]
+    fn frame_energy_db_test() {
+        assert_eq!(frame_energy_db(&[0; FRAME_LENGTH]), -100.);
+        let full_scale = vec![i16::MAX; FRAME_LENGTH];
+        assert!(frame_energy_db(&full_scale).abs() < 0.01);
+        let half_scale = vec![i16::MAX / 2; FRAME_LENGTH];
+        assert!((frame_energy_db(&half_scale) + 6.02).abs() < 0.1);
+    }
+
+    #[test]
+    fn zero_crossing_rate_test() {
+        assert_eq!(zero_crossing_rate(&[100; FRAME_LENGTH]), 0.);
+        let alternating = (0..FRAME_LENGTH).map(|i| if i % 2 == 0 { 100 } else { -100 }).collect::<Vec<i16>>();
+        assert_eq!(zero_crossing_rate(&alternating), 1.);
+    }
+}
@@ -0,0 +1,261 @@
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use anyhow::{anyhow, Result};
+use opencv::{calib3d, imgproc, objdetect};
+use opencv::core::{Mat, Point2f, Point3f, Size, TermCriteria, TermCriteria_Type, Vector, CV_64F};
+use opencv::prelude::*;
+use opencv::types::{VectorOfMat, VectorOfVectorOfPoint2f, VectorOfVectorOfPoint3f};
+use serde::{Deserialize, Serialize};
+use crate::smart_speaker::models::marker_model::ArucoDictionary;
+use crate::utils::camera_util::CameraDevice;
+use crate::utils::config_util::Config;
+use crate::utils::vision_util::VisionType;
+
+/// Fewest views of the board for `calibrate`.
+pub(crate) const MIN_CALIBRATION_VIEWS: usize = 5;
+
+/// Calibration pattern shown to the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CalibrationBoard {
+    Chessboard,
+    /// Chessboard with `--aruco-dictionary` markers in the white squares. It may be partly out of view.
+    Charuco,
+}
+
+impl FromStr for CalibrationBoard {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "chessboard" => Ok(CalibrationBoard::Chessboard),
+            "charuco" => Ok(CalibrationBoard::Charuco),
+            _ => Err(anyhow!("unsupported calibration board: {}", s)),
+        }
+    }
+}
+
+impl Display for CalibrationBoard {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationBoard::Chessboard => write!(f, "chessboard"),
+            CalibrationBoard::Charuco => write!(f, "charuco"),
+        }
+    }
+}
+
+/// `<columns>x<rows>`, e.g. 9x6.
+pub(crate) fn parse_board_size(s: &str) -> Result<(u32, u32)> {
+    let (columns, rows) = s.to_lowercase().split_once('x')
+        .and_then(|(columns, rows)| Some((columns.trim().parse::<u32>().ok()?, rows.trim().parse::<u32>().ok()?)))
+        .ok_or(anyhow!("board size must be <columns>x<rows>, e.g. 9x6: {}", s))?;
+    if columns < 2 || rows < 2 {
+        return Err(anyhow!("board must be at least 2x2: {}", s));
+    }
+    Ok((columns, rows))
+}
+
+/// Geometry of the calibration board.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct BoardSpec {
+    pub board: CalibrationBoard,
+    /// Inner corners of a chessboard, squares of a ChArUco board.
+    pub columns: u32,
+    pub rows: u32,
+    pub square_mm: f32,
+    /// Side of the ChArUco markers.
+    pub marker_mm: f32,
+    pub dictionary: ArucoDictionary,
+}
+
+impl BoardSpec {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Self {
+            board: config.calibration_board,
+            columns: config.calibration_board_size.0,
+            rows: config.calibration_board_size.1,
+            square_mm: config.calibration_square_mm,
+            marker_mm: config.calibration_marker_mm,
+            dictionary: config.aruco_dictionary,
+        }
+    }
+
+    /// Corners of a chessboard on the board plane in mm, row by row.
+    fn chessboard_corners(&self) -> Vector<Point3f> {
+        (0..self.rows).flat_map(|row| (0..self.columns).map(move |column| (column, row)))
+            .map(|(column, row)| Point3f::new(column as f32 * self.square_mm, row as f32 * self.square_mm, 0.))
+            .collect()
+    }
+
+    /// Board corners found in a BGR frame, on the board plane and in the frame.
+    /// None if the board is not (enough) in view.
+    pub(crate) fn find(&self, frame: &Mat) -> Result<Option<(Vector<Point3f>, Vector<Point2f>)>> {
+        let mut gray = Mat::default();
+        imgproc::cvt_color(frame, &mut gray, imgproc::COLOR_BGR2GRAY, 0)?;
+        match self.board {
+            CalibrationBoard::Chessboard => {
+                let mut corners = Vector::<Point2f>::new();
+                let pattern = Size::new(self.columns as i32, self.rows as i32);
+                let found = calib3d::find_chessboard_corners(&gray, pattern, &mut corners,
+                                                             calib3d::CALIB_CB_ADAPTIVE_THRESH | calib3d::CALIB_CB_NORMALIZE_IMAGE)?;
+                if !found {
+                    return Ok(None);
+                }
+                imgproc::corner_sub_pix(&gray, &mut corners, Size::new(11, 11), Size::new(-1, -1),
+                                        TermCriteria::new(TermCriteria_Type::COUNT as i32 + TermCriteria_Type::EPS as i32, 30, 0.001)?)?;
+                Ok(Some((self.chessboard_corners(), corners)))
+            }
+            CalibrationBoard::Charuco => {
+                let dictionary = self.dictionary.dictionary()?;
+                let board = objdetect::CharucoBoard::new(Size::new(self.columns as i32, self.rows as i32),
+                                                         self.square_mm, self.marker_mm, &dictionary, &opencv::core::no_array())?;
+                let detector = objdetect::CharucoDetector::new(&board, &objdetect::CharucoParameters::default()?,
+                                                               &objdetect::DetectorParameters::default()?,
+                                                               objdetect::RefineParameters::new(10., 3., true)?)?;
+                let mut charuco_corners = Vector::<Point2f>::new();
+                let mut charuco_ids = Vector::<i32>::new();
+                let mut marker_corners = VectorOfVectorOfPoint2f::new();
+                let mut marker_ids = Vector::<i32>::new();
+                detector.detect_board(&gray, &mut charuco_corners, &mut charuco_ids, &mut marker_corners, &mut marker_ids)?;
+                // fewer corners do not constrain the distortion
+                if charuco_corners.len() < 6 {
+                    return Ok(None);
+                }
+                let mut object_points = Vector::<Point3f>::new();
+                let mut image_points = Vector::<Point2f>::new();
+                board.match_image_points(&charuco_corners, &charuco_ids, &mut object_points, &mut image_points)?;
+                Ok(Some((object_points, image_points)))
+            }
+        }
+    }
+}
+
+/// Intrinsics of one camera, stored as json by `calibrate-camera`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CameraCalibration {
+    pub device: String,
+    /// Frame size of the calibration views.
+    pub image_width: i32,
+    pub image_height: i32,
+    /// fx, 0, cx, 0, fy, cy, 0, 0, 1
+    pub camera_matrix: [f64; 9],
+    /// k1, k2, p1, p2, k3
+    pub distortion: Vec<f64>,
+    /// RMS reprojection error in pixels.
+    pub rms_error: f64,
+    pub views: usize,
+}
+
+impl CameraCalibration {
+    pub(crate) fn calibrate(device: &str, image_size: (i32, i32), views: &[(Vector<Point3f>, Vector<Point2f>)]) -> Result<Self> {
+        if views.len() < MIN_CALIBRATION_VIEWS {
+            return Err(anyhow!("{} views of the board, at least {} needed", views.len(), MIN_CALIBRATION_VIEWS));
+        }
+        let object_points = views.iter().map(|(object, _)| object.clone()).collect::<VectorOfVectorOfPoint3f>();
+        let image_points = views.iter().map(|(_, image)| image.clone()).collect::<VectorOfVectorOfPoint2f>();
+        let mut camera_matrix = Mat::default();
+        let mut distortion = Mat::default();
+        let mut rvecs = VectorOfMat::new();
+        let mut tvecs = VectorOfMat::new();
+        let rms_error = calib3d::calibrate_camera(&object_points, &image_points, Size::new(image_size.0, image_size.1),
+                                                  &mut camera_matrix, &mut distortion, &mut rvecs, &mut tvecs, 0,
+                                                  TermCriteria::new(TermCriteria_Type::COUNT as i32 + TermCriteria_Type::EPS as i32, 30, f64::EPSILON)?)?;
+        let mut matrix = [0.; 9];
+        for (i, value) in matrix.iter_mut().enumerate() {
+            *value = *camera_matrix.at_2d::<f64>(i as i32 / 3, i as i32 % 3)?;
+        }
+        Ok(Self {
+            device: device.to_string(),
+            image_width: image_size.0,
+            image_height: image_size.1,
+            camera_matrix: matrix,
+            distortion: distortion.data_typed::<f64>()?.to_vec(),
+            rms_error,
+            views: views.len(),
+        })
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read calibration {}: {}", path.display(), e))?;
+        serde_json::from_str::<Self>(&text).map_err(|e| anyhow!("invalid calibration {}: {}", path.display(), e))
+    }
+
+    pub(crate) fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .map_err(|e| anyhow!("failed to write calibration {}: {}", path.display(), e))
+    }
+
+    /// Same camera at another frame size. The focal lengths and the center scale with the frame.
+    pub(crate) fn scaled_to(&self, width: i32, height: i32) -> Self {
+        let sx = width as f64 / self.image_width as f64;
+        let sy = height as f64 / self.image_height as f64;
+        let mut scaled = self.clone();
+        for i in [0, 2] {
+            scaled.camera_matrix[i] *= sx;
+        }
+        for i in [4, 5] {
+            scaled.camera_matrix[i] *= sy;
+        }
+        scaled.image_width = width;
+        scaled.image_height = height;
+        scaled
+    }
+
+    /// Pixels of a `frame_size` frame moved to where a lens without distortion would show them.
+    pub(crate) fn undistort_points(&self, points: &Vector<Point2f>, frame_size: (i32, i32)) -> Result<Vector<Point2f>> {
+        if points.is_empty() {
+            return Ok(Vector::new());
+        }
+        let calibration = self.scaled_to(frame_size.0, frame_size.1);
+        let matrix = [&calibration.camera_matrix[0..3], &calibration.camera_matrix[3..6], &calibration.camera_matrix[6..9]];
+        let camera_matrix = Mat::from_slice_2d(&matrix)?;
+        let distortion = match calibration.distortion.is_empty() {
+            true => Mat::zeros(1, 5, CV_64F)?.to_mat()?,
+            false => Mat::from_slice_2d(&[calibration.distortion.as_slice()])?,
+        };
+        let mut undistorted = Vector::<Point2f>::new();
+        // the camera matrix as the new projection keeps the points in pixels
+        calib3d::undistort_points(points, &mut undistorted, &camera_matrix, &distortion, &opencv::core::no_array(), &camera_matrix)?;
+        Ok(undistorted)
+    }
+
+    pub(crate) fn undistort_contours(&self, contours: &VectorOfVectorOfPoint2f, frame_size: (i32, i32)) -> Result<VectorOfVectorOfPoint2f> {
+        contours.iter().map(|contour| self.undistort_points(&contour, frame_size)).collect()
+    }
+}
+
+/// Name of the calibration file of the camera in the config. Recordings use the calibration of `--camera-device`.
+pub(crate) fn calibration_key(config: &Config) -> Option<String> {
+    match config.vision_type {
+        VisionType::None => None,
+        VisionType::Pupil => Some("pupil-world".to_string()),
+        VisionType::BuiltInCamera | VisionType::Recording => Some(match &config.camera_device {
+            CameraDevice::Index(index) => format!("camera-{}", index),
+            CameraDevice::Path(path) => Path::new(path).file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or(path.clone())
+                .chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+                .collect(),
+        }),
+    }
+}
+
+pub(crate) fn calibration_path(dir: &str, key: &str) -> PathBuf {
+    Path::new(dir).join(format!("{}.json", key))
+}
+
+/// Calibration of the camera in the config. None if the camera was not calibrated.
+pub(crate) fn load_calibration(config: &Config) -> Result<Option<CameraCalibration>> {
+    let path = match calibration_key(config) {
+        Some(key) => calibration_path(&config.calibration_dir, &key),
+        None => return Ok(None),
+    };
+    if !path.exists() {
+        return Ok(None);
+    }
+    CameraCalibration::load(&path).map(Some)
+}
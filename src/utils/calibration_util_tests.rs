@@ -0,0 +1,156 @@
+#[cfg(test)]
+mod calibration_util_tests {
+    use std::str::FromStr;
+    use opencv::calib3d;
+    use opencv::core::{Mat, Point, Point2f, Point3f, Rect, Scalar, Vector, CV_8UC3};
+    use opencv::imgproc;
+    use opencv::prelude::*;
+    use crate::smart_speaker::models::marker_model::ArucoDictionary;
+    use crate::utils::camera_util::CameraDevice;
+    use crate::utils::config_util::Config;
+    use crate::utils::vision_util::VisionType;
+    use super::super::calibration_util::*;
+
+    fn calibration(distortion: Vec<f64>) -> CameraCalibration {
+        CameraCalibration {
+            device: "camera-0".to_string(),
+            image_width: 640,
+            image_height: 480,
+            camera_matrix: [500., 0., 320., 0., 500., 240., 0., 0., 1.],
+            distortion,
+            rms_error: 0.2,
+            views: 20,
+        }
+    }
+
+    fn chessboard_spec() -> BoardSpec {
+        BoardSpec {
+            board: CalibrationBoard::Chessboard,
+            columns: 9,
+            rows: 6,
+            square_mm: 25.,
+            marker_mm: 18.,
+            dictionary: ArucoDictionary::default(),
+        }
+    }
+
+    /// Project the board points through the pinhole camera of `calibration`.
+    fn project(calibration: &CameraCalibration, points: &Vector<Point3f>, rvec: [f64; 3], tvec: [f64; 3]) -> Vector<Point2f> {
+        let matrix = [&calibration.camera_matrix[0..3], &calibration.camera_matrix[3..6], &calibration.camera_matrix[6..9]];
+        let mut projected = Vector::<Point2f>::new();
+        calib3d::project_points(points, &Mat::from_slice_2d(&[rvec]).unwrap(), &Mat::from_slice_2d(&[tvec]).unwrap(),
+                                &Mat::from_slice_2d(&matrix).unwrap(), &Mat::from_slice_2d(&[calibration.distortion.as_slice()]).unwrap(),
+                                &mut projected, &mut opencv::core::no_array(), 0.).unwrap();
+        projected
+    }
+
+    #[test]
+    fn board_config_test() {
+        assert_eq!(parse_board_size("9x6").unwrap(), (9, 6));
+        assert_eq!(parse_board_size("7X5").unwrap(), (7, 5));
+        assert!(parse_board_size("9").is_err());
+        assert!(parse_board_size("1x6").is_err());
+        assert_eq!(CalibrationBoard::from_str("ChArUco").unwrap(), CalibrationBoard::Charuco);
+        assert!(CalibrationBoard::from_str("circles").is_err());
+    }
+
+    #[test]
+    fn calibration_key_test() {
+        let mut config = Config::new();
+        assert_eq!(calibration_key(&config), None);
+        config.vision_type = VisionType::Pupil;
+        assert_eq!(calibration_key(&config), Some("pupil-world".to_string()));
+        config.vision_type = VisionType::BuiltInCamera;
+        assert_eq!(calibration_key(&config), Some("camera-0".to_string()));
+        config.camera_device = CameraDevice::Path("/dev/v4l/by-id/usb-Kitchen Cam-video-index0".to_string());
+        assert_eq!(calibration_key(&config), Some("usb-Kitchen_Cam-video-index0".to_string()));
+        // recordings are undistorted with the calibration of the camera they were recorded with
+        config.vision_type = VisionType::Recording;
+        assert_eq!(calibration_key(&config), Some("usb-Kitchen_Cam-video-index0".to_string()));
+        assert_eq!(calibration_path("calibration", "camera-0").to_str().unwrap(), "calibration/camera-0.json");
+    }
+
+    #[test]
+    fn calibration_file_test() {
+        let path = std::env::temp_dir().join("vas_calibration_util").join("camera-0.json");
+        let saved = calibration(vec![-0.2, 0.05, 0., 0., 0.]);
+        saved.save(&path).unwrap();
+        assert_eq!(CameraCalibration::load(&path).unwrap(), saved);
+        let mut config = Config::new();
+        config.vision_type = VisionType::BuiltInCamera;
+        config.calibration_dir = path.parent().unwrap().to_str().unwrap().to_string();
+        assert_eq!(load_calibration(&config).unwrap(), Some(saved));
+        config.camera_device = CameraDevice::Index(5);
+        assert_eq!(load_calibration(&config).unwrap(), None);
+    }
+
+    #[test]
+    fn scaled_to_test() {
+        let scaled = calibration(vec![]).scaled_to(1280, 720);
+        assert_eq!(scaled.camera_matrix, [1000., 0., 640., 0., 750., 360., 0., 0., 1.]);
+        assert_eq!((scaled.image_width, scaled.image_height), (1280, 720));
+    }
+
+    #[test]
+    fn undistort_points_test() {
+        let lens = calibration(vec![-0.3, 0.1, 0., 0., 0.]);
+        let points = Vector::<Point3f>::from_iter([Point3f::new(0., 0., 1.), Point3f::new(0.4, 0.3, 1.), Point3f::new(-0.5, 0.2, 1.)]);
+        let distorted = project(&lens, &points, [0.; 3], [0.; 3]);
+        // the corners of the frame are pulled in by barrel distortion
+        assert!(distorted.get(1).unwrap().x < 320. + 0.4 * 500.);
+        let undistorted = lens.undistort_points(&distorted, (640, 480)).unwrap();
+        for (point, ideal) in undistorted.iter().zip(points.iter()) {
+            assert!((point.x - (320. + ideal.x * 500.)).abs() < 0.1);
+            assert!((point.y - (240. + ideal.y * 500.)).abs() < 0.1);
+        }
+        // without distortion the points stay
+        let straight = calibration(vec![]).undistort_points(&distorted, (640, 480)).unwrap();
+        assert_eq!(straight.iter().map(|p| (p.x.round(), p.y.round())).collect::<Vec<_>>(),
+                   distorted.iter().map(|p| (p.x.round(), p.y.round())).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn find_chessboard_test() {
+        let spec = chessboard_spec();
+        // 10x7 squares of 40px have 9x6 inner corners
+        let mut frame = Mat::new_rows_cols_with_default(480, 640, CV_8UC3, Scalar::all(255.)).unwrap();
+        for row in 0..7 {
+            for column in 0..10 {
+                if (row + column) % 2 == 0 {
+                    imgproc::rectangle(&mut frame, Rect::new(120 + column * 40, 100 + row * 40, 40, 40),
+                                       Scalar::all(0.), -1, imgproc::LINE_8, 0).unwrap();
+                }
+            }
+        }
+        let (object, image) = spec.find(&frame).unwrap().unwrap();
+        assert_eq!((object.len(), image.len()), (54, 54));
+        assert_eq!(object.get(53).unwrap(), Point3f::new(200., 125., 0.));
+        let corner = Point::new(160, 140);
+        assert!(image.iter().any(|p| (p.x - corner.x as f32).abs() < 1. && (p.y - corner.y as f32).abs() < 1.));
+        let blank = Mat::new_rows_cols_with_default(480, 640, CV_8UC3, Scalar::all(255.)).unwrap();
+        assert!(spec.find(&blank).unwrap().is_none());
+    }
+
+    #[test]
+    fn calibrate_test() {
+        let lens = calibration(vec![-0.2, 0.05, 0., 0., 0.]);
+        let board = (0..6).flat_map(|row| (0..9).map(move |column| Point3f::new(column as f32 * 25., row as f32 * 25., 0.)))
+            .collect::<Vector<Point3f>>();
+        let poses = [
+            ([0.1, 0.2, 0.], [-100., -60., 400.]),
+            ([-0.2, 0.1, 0.1], [-120., -80., 450.]),
+            ([0.3, -0.1, -0.1], [-90., -50., 380.]),
+            ([-0.1, -0.3, 0.2], [-110., -70., 500.]),
+            ([0.2, 0.3, -0.2], [-80., -40., 420.]),
+            ([0., -0.2, 0.3], [-130., -90., 470.]),
+        ];
+        let views = poses.iter().map(|(rvec, tvec)| (board.clone(), project(&lens, &board, *rvec, *tvec))).collect::<Vec<_>>();
+        assert!(CameraCalibration::calibrate("camera-0", (640, 480), &views[..4]).is_err());
+        let calibrated = CameraCalibration::calibrate("camera-0", (640, 480), &views).unwrap();
+        assert_eq!(calibrated.views, 6);
+        assert!(calibrated.rms_error < 0.1);
+        assert!((calibrated.camera_matrix[0] - 500.).abs() < 5.);
+        assert!((calibrated.camera_matrix[2] - 320.).abs() < 5.);
+        assert!((calibrated.distortion[0] + 0.2).abs() < 0.05);
+    }
+}
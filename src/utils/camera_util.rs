@@ -1,16 +1,209 @@
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
 use opencv::core::Mat;
 use opencv::prelude::*;
-use opencv::videoio::{VideoCapture, CAP_ANY};
+use opencv::imgcodecs;
+use opencv::videoio::{VideoCapture, CAP_ANY, CAP_AVFOUNDATION, CAP_DSHOW, CAP_FFMPEG, CAP_GSTREAMER, CAP_MSMF, CAP_V4L2,
+                      CAP_PROP_FOURCC, CAP_PROP_FPS, CAP_PROP_FRAME_HEIGHT, CAP_PROP_FRAME_WIDTH, CAP_PROP_POS_FRAMES, CAP_PROP_POS_MSEC};
 use anyhow::{anyhow, Result};
+use crate::utils::config_util::Config;
+
+/// Frame rate of image sequences when none is given.
+pub(crate) const DEFAULT_RECORDING_FPS: f64 = 30.;
+const IMAGE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "bmp", "tiff"];
+
+/// Camera to open, by opencv index or by device path such as /dev/v4l/by-id/usb-...-video-index0.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum CameraDevice {
+    Index(i32),
+    Path(String),
+}
+
+impl FromStr for CameraDevice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "" => Err(anyhow!("empty camera device")),
+            s => match s.parse::<i32>() {
+                Ok(index) if index >= 0 => Ok(CameraDevice::Index(index)),
+                Ok(_) => Err(anyhow!("invalid camera index: {}", s)),
+                Err(_) => Ok(CameraDevice::Path(s.to_string())),
+            },
+        }
+    }
+}
+
+impl Display for CameraDevice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CameraDevice::Index(index) => write!(f, "{}", index),
+            CameraDevice::Path(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+/// opencv videoio backend of a camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum CameraBackend {
+    Any,
+    V4l2,
+    Gstreamer,
+    Ffmpeg,
+    AvFoundation,
+    DirectShow,
+    MediaFoundation,
+}
+
+impl CameraBackend {
+    pub(crate) fn api_preference(&self) -> i32 {
+        match self {
+            CameraBackend::Any => CAP_ANY,
+            CameraBackend::V4l2 => CAP_V4L2,
+            CameraBackend::Gstreamer => CAP_GSTREAMER,
+            CameraBackend::Ffmpeg => CAP_FFMPEG,
+            CameraBackend::AvFoundation => CAP_AVFOUNDATION,
+            CameraBackend::DirectShow => CAP_DSHOW,
+            CameraBackend::MediaFoundation => CAP_MSMF,
+        }
+    }
+}
+
+impl FromStr for CameraBackend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "any" => Ok(CameraBackend::Any),
+            "v4l2" | "v4l" => Ok(CameraBackend::V4l2),
+            "gstreamer" => Ok(CameraBackend::Gstreamer),
+            "ffmpeg" => Ok(CameraBackend::Ffmpeg),
+            "avfoundation" => Ok(CameraBackend::AvFoundation),
+            "dshow" => Ok(CameraBackend::DirectShow),
+            "msmf" => Ok(CameraBackend::MediaFoundation),
+            _ => Err(anyhow!("unsupported camera backend: {}", s)),
+        }
+    }
+}
+
+impl Display for CameraBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CameraBackend::Any => write!(f, "any"),
+            CameraBackend::V4l2 => write!(f, "v4l2"),
+            CameraBackend::Gstreamer => write!(f, "gstreamer"),
+            CameraBackend::Ffmpeg => write!(f, "ffmpeg"),
+            CameraBackend::AvFoundation => write!(f, "avfoundation"),
+            CameraBackend::DirectShow => write!(f, "dshow"),
+            CameraBackend::MediaFoundation => write!(f, "msmf"),
+        }
+    }
+}
+
+/// Four character code of a camera pixel format, e.g. MJPG or YUYV. `mjpeg` is accepted for MJPG.
+pub(crate) fn parse_fourcc(s: &str) -> Result<String> {
+    if s.eq_ignore_ascii_case("mjpeg") {
+        return Ok("MJPG".to_string());
+    }
+    if s.len() != 4 || !s.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return Err(anyhow!("fourcc must be 4 characters, e.g. MJPG: {}", s));
+    }
+    Ok(s.to_string())
+}
+
+/// Same as opencv `VideoWriter::fourcc`.
+pub(crate) fn fourcc_code(fourcc: &str) -> i32 {
+    let mut bytes = [b' '; 4];
+    for (byte, c) in bytes.iter_mut().zip(fourcc.bytes()) {
+        *byte = c;
+    }
+    i32::from_le_bytes(bytes)
+}
+
+fn fourcc_name(code: i32) -> String {
+    code.to_le_bytes().iter().map(|b| *b as char).collect::<String>().trim_end_matches('\0').to_string()
+}
+
+/// What camera to open and the mode to ask the driver for. 0 and empty leave the driver default.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CameraSettings {
+    pub device: CameraDevice,
+    pub backend: CameraBackend,
+    pub width: u32,
+    pub height: u32,
+    pub fps: f64,
+    pub fourcc: String,
+}
+
+impl CameraSettings {
+    pub(crate) fn from_config(config: &Config) -> Self {
+        Self {
+            device: config.camera_device.clone(),
+            backend: config.camera_backend,
+            width: config.camera_width,
+            height: config.camera_height,
+            fps: config.camera_fps,
+            fourcc: config.camera_fourcc.clone(),
+        }
+    }
+}
 
 pub(crate) struct Camera {
     pub video_capture: VideoCapture,
+    /// Frame rate the driver delivers. 0 if unknown.
+    pub fps: f64,
 }
 
 impl Camera {
-    pub fn new() -> Result<Self> {
-        let video_capture = VideoCapture::new(0, CAP_ANY)?;
-        Ok(Self { video_capture })
+    pub fn new(settings: &CameraSettings) -> Result<Self> {
+        let api = settings.backend.api_preference();
+        let mut video_capture = match &settings.device {
+            CameraDevice::Index(index) => VideoCapture::new(*index, api)?,
+            CameraDevice::Path(path) => VideoCapture::from_file(path, api)?,
+        };
+        if !video_capture.is_opened()? {
+            return Err(anyhow!("failed to open camera {} ({})", settings.device, settings.backend));
+        }
+        // v4l2 picks the frame sizes of the pixel format, so the format is set first.
+        if !settings.fourcc.is_empty() {
+            video_capture.set(CAP_PROP_FOURCC, fourcc_code(&settings.fourcc) as f64)?;
+        }
+        if settings.width > 0 {
+            video_capture.set(CAP_PROP_FRAME_WIDTH, settings.width as f64)?;
+        }
+        if settings.height > 0 {
+            video_capture.set(CAP_PROP_FRAME_HEIGHT, settings.height as f64)?;
+        }
+        if settings.fps > 0. {
+            video_capture.set(CAP_PROP_FPS, settings.fps)?;
+        }
+        // drivers may not report the frame rate. trust the request then.
+        let fps = match video_capture.get(CAP_PROP_FPS)? {
+            native if native > 0. => native,
+            _ => settings.fps.max(0.),
+        };
+        Ok(Self { video_capture, fps })
+    }
+
+    /// Mode the driver settled on, which may differ from the request.
+    pub fn info(&self) -> String {
+        let width = self.video_capture.get(CAP_PROP_FRAME_WIDTH).unwrap_or(0.);
+        let height = self.video_capture.get(CAP_PROP_FRAME_HEIGHT).unwrap_or(0.);
+        let fourcc = self.video_capture.get(CAP_PROP_FOURCC).unwrap_or(0.);
+        format!("{}x{} {} {}fps", width, height, fourcc_name(fourcc as i32), self.fps)
+    }
+
+    /// Time between two frames at the driver frame rate.
+    pub fn frame_interval(&self) -> Option<Duration> {
+        if self.fps > 0. {
+            Some(Duration::from_secs_f64(1. / self.fps))
+        } else {
+            None
+        }
     }
 
     pub fn get_frame(&mut self) -> Result<Mat> {
@@ -32,3 +225,221 @@ impl Camera {
         }
     }
 }
+
+/// A video4linux capture node.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CameraDeviceInfo {
+    /// opencv index, N of /dev/videoN.
+    pub index: i32,
+    pub path: String,
+    pub name: String,
+    /// /dev/v4l/by-id links, which keep pointing to the same camera across reboots and replugs.
+    pub stable_paths: Vec<String>,
+}
+
+/// Cameras found on this machine.
+#[cfg(target_os = "linux")]
+pub(crate) fn list_cameras() -> Result<Vec<CameraDeviceInfo>> {
+    list_v4l2_devices(std::path::Path::new("/sys/class/video4linux"), std::path::Path::new("/dev/v4l/by-id"))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn list_cameras() -> Result<Vec<CameraDeviceInfo>> {
+    Err(anyhow!("listing cameras is only supported on linux. try --camera-device 0, 1, ..."))
+}
+
+/// Capture nodes in `sys_dir` (/sys/class/video4linux) with their links in `by_id_dir`.
+/// The metadata nodes of uvc cameras are skipped.
+#[cfg(target_os = "linux")]
+pub(crate) fn list_v4l2_devices(sys_dir: &std::path::Path, by_id_dir: &std::path::Path) -> Result<Vec<CameraDeviceInfo>> {
+    let entries = match fs::read_dir(sys_dir) {
+        Ok(entries) => entries,
+        // no v4l2 driver loaded
+        Err(_) => return Ok(vec![]),
+    };
+    let links = fs::read_dir(by_id_dir)
+        .map(|links| links.filter_map(|link| link.ok()).map(|link| link.path()).collect::<Vec<PathBuf>>())
+        .unwrap_or_default();
+    let mut devices = vec![];
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let node = entry.file_name().to_string_lossy().to_string();
+        let index = match node.strip_prefix("video").and_then(|n| n.parse::<i32>().ok()) {
+            Some(index) => index,
+            None => continue,
+        };
+        let read = |file: &str| fs::read_to_string(entry.path().join(file)).map(|s| s.trim().to_string());
+        if matches!(read("index"), Ok(i) if i != "0") {
+            continue;
+        }
+        let mut stable_paths = links.iter()
+            .filter(|link| fs::read_link(link).ok()
+                .and_then(|target| target.file_name().map(|name| name.to_string_lossy() == node))
+                .unwrap_or(false))
+            .map(|link| link.to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        stable_paths.sort();
+        devices.push(CameraDeviceInfo {
+            index,
+            path: format!("/dev/{}", node),
+            name: read("name").unwrap_or_default(),
+            stable_paths,
+        });
+    }
+    devices.sort_by_key(|device| device.index);
+    Ok(devices)
+}
+
+/// Error of a recording, video or audio file, that played to the end without looping. Polling it again gives the same error at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EndOfStream;
+
+impl Display for EndOfStream {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "end of stream")
+    }
+}
+
+impl std::error::Error for EndOfStream {}
+
+pub(crate) fn is_end_of_stream(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<EndOfStream>().is_some()
+}
+
+/// Paces recorded frames so that they are delivered at the recording frame rate.
+pub(crate) struct FramePacer {
+    interval: Duration,
+    next_frame_at: Option<Instant>,
+}
+
+impl FramePacer {
+    pub fn new(fps: f64) -> Self {
+        Self {
+            interval: if fps > 0. { Duration::from_secs_f64(1. / fps) } else { Duration::ZERO },
+            next_frame_at: None,
+        }
+    }
+
+    pub fn wait(&mut self) {
+        let now = Instant::now();
+        match self.next_frame_at {
+            None => {
+                self.next_frame_at = Some(now + self.interval);
+            }
+            Some(next) => {
+                if next > now {
+                    thread::sleep(next - now);
+                }
+                // do not try to catch up when the consumer was slower than the recording.
+                self.next_frame_at = Some(next.max(now) + self.interval);
+            }
+        }
+    }
+}
+
+/// Video file (mp4, avi, ...) read through opencv videoio.
+pub(crate) struct VideoFile {
+    pub video_capture: VideoCapture,
+    pub looping: bool,
+    finished: bool,
+    pacer: FramePacer,
+}
+
+impl VideoFile {
+    /// If fps is 0, the frame rate of the video is used.
+    pub fn new(path: &str, fps: f64, looping: bool) -> Result<Self> {
+        let video_capture = VideoCapture::from_file(path, CAP_ANY)?;
+        if !video_capture.is_opened()? {
+            return Err(anyhow!("failed to open video file: {}", path));
+        }
+        let fps = if fps > 0. {
+            fps
+        } else {
+            match video_capture.get(CAP_PROP_FPS)? {
+                native if native > 0. => native,
+                _ => DEFAULT_RECORDING_FPS,
+            }
+        };
+        Ok(Self {
+            video_capture,
+            looping,
+            finished: false,
+            pacer: FramePacer::new(fps),
+        })
+    }
+
+    pub fn get_frame(&mut self) -> Result<Mat> {
+        if self.finished {
+            return Err(EndOfStream.into())
+        }
+        self.pacer.wait();
+        let mut frame = Mat::default();
+        if self.video_capture.read(&mut frame)? && !frame.empty() {
+            return Ok(frame)
+        }
+        if self.looping {
+            self.video_capture.set(CAP_PROP_POS_FRAMES, 0.)?;
+            if self.video_capture.read(&mut frame)? && !frame.empty() {
+                return Ok(frame)
+            }
+        }
+        self.finished = true;
+        Err(EndOfStream.into())
+    }
+
+    /// Position of the last frame read in the video, in seconds.
+    pub fn position_secs(&self) -> Option<f64> {
+        match self.video_capture.get(CAP_PROP_POS_MSEC) {
+            Ok(msec) if msec >= 0. => Some(msec / 1000.),
+            _ => None,
+        }
+    }
+}
+
+/// Directory of still images, played in file name order.
+pub(crate) struct ImageSequence {
+    pub paths: Vec<PathBuf>,
+    pub looping: bool,
+    position: usize,
+    pacer: FramePacer,
+}
+
+impl ImageSequence {
+    /// If fps is 0, `DEFAULT_RECORDING_FPS` is used.
+    pub fn new(directory: &str, fps: f64, looping: bool) -> Result<Self> {
+        let mut paths = fs::read_dir(directory)
+            .map_err(|e| anyhow!("failed to read image directory {}: {}", directory, e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|p| p.extension()
+                .and_then(|ext| ext.to_str())
+                .map_or(false, |ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str())))
+            .collect::<Vec<PathBuf>>();
+        if paths.is_empty() {
+            return Err(anyhow!("no image found in {}", directory));
+        }
+        paths.sort();
+        Ok(Self {
+            paths,
+            looping,
+            position: 0,
+            pacer: FramePacer::new(if fps > 0. { fps } else { DEFAULT_RECORDING_FPS }),
+        })
+    }
+
+    pub fn get_frame(&mut self) -> Result<Mat> {
+        if self.position >= self.paths.len() {
+            if !self.looping {
+                return Err(EndOfStream.into())
+            }
+            self.position = 0;
+        }
+        self.pacer.wait();
+        let path = &self.paths[self.position];
+        self.position += 1;
+        let frame = imgcodecs::imread(path.to_str().unwrap(), imgcodecs::IMREAD_COLOR)?;
+        if frame.empty() {
+            return Err(anyhow!("failed to read image: {}", path.display()))
+        }
+        Ok(frame)
+    }
+}
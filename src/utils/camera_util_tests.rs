@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod camera_util_tests {
+    use std::str::FromStr;
+    use opencv::core::{Mat, Scalar, Vector, CV_8UC3};
+    use opencv::imgcodecs;
+    use opencv::prelude::*;
+    use super::super::camera_util::*;
+
+    #[test]
+    fn camera_device_parse_test() {
+        assert_eq!(CameraDevice::from_str("2").unwrap(), CameraDevice::Index(2));
+        assert_eq!(CameraDevice::from_str("/dev/video2").unwrap(), CameraDevice::Path("/dev/video2".to_string()));
+        assert!(CameraDevice::from_str("-1").is_err());
+        assert!(CameraDevice::from_str(" ").is_err());
+        assert_eq!(CameraBackend::from_str("V4L2").unwrap(), CameraBackend::V4l2);
+        assert_eq!(CameraBackend::Gstreamer.to_string(), "gstreamer");
+    }
+
+    #[test]
+    fn fourcc_test() {
+        assert_eq!(parse_fourcc("YUYV").unwrap(), "YUYV");
+        assert_eq!(parse_fourcc("MJPEG").unwrap(), "MJPG");
+        assert!(parse_fourcc("MJPEGS").is_err());
+        // CV_FOURCC('M', 'J', 'P', 'G')
+        assert_eq!(fourcc_code("MJPG"), 0x47504a4d);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn list_v4l2_devices_test() {
+        use std::fs;
+        use std::os::unix::fs::symlink;
+        let dir = std::env::temp_dir().join("vas_camera_util");
+        let _ = fs::remove_dir_all(&dir);
+        let sys_dir = dir.join("video4linux");
+        let by_id_dir = dir.join("by-id");
+        fs::create_dir_all(&by_id_dir).unwrap();
+        for (node, name, index) in [("video10", "Overhead", "0"), ("video2", "Laptop", "0"), ("video3", "Laptop", "1")] {
+            fs::create_dir_all(sys_dir.join(node)).unwrap();
+            fs::write(sys_dir.join(node).join("name"), format!("{}\n", name)).unwrap();
+            fs::write(sys_dir.join(node).join("index"), index).unwrap();
+        }
+        symlink("../../video10", by_id_dir.join("usb-Overhead-video-index0")).unwrap();
+        symlink("../../video3", by_id_dir.join("usb-Laptop-video-index1")).unwrap();
+        let devices = list_v4l2_devices(&sys_dir, &by_id_dir).unwrap();
+        // the metadata node video3 is skipped
+        assert_eq!(devices.len(), 2);
+        assert_eq!((devices[0].index, devices[0].path.as_str(), devices[0].name.as_str()), (2, "/dev/video2", "Laptop"));
+        assert!(devices[0].stable_paths.is_empty());
+        assert_eq!(devices[1].index, 10);
+        assert_eq!(devices[1].stable_paths, vec![by_id_dir.join("usb-Overhead-video-index0").to_string_lossy().to_string()]);
+        assert!(list_v4l2_devices(&dir.join("missing"), &by_id_dir).unwrap().is_empty());
+    }
+
+    #[test]
+    fn image_sequence_end_of_stream_test() {
+        let dir = std::env::temp_dir().join("vas_camera_util_sequence");
+        std::fs::create_dir_all(&dir).unwrap();
+        let frame = Mat::new_rows_cols_with_default(4, 4, CV_8UC3, Scalar::all(0.)).unwrap();
+        for name in ["0.png", "1.png"] {
+            imgcodecs::imwrite(dir.join(name).to_str().unwrap(), &frame, &Vector::new()).unwrap();
+        }
+        let mut images = ImageSequence::new(dir.to_str().unwrap(), 1000., false).unwrap();
+        assert!(images.get_frame().is_ok());
+        assert!(images.get_frame().is_ok());
+        let end = images.get_frame().unwrap_err();
+        assert!(is_end_of_stream(&end));
+        assert!(is_end_of_stream(&images.get_frame().unwrap_err()));
+        assert!(!is_end_of_stream(&anyhow::anyhow!("failed to read image")));
+        let mut looping = ImageSequence::new(dir.to_str().unwrap(), 1000., true).unwrap();
+        for _ in 0..3 {
+            assert!(looping.get_frame().is_ok());
+        }
+    }
+}
@@ -1,85 +1,225 @@
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 use anyhow::{Result, anyhow};
+use crate::smart_speaker::models::gaze_model::{FixationMethod, GazeSmoothing};
+use crate::smart_speaker::models::marker_model::{ArucoDictionary, DEFAULT_MARKER_MM};
+use crate::smart_speaker::models::mic_model::{SpeechEngineType, VadEngineType};
+use crate::smart_speaker::models::session_model::SessionMediaMode;
+use crate::smart_speaker::models::speak_model::SpeechOutputType;
+use crate::utils::calibration_util::{parse_board_size, CalibrationBoard};
+use crate::utils::camera_util::{parse_fourcc, CameraBackend, CameraDevice};
 use crate::utils::vision_util::VisionType;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Command {
     Run,
+    FakePupil,
+    ListMics,
+    ListCameras,
+    ListVoices,
+    ListRecipes,
+    /// Recipe file or directory. `--recipe-path` if none.
+    ValidateRecipe(Option<String>),
+    /// Session file of `--record-session`, or a dialogue transcript.
+    Replay(String),
+    /// Amount of markers and the output directory.
+    GenerateMarkers(u32, String),
+    /// Views of the board to collect.
+    CalibrateCamera(u32),
+    Doctor,
     Help,
 }
 
+/// Options that take a value. In a config file the key is the option name without `--`.
+const CONFIG_OPTIONS: [&str; 48] = [
+    "pv-api-key",
+    "pv-ppn-model-path",
+    "pv-rhn-model-path",
+    "pv-porcupine-params-path",
+    "pv-rhino-params-path",
+    "speech-engine",
+    "vad-engine",
+    "vad-threshold",
+    "vad-energy-margin-db",
+    "vad-hangover-ms",
+    "vad-min-speech-ms",
+    "mic-index",
+    "audio-file",
+    "speech-output",
+    "speech-output-path",
+    "camera-index",
+    "camera-device",
+    "camera-backend",
+    "camera-width",
+    "camera-height",
+    "camera-fps",
+    "camera-fourcc",
+    "calibration-dir",
+    "calibration-board",
+    "calibration-board-size",
+    "calibration-square-mm",
+    "calibration-marker-mm",
+    "aruco-dictionary",
+    "aruco-marker-mm",
+    "aruco-board",
+    "vision-type",
+    "recording-path",
+    "recording-fps",
+    "gaze-recording-path",
+    "gaze-max-skew-ms",
+    "gaze-min-confidence",
+    "gaze-smoothing",
+    "gaze-fixation",
+    "gaze-fixation-dispersion",
+    "gaze-fixation-velocity",
+    "gaze-fixation-min-ms",
+    "zmq-in-endpoint",
+    "stream-out-endpoint",
+    "recipe-path",
+    "record-session",
+    "record-media",
+    "language",
+    "config",
+];
+
+/// Options without a value. In a config file or an environment variable they take true / false.
+const CONFIG_FLAGS: [&str; 6] = [
+    "recording-loop",
+    "debug",
+    "vision",
+    "text-input",
+    "audio-file-fast",
+    "speech-output-fast",
+];
+
+/// Prefix of the environment variables. `--vad-engine` is `VAS_VAD_ENGINE`.
+const ENV_PREFIX: &str = "VAS_";
+
 pub(crate) struct Cli {
     args: Vec<String>,
+    env: HashMap<String, String>,
 }
 
 impl Cli {
     pub fn new(args: Vec<String>) -> Self {
         Self {
-            args
+            args,
+            env: std::env::vars().filter(|(key, _)| key.starts_with(ENV_PREFIX)).collect(),
         }
     }
 
+    /// Replace the environment variables, for tests.
+    pub fn with_env(mut self, env: HashMap<String, String>) -> Self {
+        self.env = env;
+        self
+    }
+
     pub fn parse_command(&self) -> Result<Command> {
-        match self.args.get(0).expect("no query given")[..].as_ref() {
+        match self.args.get(0).ok_or(anyhow!("no command given. type help for available commands."))?.as_str() {
             "run" => Ok(Command::Run),
+            "fake-pupil" => Ok(Command::FakePupil),
+            "list-mics" => Ok(Command::ListMics),
+            "list-cameras" => Ok(Command::ListCameras),
+            "list-voices" => Ok(Command::ListVoices),
+            "list-recipes" => Ok(Command::ListRecipes),
+            "validate-recipe" => Ok(Command::ValidateRecipe(self.positional(1))),
+            "replay" => Ok(Command::Replay(
+                self.positional(1).ok_or(anyhow!("no session file given. replay <session.jsonl>"))?)),
+            "generate-markers" => Ok(Command::GenerateMarkers(
+                match self.positional(1) {
+                    Some(amount) => parse_value::<u32>(&amount).map_err(|e| anyhow!("marker amount: {}", e))?,
+                    None => 10,
+                },
+                self.positional(1).and(self.positional(2)).unwrap_or("markers".to_string()))),
+            "calibrate-camera" => Ok(Command::CalibrateCamera(
+                match self.positional(1) {
+                    Some(views) => parse_value::<u32>(&views).map_err(|e| anyhow!("view amount: {}", e))?,
+                    None => 20,
+                })),
+            "doctor" => Ok(Command::Doctor),
             "help" => Ok(Command::Help),
             _ => Err(anyhow!("no matched command found. type help for available commands."))
         }
     }
 
+    /// Argument of the command that is not an option, e.g. the file of `replay <file>`.
+    fn positional(&self, index: usize) -> Option<String> {
+        self.args.get(index).filter(|arg| !arg.starts_with("--")).cloned()
+    }
+
+    /// Defaults, then the config file (`--config` or `VAS_CONFIG`), then `VAS_*` environment variables, then command line options.
     pub fn parse_config(&self) -> Result<Config> {
         let mut config = Config::new();
-        for (i, arg) in self.args.iter().enumerate() {
-            match arg.as_str() {
-                "--pv-api-key" => {
-                    config.pico_voice_api_key = self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone();
-                }
-                "--pv-ppn-model-path" => {
-                    config.pico_voice_ppn_model_path = to_absolute_path(
-                        &self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone()
-                    ).unwrap();
-                }
-                "--pv-rhn-model-path" => {
-                    config.pico_voice_rhn_model_path = to_absolute_path(
-                        &self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone()
-                    ).unwrap();
-                }
-                "--mic-index" => {
-                    config.mic_index = self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone().parse::<u32>()?;
-                }
-                "--vision-type" => {
-                    config.vision_type = self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone().parse::<VisionType>()?;
-                }
-                "--debug" => {
-                    config.debug = true;
-                }
-                "--vision" => {
-                    config.vision = true;
-                }
-                "--zmq-in-endpoint" => {
-                    config.zmq_in_endpoint = self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone();
-                }
-                "--stream-out-endpoint" => {
-                    config.stream_out_endpoint = self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone();
-                }
-                "--language" => {
-                    config.language = LanguageTag::from_str(
-                        &self.args.get(i + 1).ok_or(anyhow!("no argument found for option")).unwrap().clone()
-                    );
-                }
-                _ => {}
+        let cli_options = self.parse_options()?;
+        let config_path = cli_options.iter()
+            .find(|(key, _)| key == "config")
+            .map(|(_, value)| value.clone())
+            .or(self.env.get(&env_key("config")).cloned());
+        if let Some(path) = config_path {
+            config.load_file(&path)?;
+        }
+        let current_dir = std::env::current_dir()?;
+        for key in CONFIG_OPTIONS.iter().chain(CONFIG_FLAGS.iter()) {
+            if let Some(value) = self.env.get(&env_key(key)) {
+                config.set(key, value, &current_dir)
+                    .map_err(|e| anyhow!("{}: {}", env_key(key), e))?;
             }
         }
+        for (key, value) in cli_options {
+            config.set(&key, &value, &current_dir)
+                .map_err(|e| anyhow!("--{}: {}", key, e))?;
+        }
         Ok(config)
     }
+
+    /// `--option value` pairs of the command line. Flags get "true". An unknown option is an error.
+    fn parse_options(&self) -> Result<Vec<(String, String)>> {
+        let mut options = vec![];
+        let mut i = 0;
+        while i < self.args.len() {
+            if let Some(key) = self.args[i].strip_prefix("--") {
+                if CONFIG_FLAGS.contains(&key) {
+                    options.push((key.to_string(), "true".to_string()));
+                } else if CONFIG_OPTIONS.contains(&key) {
+                    let value = self.args.get(i + 1)
+                        .filter(|value| !value.starts_with("--"))
+                        .ok_or(anyhow!("no argument found for option --{}", key))?;
+                    options.push((key.to_string(), value.clone()));
+                    i += 1;
+                } else {
+                    return Err(anyhow!("unknown option --{}. type help for available options.", key));
+                }
+            }
+            i += 1;
+        }
+        Ok(options)
+    }
+}
+
+fn env_key(key: &str) -> String {
+    format!("{}{}", ENV_PREFIX, key.to_uppercase().replace("-", "_"))
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(anyhow!("expected true or false, got {}", value)),
+    }
 }
 
-pub(crate) fn to_absolute_path(path: &str) -> Result<String> {
+fn parse_value<T: FromStr>(value: &str) -> Result<T> where T::Err: std::fmt::Display {
+    value.parse::<T>().map_err(|e| anyhow!("invalid value {:?}: {}", value, e))
+}
+
+/// Relative paths are resolved from the config file directory, or the current directory for the command line.
+fn resolve_path(path: &str, base_dir: &Path) -> Result<String> {
     let mut absolute_path = PathBuf::from(path);
     if !absolute_path.is_absolute() {
-        absolute_path = std::env::current_dir()?.join(path);
+        absolute_path = base_dir.join(path);
     }
-    Ok(absolute_path.to_str().unwrap().to_string())
+    absolute_path.to_str().map(|path| path.to_string()).ok_or(anyhow!("invalid path {:?}", path))
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -87,13 +227,59 @@ pub(crate) struct Config {
     pub pico_voice_api_key: String,
     pub pico_voice_ppn_model_path: String,
     pub pico_voice_rhn_model_path: String,
+    /// Porcupine / Rhino parameter files. Empty for the bundled file of the language.
+    pub pico_voice_porcupine_params_path: String,
+    pub pico_voice_rhino_params_path: String,
+    pub speech_engine: SpeechEngineType,
+    pub vad_engine: VadEngineType,
+    pub vad_threshold: f32,
+    pub vad_energy_margin_db: f32,
+    pub vad_hangover_ms: u32,
+    pub vad_min_speech_ms: u32,
     pub mic_index: u32,
+    pub audio_file_path: String,
+    pub audio_file_realtime: bool,
+    pub text_input: bool,
+    pub speech_output: SpeechOutputType,
+    pub speech_output_path: String,
+    pub speech_output_realtime: bool,
+    pub camera_device: CameraDevice,
+    pub camera_backend: CameraBackend,
+    /// Requested capture mode. 0 and empty leave the driver default.
+    pub camera_width: u32,
+    pub camera_height: u32,
+    pub camera_fps: f64,
+    pub camera_fourcc: String,
+    /// Calibration files of the cameras, one per device.
+    pub calibration_dir: String,
+    pub calibration_board: CalibrationBoard,
+    pub calibration_board_size: (u32, u32),
+    pub calibration_square_mm: f32,
+    pub calibration_marker_mm: f32,
+    pub aruco_dictionary: ArucoDictionary,
+    pub aruco_marker_mm: f32,
+    /// Board file with the size and position of each marker. Empty if every marker is `aruco_marker_mm`.
+    pub aruco_board_path: String,
     pub vision_type: VisionType,
+    pub recording_path: String,
+    pub recording_fps: f64,
+    pub recording_loop: bool,
+    pub gaze_recording_path: String,
+    pub gaze_max_skew_ms: u32,
+    pub gaze_min_confidence: f32,
+    pub gaze_smoothing: GazeSmoothing,
+    pub gaze_fixation: FixationMethod,
+    pub gaze_fixation_dispersion: f32,
+    pub gaze_fixation_velocity: f32,
+    pub gaze_fixation_min_ms: u32,
     pub vision: bool,
     pub debug: bool,
     pub zmq_in_endpoint: String,
     pub stream_out_endpoint: String,
     pub language: LanguageTag,
+    pub recipe_path: String,
+    pub session_record_path: String,
+    pub session_record_media: SessionMediaMode,
 }
 
 impl Config {
@@ -102,17 +288,174 @@ impl Config {
             pico_voice_api_key: "".to_string(),
             pico_voice_ppn_model_path: "model.ppn".to_string(),
             pico_voice_rhn_model_path: "model.rhn".to_string(),
+            pico_voice_porcupine_params_path: "".to_string(),
+            pico_voice_rhino_params_path: "".to_string(),
+            speech_engine: SpeechEngineType::Picovoice,
+            vad_engine: VadEngineType::Picovoice,
+            vad_threshold: 0.5,
+            vad_energy_margin_db: 12.,
+            vad_hangover_ms: 300,
+            vad_min_speech_ms: 100,
             mic_index: 0,
+            audio_file_path: "".to_string(),
+            audio_file_realtime: true,
+            text_input: false,
+            speech_output: SpeechOutputType::Tts,
+            speech_output_path: "".to_string(),
+            speech_output_realtime: true,
+            camera_device: CameraDevice::Index(0),
+            camera_backend: CameraBackend::Any,
+            camera_width: 0,
+            camera_height: 0,
+            camera_fps: 0.,
+            camera_fourcc: "".to_string(),
+            calibration_dir: "calibration".to_string(),
+            calibration_board: CalibrationBoard::Chessboard,
+            calibration_board_size: (9, 6),
+            calibration_square_mm: 25.,
+            calibration_marker_mm: 18.,
+            aruco_dictionary: ArucoDictionary::default(),
+            aruco_marker_mm: DEFAULT_MARKER_MM,
+            aruco_board_path: "".to_string(),
             vision_type: VisionType::None,
+            recording_path: "".to_string(),
+            recording_fps: 0.,
+            recording_loop: false,
+            gaze_recording_path: "".to_string(),
+            gaze_max_skew_ms: 250,
+            gaze_min_confidence: 0.8,
+            gaze_smoothing: GazeSmoothing::None,
+            gaze_fixation: FixationMethod::Dispersion,
+            gaze_fixation_dispersion: 0.05,
+            gaze_fixation_velocity: 0.5,
+            gaze_fixation_min_ms: 100,
             vision: false,
             debug: false,
             zmq_in_endpoint: "".to_string(),
             stream_out_endpoint: "".to_string(),
             language: LanguageTag::Japanese,
+            recipe_path: "resources/recipes".to_string(),
+            session_record_path: "".to_string(),
+            session_record_media: SessionMediaMode::Elide,
+        }
+    }
+
+    /// Set an option by its name without `--`. Relative paths are resolved from `base_dir`.
+    pub fn set(&mut self, key: &str, value: &str, base_dir: &Path) -> Result<()> {
+        match key {
+            "pv-api-key" => self.pico_voice_api_key = value.to_string(),
+            "pv-ppn-model-path" => self.pico_voice_ppn_model_path = resolve_path(value, base_dir)?,
+            "pv-rhn-model-path" => self.pico_voice_rhn_model_path = resolve_path(value, base_dir)?,
+            "pv-porcupine-params-path" => self.pico_voice_porcupine_params_path = resolve_path(value, base_dir)?,
+            "pv-rhino-params-path" => self.pico_voice_rhino_params_path = resolve_path(value, base_dir)?,
+            "speech-engine" => self.speech_engine = parse_value::<SpeechEngineType>(value)?,
+            "vad-engine" => self.vad_engine = parse_value::<VadEngineType>(value)?,
+            "vad-threshold" => self.vad_threshold = parse_value::<f32>(value)?,
+            "vad-energy-margin-db" => self.vad_energy_margin_db = parse_value::<f32>(value)?,
+            "vad-hangover-ms" => self.vad_hangover_ms = parse_value::<u32>(value)?,
+            "vad-min-speech-ms" => self.vad_min_speech_ms = parse_value::<u32>(value)?,
+            "mic-index" => self.mic_index = parse_value::<u32>(value)?,
+            "audio-file" => self.audio_file_path = resolve_path(value, base_dir)?,
+            "audio-file-fast" => self.audio_file_realtime = !parse_bool(value)?,
+            "text-input" => self.text_input = parse_bool(value)?,
+            "speech-output" => self.speech_output = parse_value::<SpeechOutputType>(value)?,
+            "speech-output-path" => self.speech_output_path = resolve_path(value, base_dir)?,
+            "speech-output-fast" => self.speech_output_realtime = !parse_bool(value)?,
+            "camera-index" => self.camera_device = CameraDevice::Index(parse_value::<u32>(value)? as i32),
+            "camera-device" => self.camera_device = parse_value::<CameraDevice>(value)?,
+            "camera-backend" => self.camera_backend = parse_value::<CameraBackend>(value)?,
+            "camera-width" => self.camera_width = parse_value::<u32>(value)?,
+            "camera-height" => self.camera_height = parse_value::<u32>(value)?,
+            "camera-fps" => self.camera_fps = parse_value::<f64>(value)?,
+            "camera-fourcc" => self.camera_fourcc = parse_fourcc(value)?,
+            "calibration-dir" => self.calibration_dir = resolve_path(value, base_dir)?,
+            "calibration-board" => self.calibration_board = parse_value::<CalibrationBoard>(value)?,
+            "calibration-board-size" => self.calibration_board_size = parse_board_size(value)?,
+            "calibration-square-mm" => self.calibration_square_mm = parse_value::<f32>(value)?,
+            "calibration-marker-mm" => self.calibration_marker_mm = parse_value::<f32>(value)?,
+            "aruco-dictionary" => self.aruco_dictionary = parse_value::<ArucoDictionary>(value)?,
+            "aruco-marker-mm" => self.aruco_marker_mm = parse_value::<f32>(value)?,
+            "aruco-board" => self.aruco_board_path = resolve_path(value, base_dir)?,
+            "vision-type" => self.vision_type = parse_value::<VisionType>(value)?,
+            "recording-path" => self.recording_path = resolve_path(value, base_dir)?,
+            "recording-fps" => self.recording_fps = parse_value::<f64>(value)?,
+            "recording-loop" => self.recording_loop = parse_bool(value)?,
+            "gaze-recording-path" => self.gaze_recording_path = resolve_path(value, base_dir)?,
+            "gaze-max-skew-ms" => self.gaze_max_skew_ms = parse_value::<u32>(value)?,
+            "gaze-min-confidence" => self.gaze_min_confidence = parse_value::<f32>(value)?,
+            "gaze-smoothing" => self.gaze_smoothing = parse_value::<GazeSmoothing>(value)?,
+            "gaze-fixation" => self.gaze_fixation = parse_value::<FixationMethod>(value)?,
+            "gaze-fixation-dispersion" => self.gaze_fixation_dispersion = parse_value::<f32>(value)?,
+            "gaze-fixation-velocity" => self.gaze_fixation_velocity = parse_value::<f32>(value)?,
+            "gaze-fixation-min-ms" => self.gaze_fixation_min_ms = parse_value::<u32>(value)?,
+            "vision" => self.vision = parse_bool(value)?,
+            "debug" => self.debug = parse_bool(value)?,
+            "zmq-in-endpoint" => self.zmq_in_endpoint = value.to_string(),
+            "stream-out-endpoint" => self.stream_out_endpoint = value.to_string(),
+            "recipe-path" => self.recipe_path = resolve_path(value, base_dir)?,
+            "record-session" => self.session_record_path = resolve_path(value, base_dir)?,
+            "record-media" => self.session_record_media = parse_value::<SessionMediaMode>(value)?,
+            "language" => self.language = LanguageTag::parse(value)?,
+            // the file is read before any other option
+            "config" => {}
+            _ => return Err(anyhow!("unknown option")),
         }
+        Ok(())
+    }
+
+    /// Apply a flat TOML (`.toml`) or JSON (`.json`) file. Keys are option names, `vad-engine` or `vad_engine`.
+    pub fn load_file(&mut self, path: &str) -> Result<()> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read config file {}: {}", path, e))?;
+        let values = if path.ends_with(".json") {
+            parse_json_config(&text)
+        } else {
+            parse_toml_config(&text)
+        }.map_err(|e| anyhow!("{}: {}", path, e))?;
+        let base_dir = std::fs::canonicalize(path)?.parent().map(|dir| dir.to_path_buf()).unwrap_or(std::env::current_dir()?);
+        for (key, value) in values {
+            let key = key.replace("_", "-");
+            if key == "config" {
+                return Err(anyhow!("{}: config files can not include another config file", path));
+            }
+            self.set(&key, &value, &base_dir)
+                .map_err(|e| anyhow!("{}: {}: {}", path, key, e))?;
+        }
+        Ok(())
     }
 }
 
+fn parse_toml_config(text: &str) -> Result<Vec<(String, String)>> {
+    let table = text.parse::<toml::Table>().map_err(|e| anyhow!("invalid toml: {}", e))?;
+    table.into_iter().map(|(key, value)| {
+        let value = match value {
+            toml::Value::String(s) => s,
+            toml::Value::Integer(i) => i.to_string(),
+            toml::Value::Float(f) => f.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            _ => return Err(anyhow!("{}: must be a string, number or boolean", key)),
+        };
+        Ok((key, value))
+    }).collect()
+}
+
+fn parse_json_config(text: &str) -> Result<Vec<(String, String)>> {
+    let value = serde_json::from_str::<serde_json::Value>(text).map_err(|e| anyhow!("invalid json: {}", e))?;
+    let object = match value {
+        serde_json::Value::Object(object) => object,
+        _ => return Err(anyhow!("config must be a json object")),
+    };
+    object.into_iter().map(|(key, value)| {
+        let value = match value {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Number(n) => n.to_string(),
+            serde_json::Value::Bool(b) => b.to_string(),
+            _ => return Err(anyhow!("{}: must be a string, number or boolean", key)),
+        };
+        Ok((key, value))
+    }).collect()
+}
+
 // BCP 47
 #[derive(Debug, Clone, PartialEq)]
 pub(crate) enum LanguageTag {
@@ -132,6 +475,14 @@ impl LanguageTag {
         }
     }
 
+    /// Same as `from_str`, but unknown tags are an error.
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "en" | "en-US" | "ja" | "ja-JP" | "zh" | "zh-CN" | "ko" | "ko-KR" => Ok(Self::from_str(s)),
+            _ => Err(anyhow!("invalid language {:?}. en / ja / zh / ko", s)),
+        }
+    }
+
     pub fn from_str(s: &str) -> Self {
         match s {
             "en" | "en-US" => LanguageTag::English,
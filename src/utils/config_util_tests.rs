@@ -1,10 +1,17 @@
 #[cfg(test)]
 mod config_util_tests {
+    use std::collections::HashMap;
+    use crate::smart_speaker::models::gaze_model::{FixationMethod, GazeSmoothing};
+    use crate::smart_speaker::models::marker_model::ArucoDictionary;
+    use crate::smart_speaker::models::mic_model::VadEngineType;
+    use crate::smart_speaker::models::speak_model::SpeechOutputType;
+    use crate::utils::calibration_util::CalibrationBoard;
+    use crate::utils::camera_util::{CameraBackend, CameraDevice};
+    use crate::utils::vision_util::VisionType;
     use super::super::config_util::*;
-    const ARGS: [&str; 9] = [
+    const ARGS: [&str; 7] = [
         "run",
         "--pv-api-key", "",
-        "--pv-model-path", "model.rhn",
         "--mic-index", "0",
         "--vision-type", "none"];
     #[test]
@@ -18,4 +25,140 @@ mod config_util_tests {
         let cli = Cli::new(ARGS.iter().map(|s| s.to_string()).collect::<Vec<String>>());
         assert_eq!(cli.parse_config().unwrap(), Config::new());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn cli_parse_recording_config() {
+        let args = ["run", "--vision-type", "recording", "--recording-path", "/tmp/session.mp4", "--recording-fps", "60", "--recording-loop"];
+        let cli = Cli::new(args.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+        let config = cli.parse_config().unwrap();
+        assert_eq!(config.vision_type, VisionType::Recording);
+        assert_eq!(config.recording_path, "/tmp/session.mp4");
+        assert_eq!(config.recording_fps, 60.);
+        assert!(config.recording_loop);
+    }
+
+    #[test]
+    fn cli_parse_gaze_config() {
+        let config = cli(&["run", "--gaze-smoothing", "one-euro", "--gaze-fixation", "ivt", "--gaze-min-confidence", "0.6"], &[])
+            .parse_config().unwrap();
+        assert_eq!(config.gaze_smoothing, GazeSmoothing::OneEuro);
+        assert_eq!(config.gaze_fixation, FixationMethod::Velocity);
+        assert_eq!(config.gaze_min_confidence, 0.6);
+        assert!(cli(&["run", "--gaze-smoothing", "kalman"], &[]).parse_config().is_err());
+    }
+
+    #[test]
+    fn cli_parse_camera_config() {
+        let config = cli(&["run", "--camera-device", "/dev/v4l/by-id/usb-kitchen-video-index0", "--camera-backend", "v4l2",
+                           "--camera-width", "1280", "--camera-height", "720", "--camera-fps", "30", "--camera-fourcc", "mjpeg"], &[])
+            .parse_config().unwrap();
+        assert_eq!(config.camera_device, CameraDevice::Path("/dev/v4l/by-id/usb-kitchen-video-index0".to_string()));
+        assert_eq!(config.camera_backend, CameraBackend::V4l2);
+        assert_eq!((config.camera_width, config.camera_height), (1280, 720));
+        assert_eq!(config.camera_fps, 30.);
+        assert_eq!(config.camera_fourcc, "MJPG");
+        assert_eq!(cli(&["run", "--camera-index", "2"], &[]).parse_config().unwrap().camera_device, CameraDevice::Index(2));
+        assert!(cli(&["run", "--camera-fourcc", "H26"], &[]).parse_config().is_err());
+        assert!(cli(&["run", "--camera-backend", "vfw"], &[]).parse_config().is_err());
+        let config = cli(&["run", "--calibration-board", "charuco", "--calibration-board-size", "7x5", "--calibration-square-mm", "30"], &[])
+            .parse_config().unwrap();
+        assert_eq!(config.calibration_board, CalibrationBoard::Charuco);
+        assert_eq!(config.calibration_board_size, (7, 5));
+        assert_eq!(config.calibration_square_mm, 30.);
+        assert!(cli(&["run", "--calibration-board-size", "9"], &[]).parse_config().is_err());
+        let config = cli(&["run", "--aruco-dictionary", "DICT_5X5_100", "--aruco-marker-mm", "50"], &[]).parse_config().unwrap();
+        assert_eq!(config.aruco_dictionary, ArucoDictionary { bits: 5, size: 100 });
+        assert_eq!(config.aruco_marker_mm, 50.);
+        assert!(cli(&["run", "--aruco-dictionary", "3x3_50"], &[]).parse_config().is_err());
+    }
+
+    fn cli(args: &[&str], env: &[(&str, &str)]) -> Cli {
+        Cli::new(args.iter().map(|s| s.to_string()).collect::<Vec<String>>())
+            .with_env(env.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<HashMap<String, String>>())
+    }
+
+    fn write_config(name: &str, text: &str) -> String {
+        let dir = std::env::temp_dir().join("vas_config_util");
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        std::fs::write(&path, text).unwrap();
+        path.to_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn config_file_toml_test() {
+        let path = write_config("config.toml", r#"
+vad-engine = "energy"
+vad_hangover_ms = 500
+vad-threshold = 0.7
+text-input = true
+language = "en"
+recipe-path = "recipes"
+"#);
+        let config = cli(&["run", "--config", path.as_str()], &[]).parse_config().unwrap();
+        assert_eq!(config.vad_engine, VadEngineType::Energy);
+        assert_eq!(config.vad_hangover_ms, 500);
+        assert_eq!(config.vad_threshold, 0.7);
+        assert!(config.text_input);
+        assert_eq!(config.language, LanguageTag::English);
+        // relative to the config file
+        let dir = std::fs::canonicalize(std::env::temp_dir().join("vas_config_util")).unwrap();
+        assert_eq!(config.recipe_path, dir.join("recipes").to_str().unwrap());
+    }
+
+    #[test]
+    fn config_file_json_test() {
+        let path = write_config("config.json", r#"{"speech-output": "console", "speech-output-fast": true, "mic-index": 2}"#);
+        let config = cli(&["run"], &[("VAS_CONFIG", path.as_str())]).parse_config().unwrap();
+        assert_eq!(config.speech_output, SpeechOutputType::Console);
+        assert!(!config.speech_output_realtime);
+        assert_eq!(config.mic_index, 2);
+    }
+
+    #[test]
+    fn config_precedence_test() {
+        let path = write_config("precedence.toml", "mic-index = 1\nvad-hangover-ms = 100\nvad-min-speech-ms = 50\n");
+        let config = cli(&["run", "--config", path.as_str(), "--mic-index", "3"],
+                         &[("VAS_MIC_INDEX", "2"), ("VAS_VAD_HANGOVER_MS", "200")]).parse_config().unwrap();
+        assert_eq!(config.mic_index, 3);
+        assert_eq!(config.vad_hangover_ms, 200);
+        assert_eq!(config.vad_min_speech_ms, 50);
+    }
+
+    #[test]
+    fn config_invalid_value_test() {
+        assert!(cli(&["run", "--mic-index", "first"], &[]).parse_config().is_err());
+        assert!(cli(&["run", "--language", "fr"], &[]).parse_config().is_err());
+        assert!(cli(&["run", "--vad-engine"], &[]).parse_config().is_err());
+        assert!(cli(&["run", "--vad-engine", "--debug"], &[]).parse_config().is_err());
+        let error = cli(&["run", "--vad-engien", "energy"], &[]).parse_config().unwrap_err();
+        assert!(error.to_string().contains("--vad-engien"));
+        assert!(cli(&["run"], &[("VAS_DEBUG", "maybe")]).parse_config().is_err());
+        let path = write_config("unknown.toml", "vad-engien = \"energy\"\n");
+        let error = cli(&["run", "--config", path.as_str()], &[]).parse_config().unwrap_err();
+        assert!(error.to_string().contains("vad-engien"));
+        assert!(cli(&["run", "--config", "/nonexistent/vas.toml"], &[]).parse_config().is_err());
+    }
+
+    #[test]
+    fn cli_no_command_test() {
+        assert!(cli(&[], &[]).parse_command().is_err());
+    }
+
+    #[test]
+    fn cli_parse_subcommand_test() {
+        assert_eq!(cli(&["list-mics"], &[]).parse_command().unwrap(), Command::ListMics);
+        assert_eq!(cli(&["list-cameras"], &[]).parse_command().unwrap(), Command::ListCameras);
+        assert_eq!(cli(&["calibrate-camera"], &[]).parse_command().unwrap(), Command::CalibrateCamera(20));
+        assert_eq!(cli(&["calibrate-camera", "30", "--vision-type", "camera"], &[]).parse_command().unwrap(), Command::CalibrateCamera(30));
+        assert_eq!(cli(&["validate-recipe"], &[]).parse_command().unwrap(), Command::ValidateRecipe(None));
+        assert_eq!(cli(&["validate-recipe", "salad.json", "--language", "en"], &[]).parse_command().unwrap(),
+                   Command::ValidateRecipe(Some("salad.json".to_string())));
+        assert_eq!(cli(&["replay", "session.jsonl"], &[]).parse_command().unwrap(), Command::Replay("session.jsonl".to_string()));
+        assert!(cli(&["replay", "--vision"], &[]).parse_command().is_err());
+        assert_eq!(cli(&["generate-markers"], &[]).parse_command().unwrap(), Command::GenerateMarkers(10, "markers".to_string()));
+        assert_eq!(cli(&["generate-markers", "4", "/tmp/markers"], &[]).parse_command().unwrap(),
+                   Command::GenerateMarkers(4, "/tmp/markers".to_string()));
+        assert!(cli(&["generate-markers", "many"], &[]).parse_command().is_err());
+    }
+}
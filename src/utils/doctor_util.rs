@@ -0,0 +1,216 @@
+use std::fmt;
+use std::path::Path;
+use anyhow::{anyhow, Result};
+use opencv::prelude::*;
+use crate::smart_speaker::actors::logger_actor::LOG_DIR;
+use crate::smart_speaker::models::marker_model::MarkerBoard;
+use crate::smart_speaker::models::mic_model::{list_audio_devices, PorcupineWakeWordEngine, RhinoIntentEngine, SpeechEngineType, VadEngineType};
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+use crate::smart_speaker::models::speak_model::{list_voices, SpeechOutputType};
+use crate::utils::audio_util::{WavFile, SAMPLE_RATE};
+use crate::utils::camera_util::{Camera, CameraSettings};
+use crate::utils::config_util::{Config, LanguageTag};
+use crate::utils::pupil_util::request_sub_port;
+use crate::utils::vision_util::VisionType;
+
+/// How long to wait for Pupil Remote before the check fails.
+const PUPIL_REMOTE_TIMEOUT_MS: i32 = 2000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum DoctorStatus {
+    Pass,
+    Fail,
+    /// Not needed by the config.
+    Skip,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DoctorCheck {
+    pub name: String,
+    pub status: DoctorStatus,
+    pub detail: String,
+}
+
+impl DoctorCheck {
+    pub fn from_result(name: &str, result: Result<String>) -> Self {
+        match result {
+            Ok(detail) => Self { name: name.to_string(), status: DoctorStatus::Pass, detail },
+            Err(e) => Self { name: name.to_string(), status: DoctorStatus::Fail, detail: e.to_string() },
+        }
+    }
+
+    pub fn skip(name: &str, reason: &str) -> Self {
+        Self { name: name.to_string(), status: DoctorStatus::Skip, detail: reason.to_string() }
+    }
+}
+
+/// Everything `run` would need with the same config, checked up front.
+pub(crate) struct DoctorReport {
+    pub checks: Vec<DoctorCheck>,
+}
+
+impl DoctorReport {
+    pub fn run(config: &Config) -> Self {
+        let mut checks = vec![];
+        if config.text_input {
+            checks.push(DoctorCheck::skip("audio input", "--text-input"));
+        } else if !config.audio_file_path.is_empty() {
+            checks.push(DoctorCheck::from_result("audio input", check_audio_file(&config.audio_file_path)));
+        } else {
+            checks.push(DoctorCheck::from_result("audio input", check_mic(config.mic_index)));
+        }
+        let picovoice_needed = !config.text_input
+            && (config.speech_engine == SpeechEngineType::Picovoice || config.vad_engine == VadEngineType::Picovoice);
+        if picovoice_needed {
+            checks.push(DoctorCheck::from_result("picovoice api key", check_api_key(&config.pico_voice_api_key)));
+        } else {
+            checks.push(DoctorCheck::skip("picovoice api key", "picovoice is not used"));
+        }
+        if !config.text_input && config.speech_engine == SpeechEngineType::Picovoice {
+            if config.pico_voice_ppn_model_path == "default" {
+                checks.push(DoctorCheck::skip("wake word model (.ppn)", "built-in keywords"));
+            } else {
+                checks.push(DoctorCheck::from_result("wake word model (.ppn)", check_model_file(&config.pico_voice_ppn_model_path)));
+            }
+            checks.push(DoctorCheck::from_result("intent model (.rhn)", check_model_file(&config.pico_voice_rhn_model_path)));
+            let porcupine_params = if config.pico_voice_porcupine_params_path.is_empty() {
+                PorcupineWakeWordEngine::default_model_path(&config.language)
+            } else {
+                config.pico_voice_porcupine_params_path.clone()
+            };
+            checks.push(DoctorCheck::from_result("porcupine params", check_model_file(&porcupine_params)));
+            let rhino_params = if config.pico_voice_rhino_params_path.is_empty() {
+                RhinoIntentEngine::default_model_path(&config.language)
+            } else {
+                config.pico_voice_rhino_params_path.clone()
+            };
+            checks.push(DoctorCheck::from_result("rhino params", check_model_file(&rhino_params)));
+        } else {
+            checks.push(DoctorCheck::skip("speech models", "picovoice speech engine is not used"));
+        }
+        match config.speech_output {
+            SpeechOutputType::Tts => {
+                checks.push(DoctorCheck::from_result("tts voice", check_tts_voice(&config.language)));
+            }
+            _ => {
+                checks.push(DoctorCheck::skip("tts voice", "--speech-output is not tts"));
+            }
+        }
+        match config.vision_type {
+            VisionType::None => {
+                checks.push(DoctorCheck::skip("vision source", "--vision-type none"));
+            }
+            VisionType::BuiltInCamera => {
+                checks.push(DoctorCheck::from_result("camera", check_camera(&CameraSettings::from_config(config))));
+            }
+            VisionType::Pupil => {
+                checks.push(DoctorCheck::from_result("pupil remote", check_pupil_remote(&config.zmq_in_endpoint)));
+            }
+            VisionType::Recording => {
+                checks.push(DoctorCheck::from_result("recording", check_path_exists(&config.recording_path)));
+            }
+        }
+        if config.vision_type != VisionType::None {
+            checks.push(DoctorCheck::from_result("aruco board", MarkerBoard::from_config(config)
+                .map(|board| format!("{}, {} mm, {} markers on the board, {} tagged", board.dictionary, board.marker_mm,
+                                     board.markers.iter().filter(|marker| marker.position_mm.is_some()).count(),
+                                     board.markers.iter().filter(|marker| marker.tag.is_some()).count()))));
+        }
+        checks.push(DoctorCheck::from_result("recipes", RecipeCatalog::load(&config.recipe_path)
+            .map(|catalog| format!("{} recipes in {}", catalog.recipes.len(), config.recipe_path))));
+        checks.push(DoctorCheck::from_result("log directory", check_writable_dir(LOG_DIR)));
+        Self {
+            checks,
+        }
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.status != DoctorStatus::Fail)
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in self.checks.iter() {
+            let status = match check.status {
+                DoctorStatus::Pass => "PASS",
+                DoctorStatus::Fail => "FAIL",
+                DoctorStatus::Skip => "SKIP",
+            };
+            writeln!(f, "[{}] {}: {}", status, check.name, check.detail)?;
+        }
+        let count = |status: DoctorStatus| self.checks.iter().filter(|check| check.status == status).count();
+        write!(f, "{} passed, {} failed, {} skipped", count(DoctorStatus::Pass), count(DoctorStatus::Fail), count(DoctorStatus::Skip))
+    }
+}
+
+pub(crate) fn check_mic(mic_index: u32) -> Result<String> {
+    let devices = list_audio_devices()?;
+    let device = devices.get(mic_index as usize)
+        .ok_or(anyhow!("mic index {} not found. {} devices available", mic_index, devices.len()))?;
+    Ok(format!("{}: {}", mic_index, device))
+}
+
+pub(crate) fn check_audio_file(path: &str) -> Result<String> {
+    let wav = WavFile::open(path)?;
+    if wav.sample_rate != SAMPLE_RATE {
+        return Err(anyhow!("wav sample rate must be {}Hz, got {}Hz", SAMPLE_RATE, wav.sample_rate));
+    }
+    Ok(format!("{} ({:.2}s)", path, wav.duration_secs()))
+}
+
+pub(crate) fn check_api_key(api_key: &str) -> Result<String> {
+    if api_key.trim().is_empty() {
+        return Err(anyhow!("--pv-api-key is not set"));
+    }
+    Ok("set".to_string())
+}
+
+pub(crate) fn check_model_file(path: &str) -> Result<String> {
+    check_path_exists(path)?;
+    if !Path::new(path).is_file() {
+        return Err(anyhow!("{} is not a file", path));
+    }
+    Ok(path.to_string())
+}
+
+pub(crate) fn check_path_exists(path: &str) -> Result<String> {
+    if path.is_empty() {
+        return Err(anyhow!("path is not set"));
+    }
+    if !Path::new(path).exists() {
+        return Err(anyhow!("{} not found", path));
+    }
+    Ok(path.to_string())
+}
+
+pub(crate) fn check_tts_voice(language: &LanguageTag) -> Result<String> {
+    let voices = list_voices(language)?;
+    let voice = voices.first().ok_or(anyhow!("no voice found for {}", language.to_str()))?;
+    Ok(format!("{} ({} voices for {})", voice.name(), voices.len(), language.to_str()))
+}
+
+pub(crate) fn check_camera(settings: &CameraSettings) -> Result<String> {
+    let mut camera = Camera::new(settings)?;
+    let frame = camera.get_frame()?;
+    if frame.empty() {
+        return Err(anyhow!("camera {} returned an empty frame", settings.device));
+    }
+    Ok(format!("camera {} ({}): {}", settings.device, settings.backend, camera.info()))
+}
+
+pub(crate) fn check_pupil_remote(endpoint: &str) -> Result<String> {
+    if endpoint.is_empty() {
+        return Err(anyhow!("--zmq-in-endpoint is not set"));
+    }
+    let sub_port = request_sub_port(endpoint, PUPIL_REMOTE_TIMEOUT_MS)?;
+    Ok(format!("{} (SUB_PORT {})", endpoint, sub_port))
+}
+
+pub(crate) fn check_writable_dir(dir: &str) -> Result<String> {
+    std::fs::create_dir_all(dir).map_err(|e| anyhow!("failed to create {}: {}", dir, e))?;
+    let probe = Path::new(dir).join(".doctor");
+    std::fs::write(&probe, b"").map_err(|e| anyhow!("{} is not writable: {}", dir, e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(dir.to_string())
+}
@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod doctor_util_tests {
+    use crate::utils::fake_pupil_util::{FakePupilFrameSource, FakePupilGazeSource, FakePupilServer};
+    use super::super::doctor_util::*;
+
+    #[test]
+    fn pupil_remote_check_test() {
+        let mut server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Synthetic,
+            60.).unwrap();
+        let detail = check_pupil_remote(&server.endpoint()).unwrap();
+        assert!(detail.contains("SUB_PORT"));
+        server.stop();
+        assert!(check_pupil_remote("").is_err());
+    }
+
+    #[test]
+    fn pupil_remote_unreachable_test() {
+        // nothing listens on the discard port
+        assert!(check_pupil_remote("127.0.0.1:9").is_err());
+    }
+
+    #[test]
+    fn model_file_check_test() {
+        assert!(check_model_file(&format!("{}/Cargo.toml", env!("CARGO_MANIFEST_DIR"))).is_ok());
+        assert!(check_model_file("/nonexistent/model.rhn").is_err());
+        assert!(check_model_file(env!("CARGO_MANIFEST_DIR")).is_err());
+        assert!(check_api_key(" ").is_err());
+    }
+
+    #[test]
+    fn writable_dir_check_test() {
+        let dir = std::env::temp_dir().join("vas_doctor_log");
+        assert!(check_writable_dir(dir.to_str().unwrap()).is_ok());
+        assert!(!dir.join(".doctor").exists());
+    }
+
+    #[test]
+    fn doctor_report_test() {
+        let report = DoctorReport {
+            checks: vec![
+                DoctorCheck::from_result("mic", Ok("0: default".to_string())),
+                DoctorCheck::skip("camera", "--vision-type none"),
+            ],
+        };
+        assert!(report.is_ok());
+        assert!(report.to_string().ends_with("1 passed, 0 failed, 1 skipped"));
+        let report = DoctorReport {
+            checks: vec![DoctorCheck::from_result("tts voice", Err(anyhow::anyhow!("no voice found for ja-JP")))],
+        };
+        assert!(!report.is_ok());
+        assert!(report.to_string().starts_with("[FAIL] tts voice: no voice found for ja-JP"));
+    }
+}
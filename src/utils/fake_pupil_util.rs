@@ -0,0 +1,253 @@
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use opencv::core::{Mat, Point, Scalar, CV_8UC3};
+use opencv::imgproc;
+use opencv::prelude::*;
+use anyhow::{anyhow, Result};
+use rmps::Serializer;
+use serde::Serialize;
+use zmq;
+use crate::smart_speaker::models::vision_model::Capture;
+use crate::utils::pupil_util::{Pupil3dGazePayload, PupilCameraPayload};
+
+/// Where the fake server takes world camera frames from.
+pub(crate) enum FakePupilFrameSource {
+    /// Gray frames with a green dot at the synthetic gaze position.
+    Synthetic { width: i32, height: i32 },
+    /// Frames from a capture source, e.g. a recording of a study session.
+    Recording(Capture),
+}
+
+/// Where the fake server takes gaze data from.
+pub(crate) enum FakePupilGazeSource {
+    /// Gaze moving on a circle around the center of the frame.
+    Synthetic,
+    /// Recorded gaze samples, replayed by their timestamps.
+    Recording(Vec<FakePupilGazeSample>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct FakePupilGazeSample {
+    pub timestamp: f64,
+    pub norm_pos: (f32, f32),
+    pub confidence: f32,
+}
+
+/// Load gaze samples from a Pupil Player `gaze_positions.csv` export,
+/// or any csv with `timestamp`, `norm_pos_x`, `norm_pos_y` and `confidence` columns.
+pub(crate) fn load_gaze_csv(path: &str) -> Result<Vec<FakePupilGazeSample>> {
+    let raw = fs::read_to_string(path).map_err(|e| anyhow!("failed to read gaze csv {}: {}", path, e))?;
+    let mut lines = raw.lines();
+    let header = lines.next().ok_or(anyhow!("gaze csv is empty"))?
+        .split(',').map(|c| c.trim()).collect::<Vec<&str>>();
+    let column = |names: &[&str]| header.iter().position(|c| names.contains(c))
+        .ok_or(anyhow!("gaze csv has no {} column", names[0]));
+    let timestamp = column(&["gaze_timestamp", "timestamp"])?;
+    let x = column(&["norm_pos_x"])?;
+    let y = column(&["norm_pos_y"])?;
+    let confidence = column(&["confidence"])?;
+    let mut samples = vec![];
+    for (i, line) in lines.enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let values = line.split(',').map(|v| v.trim()).collect::<Vec<&str>>();
+        let value = |index: usize| values.get(index)
+            .ok_or(anyhow!("gaze csv line {}: missing column", i + 2))?
+            .parse::<f64>()
+            .map_err(|e| anyhow!("gaze csv line {}: {}", i + 2, e));
+        samples.push(FakePupilGazeSample {
+            timestamp: value(timestamp)?,
+            norm_pos: (value(x)? as f32, value(y)? as f32),
+            confidence: value(confidence)? as f32,
+        });
+    }
+    samples.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    Ok(samples)
+}
+
+/// A stand-in for Pupil Capture. Answers the Pupil Remote REQ socket (`SUB_PORT`, notifications)
+/// and publishes `frame.world` and `gaze` data like the real frame publisher plugin.
+pub(crate) struct FakePupilServer {
+    endpoint: String,
+    alive: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl FakePupilServer {
+    /// Bind to `endpoint` (`host:port`). Port 0 picks a free port, see `endpoint()`.
+    pub fn start(endpoint: &str, frames: FakePupilFrameSource, gaze: FakePupilGazeSource, fps: f64) -> Result<Self> {
+        let host = endpoint.split(":").next().unwrap_or("127.0.0.1").to_string();
+        let port = endpoint.split(":").nth(1).unwrap_or("0");
+        let ctx = zmq::Context::new();
+        let remote = ctx.socket(zmq::REP)?;
+        remote.bind(&format!("tcp://{}:{}", host, if port == "0" { "*" } else { port }))?;
+        remote.set_rcvtimeo(100)?;
+        let publisher = ctx.socket(zmq::PUB)?;
+        publisher.bind(&format!("tcp://{}:*", host))?;
+        let remote_port = last_endpoint_port(&remote)?;
+        let sub_port = last_endpoint_port(&publisher)?;
+        let alive = Arc::new(AtomicBool::new(true));
+        let started_at = Instant::now();
+
+        let remote_alive = alive.clone();
+        let remote_handle = thread::spawn(move || {
+            while remote_alive.load(Ordering::Relaxed) {
+                if let Ok(request) = remote.recv_multipart(0) {
+                    let command = String::from_utf8_lossy(&request[0]).to_string();
+                    let reply = match command.as_str() {
+                        "SUB_PORT" | "PUB_PORT" => sub_port.clone(),
+                        "t" => started_at.elapsed().as_secs_f64().to_string(),
+                        c if c.starts_with("notify.") => "Notification received".to_string(),
+                        _ => "Unknown command.".to_string(),
+                    };
+                    let _ = remote.send(reply.as_str(), 0);
+                }
+            }
+        });
+
+        let publisher_alive = alive.clone();
+        let publisher_handle = thread::spawn(move || {
+            let mut publisher = FakePupilPublisher {
+                socket: publisher,
+                frames,
+                gaze,
+                started_at,
+                gaze_cursor: 0,
+                gaze_loop_started_at: started_at,
+                frame_index: 0,
+            };
+            let interval = Duration::from_secs_f64(1. / if fps > 0. { fps } else { 30. });
+            while publisher_alive.load(Ordering::Relaxed) {
+                let tick = Instant::now();
+                publisher.publish();
+                if let Some(rest) = interval.checked_sub(tick.elapsed()) {
+                    thread::sleep(rest);
+                }
+            }
+        });
+
+        Ok(Self {
+            endpoint: format!("{}:{}", host, remote_port),
+            alive,
+            handles: vec![remote_handle, publisher_handle],
+        })
+    }
+
+    /// Pupil Remote endpoint to give to `PupilRemote::new`.
+    pub fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    pub fn stop(&mut self) {
+        self.alive.store(false, Ordering::Relaxed);
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for FakePupilServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn last_endpoint_port(socket: &zmq::Socket) -> Result<String> {
+    let endpoint = socket.get_last_endpoint()?.map_err(|_| anyhow!("invalid endpoint"))?;
+    endpoint.rsplit(":").next().map(|p| p.to_string()).ok_or(anyhow!("invalid endpoint: {}", endpoint))
+}
+
+struct FakePupilPublisher {
+    socket: zmq::Socket,
+    frames: FakePupilFrameSource,
+    gaze: FakePupilGazeSource,
+    started_at: Instant,
+    gaze_cursor: usize,
+    gaze_loop_started_at: Instant,
+    frame_index: i32,
+}
+
+impl FakePupilPublisher {
+    fn publish(&mut self) {
+        let timestamp = self.started_at.elapsed().as_secs_f64();
+        let samples = self.next_gaze_samples(timestamp);
+        let frame = match &mut self.frames {
+            FakePupilFrameSource::Synthetic { width, height } => {
+                synthetic_frame(*width, *height, samples.last().map(|s| s.norm_pos).unwrap_or((0.5, 0.5)))
+            }
+            FakePupilFrameSource::Recording(capture) => {
+                match capture.source.as_mut() {
+                    None => Err(anyhow!("no capture source")),
+                    Some(source) => source.get_frame(),
+                }
+            }
+        };
+        if let Ok(frame) = frame {
+            let _ = self.send_frame(&frame, timestamp);
+        }
+        for sample in samples {
+            let _ = self.send_gaze(&sample);
+        }
+    }
+
+    fn next_gaze_samples(&mut self, timestamp: f64) -> Vec<FakePupilGazeSample> {
+        match &self.gaze {
+            FakePupilGazeSource::Synthetic => {
+                let angle = timestamp * std::f64::consts::PI;
+                vec![FakePupilGazeSample {
+                    timestamp,
+                    norm_pos: ((0.5 + 0.25 * angle.cos()) as f32, (0.5 + 0.25 * angle.sin()) as f32),
+                    confidence: 0.95,
+                }]
+            }
+            FakePupilGazeSource::Recording(samples) => {
+                if samples.is_empty() {
+                    return vec![]
+                }
+                if self.gaze_cursor >= samples.len() {
+                    self.gaze_cursor = 0;
+                    self.gaze_loop_started_at = Instant::now();
+                }
+                let first = samples[0].timestamp;
+                let elapsed = self.gaze_loop_started_at.elapsed().as_secs_f64();
+                let due = samples[self.gaze_cursor..].iter()
+                    .take_while(|s| s.timestamp - first <= elapsed)
+                    .map(|s| FakePupilGazeSample { timestamp, ..s.clone() })
+                    .collect::<Vec<FakePupilGazeSample>>();
+                self.gaze_cursor += due.len();
+                due
+            }
+        }
+    }
+
+    fn send_frame(&mut self, frame: &Mat, timestamp: f64) -> Result<()> {
+        let size = frame.size()?;
+        let payload = PupilCameraPayload::new("bgr", size.width, size.height, self.frame_index, timestamp);
+        self.frame_index += 1;
+        let mut serializer = Serializer::new(Vec::new()).with_struct_map();
+        payload.serialize(&mut serializer)?;
+        self.socket.send("frame.world", zmq::SNDMORE)?;
+        self.socket.send(serializer.into_inner(), zmq::SNDMORE)?;
+        self.socket.send(frame.data_bytes()?, 0)?;
+        Ok(())
+    }
+
+    fn send_gaze(&mut self, sample: &FakePupilGazeSample) -> Result<()> {
+        let payload = Pupil3dGazePayload::new(sample.norm_pos, sample.timestamp, sample.confidence);
+        let mut serializer = Serializer::new(Vec::new()).with_struct_map();
+        payload.serialize(&mut serializer)?;
+        self.socket.send("gaze.3d.0.", zmq::SNDMORE)?;
+        self.socket.send(serializer.into_inner(), 0)?;
+        Ok(())
+    }
+}
+
+fn synthetic_frame(width: i32, height: i32, (gaze_x, gaze_y): (f32, f32)) -> Result<Mat> {
+    let mut frame = Mat::new_rows_cols_with_default(height, width, CV_8UC3, Scalar::new(64., 64., 64., 0.))?;
+    // pupil norm_pos origin is bottom left
+    let center = Point::new((gaze_x * width as f32) as i32, ((1. - gaze_y) * height as f32) as i32);
+    imgproc::circle(&mut frame, center, 20, Scalar::new(0., 255., 0., 0.), -1, imgproc::LINE_8, 0)?;
+    Ok(frame)
+}
@@ -1,56 +1,36 @@
-// use opencv::prelude::*;
-// use opencv::{objdetect, imgproc, imgcodecs};
-// use opencv::core::{CV_8UC1, hconcat2, Mat, Rect, Scalar, vconcat};
-// use opencv::types::{VectorOfi32, VectorOfMat};
-// use anyhow::Result;
+use std::path::{Path, PathBuf};
+use opencv::prelude::*;
+use opencv::{core, objdetect, imgcodecs};
+use crate::smart_speaker::models::marker_model::ArucoDictionary;
+use opencv::core::{Mat, Scalar, BORDER_CONSTANT};
+use opencv::types::VectorOfi32;
+use anyhow::{anyhow, Result};
 
-// pub fn generate_aruco(marker_amount: u32) -> Result<Vec<u32>> {
-//     let mut index_generated = vec![];
-//     let pixel = 200;
-//     let mut img: Mat = Default::default();
-//     let mut imwrite_params = VectorOfi32::new();
-//     imwrite_params.push(imgcodecs::IMWRITE_PNG_COMPRESSION);
-//     imwrite_params.push(1);
-//     let mut concat_temp_1: VectorOfMat = Default::default();
-//     let mut concat_temp_2: VectorOfMat = Default::default();
-//     let mut v_concat_1: Mat = Default::default();
-//     let mut v_concat_2: Mat = Default::default();
-//
-//     let dictionary = objdetect::get_predefined_dictionary(objdetect::PredefinedDictionaryType::DICT_4X4_50)?;
-//     for i in 0..marker_amount {
-//         let mut ar_marker: Mat = Default::default();
-//         imgproc::draw_marker(&mut img, Point::new(pixel / 2, pixel / 2),
-//                              Scalar::new(0., 0., 0., 255.), dictionary .get(i as i32).unwrap(), pixel, 1, 1).unwrap();
-//         let mut padding_img = Mat::new_rows_cols_with_default(
-//             ar_marker.rows() + 100, ar_marker.cols() + 100,
-//             CV_8UC1, Scalar::new(255., 255., 255., 255.,))?;
-//         let mut padding_roi = Mat::roi(&mut padding_img, Rect::new(50, 50, ar_marker.cols(), ar_marker.rows()))?;
-//         ar_marker.copy_to(&mut padding_roi).unwrap();
-//         if i % 2 == 0 {
-//             concat_temp_1.push(padding_img.clone());
-//         } else {
-//             concat_temp_2.push(padding_img.clone());
-//         }
-//         imgcodecs::imwrite(format!("ar_{i}.png").as_str(), &ar_marker, &imwrite_params)?;
-//         index_generated.push(i);
-//     }
-//     match concat_temp_1.len() > concat_temp_2.len() {
-//         true => {
-//             concat_temp_2.push(generate_padding_img(&concat_temp_1.get(0)?)?.clone());
-//         },
-//         false => {
-//             concat_temp_1.push(generate_padding_img(&concat_temp_1.get(0)?)?.clone());
-//         },
-//     }
-//     vconcat(&concat_temp_1, &mut v_concat_1)?;
-//     vconcat(&concat_temp_2, &mut v_concat_2)?;
-//     hconcat2(&v_concat_1, &v_concat_2, &mut img).unwrap();
-//     imgcodecs::imwrite("ar_all.png", &img, &imwrite_params).unwrap();
-//     Ok(index_generated)
-// }
-
-// fn generate_padding_img(mat: &Mat) -> Result<Mat> {
-//     Ok(Mat::new_rows_cols_with_default(
-//         mat.rows(), mat.cols(),
-//         CV_8UC1, Scalar::new(255., 255., 255., 255.,))?)
-// }
+/// Write `aruco_<id>.png` of `dictionary` for ids `0..marker_amount` into `output_dir`.
+/// Markers are `pixel` wide with a white quiet zone of a quarter of the marker width on each side.
+pub(crate) fn generate_aruco(dictionary: ArucoDictionary, marker_amount: u32, pixel: i32, output_dir: &str) -> Result<Vec<PathBuf>> {
+    if marker_amount == 0 || marker_amount > dictionary.size {
+        return Err(anyhow!("marker amount must be 1 to {} for {}", dictionary.size, dictionary));
+    }
+    std::fs::create_dir_all(output_dir)?;
+    let dictionary = dictionary.dictionary()?;
+    let mut imwrite_params = VectorOfi32::new();
+    imwrite_params.push(imgcodecs::IMWRITE_PNG_COMPRESSION);
+    imwrite_params.push(1);
+    let padding = pixel / 4;
+    let mut paths = vec![];
+    for i in 0..marker_amount {
+        let mut marker = Mat::default();
+        objdetect::generate_image_marker(&dictionary, i as i32, pixel, &mut marker, 1)?;
+        let mut padded = Mat::default();
+        core::copy_make_border(&marker, &mut padded, padding, padding, padding, padding,
+                               BORDER_CONSTANT, Scalar::all(255.))?;
+        let path = Path::new(output_dir).join(format!("aruco_{}.png", i));
+        let written = imgcodecs::imwrite(path.to_str().ok_or(anyhow!("invalid output path"))?, &padded, &imwrite_params)?;
+        if !written {
+            return Err(anyhow!("failed to write {}", path.display()));
+        }
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -1,5 +1,8 @@
 use std::sync::mpsc;
-use crate::smart_speaker::models::core_model::SmartSpeakerState;
+use std::time::{Duration, Instant};
+use crate::smart_speaker::models::core_model::{SmartSpeakerState, HEARTBEAT_INTERVAL};
+use crate::smart_speaker::models::frame_model::SharedFrame;
+use crate::smart_speaker::models::gaze_model::{Fixation, GazeSample};
 use crate::smart_speaker::models::message_model::*;
 
 pub(crate) fn audio_stream_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
@@ -21,13 +24,11 @@ pub(crate) fn audio_stream_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
 pub(crate) fn camera_frame_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
                                    send_from: SmartSpeakerActors,
                                    send_to: SmartSpeakerActors,
-                                   frame_data_bytes: Vec<u8>,
-                                   height: i32) {
+                                   frame: Option<SharedFrame>) {
     match sender.send(SmartSpeakerMessage::RequestCameraFrame(CameraFrameMessage {
         send_from,
         send_to,
-        frame_data_bytes,
-        height,
+        frame,
     })) {
         Ok(_) => {}
         Err(e) => {
@@ -53,11 +54,13 @@ pub(crate) fn generate_actor_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
 pub(crate) fn gaze_info_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
                                 send_from: SmartSpeakerActors,
                                 send_to: SmartSpeakerActors,
-                                gaze_info: (f32, f32)) {
+                                gaze_info: Option<GazeSample>,
+                                fixation: Option<Fixation>) {
     match sender.send(SmartSpeakerMessage::RequestGazeInfo(GazeInfoMessage {
         send_from,
         send_to,
         gaze_info,
+        fixation,
     })) {
         Ok(_) => {}
         Err(e) => {
@@ -207,3 +210,105 @@ pub(crate) fn write_log_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
         }
     }
 }
+
+pub(crate) fn heartbeat_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
+                                send_from: SmartSpeakerActors) {
+    match sender.send(SmartSpeakerMessage::Heartbeat(HeartbeatMessage {
+        send_from,
+    })) {
+        Ok(_) => {}
+        Err(e) => {
+            println!("Error: {}", e);
+        }
+    }
+}
+
+pub(crate) fn actor_restarted_message(sender: &mpsc::Sender<SmartSpeakerMessage>,
+                                      send_from: SmartSpeakerActors,
+                                      send_to: SmartSpeakerActors,
+                                      actor: SmartSpeakerActors,
+                                      cause: String,
+                                      degraded: bool) {
+    match sender.send(SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage {
+        send_from,
+        send_to,
+        actor,
+        cause,
+        degraded,
+    })) {
+        Ok(_) => {}
+        Err(e) => {
+            println!("Error: {}", e);
+        }
+    }
+}
+
+/// Sends a heartbeat of `actor` to the core actor at most once per `HEARTBEAT_INTERVAL`. Call it on every loop.
+pub(crate) struct Heartbeat {
+    actor: SmartSpeakerActors,
+    last_sent: Instant,
+}
+
+impl Heartbeat {
+    pub(crate) fn new(actor: SmartSpeakerActors) -> Self {
+        Self {
+            actor,
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// When the next heartbeat is due. Actors without periodic work wait for messages until then.
+    pub(crate) fn deadline(&self) -> Instant {
+        self.last_sent + HEARTBEAT_INTERVAL
+    }
+
+    pub(crate) fn beat(&mut self, sender: &mpsc::Sender<SmartSpeakerMessage>) {
+        if self.last_sent.elapsed() >= HEARTBEAT_INTERVAL {
+            heartbeat_message(sender, self.actor.clone());
+            self.last_sent = Instant::now();
+        }
+    }
+}
+
+/// Schedules the periodic work of an actor loop. A late tick is not caught up.
+pub(crate) struct Ticker {
+    interval: Duration,
+    next_tick: Instant,
+}
+
+impl Ticker {
+    /// The first tick is due immediately.
+    pub(crate) fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next_tick: Instant::now(),
+        }
+    }
+
+    pub(crate) fn deadline(&self) -> Instant {
+        self.next_tick
+    }
+
+    /// True once per interval. Call it on every loop.
+    pub(crate) fn tick(&mut self) -> bool {
+        let now = Instant::now();
+        if now < self.next_tick {
+            return false;
+        }
+        self.next_tick += self.interval;
+        if self.next_tick <= now && !self.interval.is_zero() {
+            self.next_tick = now + self.interval;
+        }
+        true
+    }
+}
+
+/// Block until a message arrives or `deadline` passes. None when the deadline passed first.
+/// A closed channel means the core actor dropped this actor, so it is reported as a shutdown request.
+pub(crate) fn receive_until(receiver: &mpsc::Receiver<SmartSpeakerMessage>, deadline: Instant) -> Option<SmartSpeakerMessage> {
+    match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(message) => Some(message),
+        Err(mpsc::RecvTimeoutError::Timeout) => None,
+        Err(mpsc::RecvTimeoutError::Disconnected) => Some(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})),
+    }
+}
@@ -1,7 +1,7 @@
 #[cfg(test)]
 mod message_util_tests {
     use std::sync::mpsc;
-    use crate::smart_speaker::models::core_model::SmartSpeakerState;
+    use crate::smart_speaker::models::core_model::{SmartSpeakerState, HEARTBEAT_INTERVAL};
     use crate::smart_speaker::models::message_model::*;
     use super::super::message_util::*;
 
@@ -67,4 +67,71 @@ mod message_util_tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn actor_restarted_message_test() {
+        let (sender, receiver) = mpsc::channel::<SmartSpeakerMessage>();
+        actor_restarted_message(
+            &sender,
+            SmartSpeakerActors::CoreActor,
+            SmartSpeakerActors::ContextActor,
+            SmartSpeakerActors::VisionActor,
+            "panicked: test".to_string(),
+            false,
+        );
+        match receiver.recv().unwrap() {
+            SmartSpeakerMessage::ReportActorRestarted(ActorRestartedMessage { send_from, send_to, actor, cause, degraded }) => {
+                assert_eq!(send_from, SmartSpeakerActors::CoreActor);
+                assert_eq!(send_to, SmartSpeakerActors::ContextActor);
+                assert_eq!(actor, SmartSpeakerActors::VisionActor);
+                assert_eq!(cause, "panicked: test");
+                assert!(!degraded);
+            },
+            _ => {
+                panic!("unexpected message");
+            }
+        }
+    }
+
+    #[test]
+    fn heartbeat_test() {
+        let (sender, receiver) = mpsc::channel::<SmartSpeakerMessage>();
+        let mut heartbeat = Heartbeat::new(SmartSpeakerActors::WakeWordActor);
+        heartbeat.beat(&sender);
+        assert!(receiver.try_recv().is_err());
+        std::thread::sleep(HEARTBEAT_INTERVAL);
+        heartbeat.beat(&sender);
+        heartbeat.beat(&sender);
+        assert_eq!(receiver.try_recv().unwrap(), SmartSpeakerMessage::Heartbeat(HeartbeatMessage {
+            send_from: SmartSpeakerActors::WakeWordActor,
+        }));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn ticker_test() {
+        let mut ticker = Ticker::new(std::time::Duration::from_millis(20));
+        assert!(ticker.tick());
+        assert!(!ticker.tick());
+        std::thread::sleep(ticker.deadline().saturating_duration_since(std::time::Instant::now()));
+        assert!(ticker.tick());
+        // a late tick is not caught up
+        std::thread::sleep(std::time::Duration::from_millis(70));
+        assert!(ticker.tick());
+        assert!(!ticker.tick());
+    }
+
+    #[test]
+    fn receive_until_test() {
+        let (sender, receiver) = mpsc::channel::<SmartSpeakerMessage>();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_millis(20);
+        assert!(receive_until(&receiver, deadline).is_none());
+        assert!(std::time::Instant::now() >= deadline);
+        heartbeat_message(&sender, SmartSpeakerActors::GazeActor);
+        assert_eq!(receive_until(&receiver, deadline), Some(SmartSpeakerMessage::Heartbeat(HeartbeatMessage {
+            send_from: SmartSpeakerActors::GazeActor,
+        })));
+        drop(sender);
+        assert_eq!(receive_until(&receiver, deadline), Some(SmartSpeakerMessage::RequestShutdown(ShutdownMessage {})));
+    }
+}
@@ -3,9 +3,16 @@ pub mod config_util_tests;
 pub mod message_util;
 pub mod message_util_tests;
 pub mod pupil_util;
-// pub mod pupil_util_tests;
+pub mod pupil_util_tests;
+pub mod fake_pupil_util;
+pub mod replay_util;
+pub mod replay_util_tests;
+pub mod audio_util;
+pub mod audio_util_tests;
 pub mod camera_util;
-// pub mod camera_util_tests;
+pub mod camera_util_tests;
+pub mod calibration_util;
+pub mod calibration_util_tests;
 pub mod marker_util;
 // pub mod marker_util_tests;
 pub mod vision_util;
@@ -13,3 +20,5 @@ pub mod vision_util;
 pub mod query_util;
 pub mod gaze_util;
 pub mod debug_util;
+pub mod doctor_util;
+pub mod doctor_util_tests;
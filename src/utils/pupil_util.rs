@@ -1,9 +1,11 @@
 use std::io::{Cursor};
+use std::str::FromStr;
 use opencv::prelude::*;
 use anyhow::{anyhow, Result};
 use zmq;
 use rmps::{Deserializer, Serializer};
 use serde::{Deserialize, Serialize};
+use crate::smart_speaker::models::frame_model::PixelFormat;
 
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub(crate) struct PupilNotifySetFrameFormat {
@@ -24,6 +26,20 @@ pub(crate) struct PupilCameraPayload {
     raw_data: Option<Vec<u8>>,
 }
 
+impl PupilCameraPayload {
+    pub fn new(format: &str, width: i32, height: i32, index: i32, timestamp: f64) -> Self {
+        Self {
+            format: format.to_string(),
+            topic: "frame.world".to_string(),
+            width,
+            height,
+            index,
+            timestamp,
+            raw_data: None,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub(crate) struct Pupil3dGazePayload {
     // id: i32,
@@ -36,6 +52,17 @@ pub(crate) struct Pupil3dGazePayload {
     // ellipse: Pupil3dGazeEllipse,
 }
 
+impl Pupil3dGazePayload {
+    pub fn new(norm_pos: (f32, f32), timestamp: f64, confidence: f32) -> Self {
+        Self {
+            topic: "gaze.3d.0.".to_string(),
+            norm_pos: vec![norm_pos.0, norm_pos.1],
+            timestamp,
+            confidence,
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Deserialize, Serialize)]
 pub(crate) struct Pupil3dGazeEllipse {
     angle: f32,
@@ -93,10 +120,16 @@ impl Pupil {
     }
 
     pub fn get_frame(&self) -> Result<Mat> {
+        let (frame, _, _) = self.get_frame_with_info()?;
+        Ok(frame)
+    }
+
+    /// The frame with its pixel format and pupil timestamp.
+    pub fn get_frame_with_info(&self) -> Result<(Mat, PixelFormat, f64)> {
         let mut frame = Mat::default();
         match self.pupil_remote.get_frame(&mut frame) {
-            Ok(_) => {
-                Ok(frame)
+            Ok(payload) => {
+                Ok((frame, PixelFormat::from_str(&payload.format)?, payload.timestamp))
             }
             Err(_) => {
                 Err(anyhow!("failed to get frame from pupil core"))
@@ -105,16 +138,42 @@ impl Pupil {
     }
 
     pub fn get_gaze(&self) -> Result<(f32, f32)> {
+        let (gaze, _, _) = self.get_gaze_with_info()?;
+        Ok(gaze)
+    }
+
+    /// The gaze position with its pupil timestamp and confidence. Low confidence samples are not dropped.
+    pub fn get_gaze_with_info(&self) -> Result<((f32, f32), f64, f32)> {
         let mut gaze = (0., 0.);
         match self.pupil_remote.get_gaze(&mut gaze) {
-            Ok(_) => {
-                Ok(gaze)
+            Ok((timestamp, confidence)) => {
+                Ok((gaze, timestamp, confidence))
             }
             Err(_) => {
                 Err(anyhow!("failed to get gaze from pupil core"))
             }
         }
     }
+
+    /// True if data was received and can be read without blocking.
+    pub fn has_pending(&self) -> bool {
+        self.pupil_remote.has_pending()
+    }
+}
+
+/// Ask Pupil Remote at `endpoint` (host:port) for the `SUB_PORT` without blocking longer than `timeout_ms`.
+pub(crate) fn request_sub_port(endpoint: &str, timeout_ms: i32) -> Result<String> {
+    let ctx = zmq::Context::new();
+    let socket = ctx.socket(zmq::REQ)?;
+    socket.set_linger(0)?;
+    socket.set_sndtimeo(timeout_ms)?;
+    socket.set_rcvtimeo(timeout_ms)?;
+    socket.connect(&format!("tcp://{}", endpoint))?;
+    socket.send("SUB_PORT", 0).map_err(|e| anyhow!("failed to send SUB_PORT to {}: {}", endpoint, e))?;
+    let reply = socket.recv_string(0).map_err(|e| anyhow!("no reply from pupil remote at {}: {}", endpoint, e))?;
+    let sub_port = reply.map_err(|_| anyhow!("invalid SUB_PORT reply from {}", endpoint))?;
+    sub_port.parse::<u16>().map_err(|_| anyhow!("invalid SUB_PORT reply from {}: {}", endpoint, sub_port))?;
+    Ok(sub_port)
 }
 
 pub(crate) struct PupilRemote {
@@ -186,27 +245,29 @@ impl PupilRemote {
 
     }
 
-    pub fn get_frame(&self, frame: &mut Mat) -> Result<()> {
+    /// The payload is returned without the pixels, which are decoded into `frame`.
+    pub fn get_frame(&self, frame: &mut Mat) -> Result<PupilCameraPayload> {
         match self.sub.as_ref() {
             None => {}
             Some(socket) => {
                 let _ = socket.recv_string(0).unwrap().unwrap(); // topic
                 let msg = socket.recv_bytes(0).unwrap();
                 let mut de = Deserializer::new(Cursor::new(&msg[..]));
-                let mut payload: PupilCameraPayload = Deserialize::deserialize(&mut de).unwrap();
+                let payload: PupilCameraPayload = Deserialize::deserialize(&mut de).unwrap();
                 let mut additional_data: Vec<u8> = vec![];
                 while socket.get_rcvmore().unwrap() {
                     additional_data.append(&mut socket.recv_bytes(0).unwrap());
                 }
-                payload.raw_data = Some(additional_data);
-                *frame = Mat::from_slice(&payload.raw_data.unwrap()).unwrap().reshape(3, payload.height).unwrap();
-                return Ok(())
+                let channels = PixelFormat::from_str(&payload.format)?.channels();
+                *frame = Mat::from_slice(&additional_data)?.reshape(channels, payload.height)?;
+                return Ok(payload)
             }
         }
         Err(anyhow!("failed to get frame from pupil core. it is not connected?"))
     }
 
-    pub fn get_gaze(&self, gaze: &mut (f32, f32)) -> Result<()> {
+    /// Returns the pupil timestamp and the confidence of the gaze.
+    pub fn get_gaze(&self, gaze: &mut (f32, f32)) -> Result<(f64, f32)> {
         match self.sub.as_ref() {
             None => {}
             Some(socket) => {
@@ -214,15 +275,18 @@ impl PupilRemote {
                 let msg = socket.recv_bytes(0).unwrap();
                 let mut de = Deserializer::new(Cursor::new(&msg[..]));
                 let payload: Pupil3dGazePayload = Deserialize::deserialize(&mut de).unwrap();
-                if &payload.confidence > &0.8_f32 {
-                    gaze.0 = payload.norm_pos[0];
-                    gaze.1 = payload.norm_pos[1];
-                    return Ok(())
-                } else {
-                    return Err(anyhow!("low confidence"))
-                }
+                gaze.0 = payload.norm_pos[0];
+                gaze.1 = payload.norm_pos[1];
+                return Ok((payload.timestamp, payload.confidence))
             }
         }
         Err(anyhow!("failed to get gaze from pupil core. it is not connected?"))
     }
+
+    pub fn has_pending(&self) -> bool {
+        match self.sub.as_ref() {
+            None => false,
+            Some(socket) => socket.poll(zmq::POLLIN, 0).map(|events| events > 0).unwrap_or(false),
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,52 @@
+#[cfg(test)]
+mod pupil_util_tests {
+    use opencv::prelude::*;
+    use crate::utils::fake_pupil_util::*;
+    use super::super::pupil_util::*;
+
+    #[test]
+    fn pupil_get_frame_test() {
+        let server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Synthetic,
+            60.).unwrap();
+        let pupil = Pupil::new(PupilRemote::new(server.endpoint(), "frame"));
+        let frame = pupil.get_frame().unwrap();
+        assert_eq!(frame.size().unwrap().width, 320);
+        assert_eq!(frame.size().unwrap().height, 240);
+        assert_eq!(frame.channels(), 3);
+    }
+
+    #[test]
+    fn pupil_get_gaze_test() {
+        let server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Synthetic,
+            60.).unwrap();
+        let pupil = Pupil::new(PupilRemote::new(server.endpoint(), "gaze"));
+        let (x, y) = pupil.get_gaze().unwrap();
+        assert!(x >= 0.25 && x <= 0.75);
+        assert!(y >= 0.25 && y <= 0.75);
+    }
+
+    #[test]
+    fn pupil_get_recorded_gaze_test() {
+        let path = std::env::temp_dir().join("vas_pupil_gaze_positions.csv");
+        std::fs::write(&path, "gaze_timestamp,world_index,confidence,norm_pos_x,norm_pos_y\n\
+                               100.0,0,0.99,0.1,0.2\n\
+                               100.1,1,0.99,0.1,0.2\n").unwrap();
+        let samples = load_gaze_csv(path.to_str().unwrap()).unwrap();
+        assert_eq!(samples.len(), 2);
+        let server = FakePupilServer::start(
+            "127.0.0.1:0",
+            FakePupilFrameSource::Synthetic { width: 320, height: 240 },
+            FakePupilGazeSource::Recording(samples),
+            60.).unwrap();
+        let pupil = Pupil::new(PupilRemote::new(server.endpoint(), "gaze"));
+        let (x, y) = pupil.get_gaze().unwrap();
+        assert_eq!((x, y), (0.1, 0.2));
+        let _ = std::fs::remove_file(path);
+    }
+}
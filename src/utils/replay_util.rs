@@ -0,0 +1,365 @@
+use std::fs;
+use std::sync::mpsc;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use crate::smart_speaker::actors::context_actor::ContextActor;
+use crate::smart_speaker::models::core_model::{SmartSpeakerState, WaitingInteraction};
+use crate::smart_speaker::models::intent_model::{IntentAction, IntentCookingMenu, IntentSlot};
+use crate::smart_speaker::models::message_model::*;
+use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+use crate::smart_speaker::models::session_model::SessionRecord;
+use crate::smart_speaker::models::speak_model::MachineSpeechBoilerplate;
+use crate::smart_speaker::models::marker_model::MarkerTag;
+use crate::smart_speaker::models::vision_model::{DetectableObject, MarkerPresence, VisionAction, VisionMarker, VisionObject, VisionObjectShape, VisionObjectSize, VisionSlot};
+use crate::utils::config_util::LanguageTag;
+
+/// A message fed into the context actor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum DialogueInput {
+    Intent {
+        action: IntentAction,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        menu: Option<String>,
+        #[serde(default)]
+        failed: bool,
+    },
+    Vision {
+        #[serde(default)]
+        objects: Vec<DialogueVisionObject>,
+        /// Answer to a `MarkerPresence` action.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        markers: Vec<DialogueVisionMarker>,
+        #[serde(default)]
+        failed: bool,
+        /// The objects are candidates that could not be told apart by gaze.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        unsure: bool,
+    },
+    SpeechFinished,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DialogueVisionObject {
+    pub object_type: DetectableObject,
+    pub shape: VisionObjectShape,
+    /// Area on the marker plane in cm^2. Older transcripts call it perimeter.
+    #[serde(alias = "perimeter")]
+    pub area: f32,
+    #[serde(default)]
+    pub width: f32,
+    #[serde(default)]
+    pub height: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DialogueVisionMarker {
+    pub marker: MarkerTag,
+    pub presence: MarkerPresence,
+}
+
+/// A message the context actor sent out. Speech is compared in the transcript language.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DialogueOutput {
+    Speak(String),
+    State(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DialogueTurn {
+    pub input: DialogueInput,
+    #[serde(default)]
+    pub expect: Vec<DialogueOutput>,
+}
+
+/// A scripted or recorded dialogue with the expected outputs of every turn.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct DialogueTranscript {
+    #[serde(default)]
+    pub vision: bool,
+    #[serde(default = "default_language")]
+    pub language: String,
+    /// Answer every speech request with `TextToSpeechFinished` instead of scripting it.
+    #[serde(default)]
+    pub auto_speech_finished: bool,
+    pub turns: Vec<DialogueTurn>,
+}
+
+fn default_language() -> String {
+    LanguageTag::Japanese.to_str().to_string()
+}
+
+impl DialogueTranscript {
+    pub(crate) fn load(path: &str) -> Result<Self> {
+        let raw = fs::read_to_string(path).map_err(|e| anyhow!("failed to read transcript {}: {}", path, e))?;
+        serde_json::from_str::<Self>(&raw).map_err(|e| anyhow!("failed to parse transcript {}: {}", path, e))
+    }
+
+    /// Build a transcript from a session file written by `SessionRecorder`.
+    /// Inputs are the messages routed to the context actor, expectations are what it answered.
+    pub(crate) fn from_session(path: &str, vision: bool, language: &LanguageTag) -> Result<Self> {
+        let raw = fs::read_to_string(path).map_err(|e| anyhow!("failed to read session {}: {}", path, e))?;
+        let mut turns: Vec<DialogueTurn> = vec![];
+        for (i, line) in raw.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let record = serde_json::from_str::<SessionRecord>(line)
+                .map_err(|e| anyhow!("session line {}: {}", i + 1, e))?;
+            let from_context = record.send_from.as_deref() == Some("ContextActor");
+            match record.kind.as_str() {
+                "IntentFinalized" => {
+                    turns.push(DialogueTurn { input: intent_input_from_payload(&record.payload)?, expect: vec![] });
+                }
+                "VisionFinalized" => {
+                    turns.push(DialogueTurn { input: vision_input_from_payload(&record.payload)?, expect: vec![] });
+                }
+                "TextToSpeechFinished" => {
+                    match record.send_to.as_deref() {
+                        Some("WakeWordActor") | Some("CoreActor") => {}
+                        _ => turns.push(DialogueTurn { input: DialogueInput::SpeechFinished, expect: vec![] }),
+                    }
+                }
+                "RequestTextToSpeech" if from_context => {
+                    let text = match record.payload.get("boilerplate") {
+                        Some(index) => boilerplate_text(index.as_u64().unwrap_or(usize::MAX as u64) as usize, language),
+                        None => serde_json::from_value::<SmartSpeakerI18nText>(record.payload.clone())?.get(language),
+                    };
+                    if let Some(turn) = turns.last_mut() {
+                        turn.expect.push(DialogueOutput::Speak(text));
+                    }
+                }
+                "RequestStateUpdate" if from_context => {
+                    let state = record.payload["state"].as_str().ok_or(anyhow!("session line {}: no state", i + 1))?;
+                    if let Some(turn) = turns.last_mut() {
+                        turn.expect.push(DialogueOutput::State(normalize_state_label(state)));
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(Self {
+            vision,
+            language: language.to_str().to_string(),
+            auto_speech_finished: false,
+            turns,
+        })
+    }
+}
+
+/// Drives a `ContextActor` without threads. Every input is handled synchronously and
+/// the outputs it produced are collected right after, so a replay is deterministic.
+pub(crate) struct DialogueReplay {
+    actor: ContextActor,
+    outputs: mpsc::Receiver<SmartSpeakerMessage>,
+    language: LanguageTag,
+    auto_speech_finished: bool,
+    requested_vision: Vec<VisionAction>,
+}
+
+impl DialogueReplay {
+    pub(crate) fn new(vision: bool, recipes: RecipeCatalog, language: LanguageTag, auto_speech_finished: bool) -> Self {
+        let (_, inputs) = mpsc::channel();
+        let (sender, outputs) = mpsc::channel();
+        Self {
+            actor: ContextActor::new(inputs, sender, vision, recipes),
+            outputs,
+            language,
+            auto_speech_finished,
+            requested_vision: vec![],
+        }
+    }
+
+    pub(crate) fn from_transcript(transcript: &DialogueTranscript, recipes: RecipeCatalog) -> Self {
+        Self::new(transcript.vision,
+                  recipes,
+                  LanguageTag::from_str(&transcript.language),
+                  transcript.auto_speech_finished)
+    }
+
+    pub(crate) fn feed(&mut self, input: &DialogueInput) -> Vec<DialogueOutput> {
+        let message = self.to_message(input);
+        self.actor.handle_message(message);
+        let mut outputs = vec![];
+        loop {
+            let mut spoken = 0;
+            while let Ok(message) = self.outputs.try_recv() {
+                if let Some(output) = self.to_output(message) {
+                    if let DialogueOutput::Speak(_) = output {
+                        spoken += 1;
+                    }
+                    outputs.push(output);
+                }
+            }
+            if !self.auto_speech_finished || spoken == 0 {
+                break
+            }
+            for _ in 0..spoken {
+                let message = self.to_message(&DialogueInput::SpeechFinished);
+                self.actor.handle_message(message);
+            }
+        }
+        outputs
+    }
+
+    /// Feed every turn and return the transcript with the actual outputs.
+    pub(crate) fn run(&mut self, turns: &Vec<DialogueTurn>) -> Vec<DialogueTurn> {
+        turns.iter().map(|turn| DialogueTurn {
+            input: turn.input.clone(),
+            expect: self.feed(&turn.input),
+        }).collect()
+    }
+
+    /// Replay the transcript and fail at the first turn whose outputs differ.
+    pub(crate) fn verify(transcript: &DialogueTranscript, recipes: RecipeCatalog) -> Result<()> {
+        let mut replay = Self::from_transcript(transcript, recipes);
+        for (i, turn) in transcript.turns.iter().enumerate() {
+            let actual = replay.feed(&turn.input);
+            if actual != turn.expect {
+                return Err(anyhow!("turn {} ({:?}) differs.\nexpected: {:?}\nactual: {:?}", i, turn.input, turn.expect, actual))
+            }
+        }
+        Ok(())
+    }
+
+    fn to_message(&self, input: &DialogueInput) -> SmartSpeakerMessage {
+        match input {
+            DialogueInput::Intent { action, menu, failed } => {
+                let mut entities: Vec<Box<dyn IntentSlot>> = vec![];
+                if let Some(menu) = menu {
+                    entities.push(Box::new(IntentCookingMenu::new(menu)));
+                }
+                SmartSpeakerMessage::IntentFinalized(IntentFinalizedMessage {
+                    send_from: SmartSpeakerActors::SpeechToIntentActor,
+                    send_to: SmartSpeakerActors::ContextActor,
+                    result: if *failed { ProcessResult::Failure } else { ProcessResult::Success },
+                    content: IntentContent::new(action.clone(), entities),
+                })
+            }
+            DialogueInput::Vision { objects, markers, failed, unsure } => {
+                // the vision actor answers with the action it was asked for
+                let action = self.requested_vision.first().cloned().unwrap_or(VisionAction::None);
+                let entities = objects.iter().map(|o| {
+                    Box::new(VisionObject::new(o.object_type,
+                                               VisionObjectSize::new(o.area, o.width, o.height),
+                                               o.shape.clone())) as Box<dyn VisionSlot>
+                }).chain(markers.iter().map(|m| {
+                    Box::new(VisionMarker::new(m.marker, m.presence)) as Box<dyn VisionSlot>
+                })).collect::<Vec<Box<dyn VisionSlot>>>();
+                SmartSpeakerMessage::VisionFinalized(VisionFinalizedMessage {
+                    send_from: SmartSpeakerActors::VisionActor,
+                    send_to: SmartSpeakerActors::ContextActor,
+                    result: if *failed {
+                        ProcessResult::Failure
+                    } else if *unsure {
+                        ProcessResult::Unsure
+                    } else {
+                        ProcessResult::Success
+                    },
+                    contents: if *failed { vec![] } else { vec![VisionContent::new(action, entities)] },
+                })
+            }
+            DialogueInput::SpeechFinished => {
+                SmartSpeakerMessage::TextToSpeechFinished(StringMessage {
+                    send_from: SmartSpeakerActors::MachineSpeechActor,
+                    send_to: SmartSpeakerActors::ContextActor,
+                    message: "".to_string(),
+                })
+            }
+        }
+    }
+
+    fn to_output(&mut self, message: SmartSpeakerMessage) -> Option<DialogueOutput> {
+        match message {
+            SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage { send_from: _, send_to: _, message }) => {
+                Some(DialogueOutput::Speak(match message {
+                    TextToSpeechMessageType::Normal(text) => text.get(&self.language),
+                    TextToSpeechMessageType::Boilerplate(index) => boilerplate_text(index, &self.language),
+                }))
+            }
+            SmartSpeakerMessage::RequestStateUpdate(StateUpdateMessage { send_from: _, send_to: _, state }) => {
+                if let SmartSpeakerState::WaitingForInteraction(WaitingInteraction::Vision(actions)) = &state {
+                    self.requested_vision = actions.clone();
+                }
+                Some(DialogueOutput::State(normalize_state_label(&state.to_string())))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn boilerplate_text(index: usize, language: &LanguageTag) -> String {
+    match MachineSpeechBoilerplate::try_from(index) {
+        Ok(boilerplate) => boilerplate.to_i18n().get(language),
+        Err(_) => format!("boilerplate({})", index),
+    }
+}
+
+/// Vision states carry the whole detection detail. Transcripts only keep the kind of interaction.
+fn normalize_state_label(state: &str) -> String {
+    if state.starts_with("Pending(Vision(") {
+        "Pending(Vision)".to_string()
+    } else {
+        state.to_string()
+    }
+}
+
+/// Parse a `{:?}` formatted unit variant (e.g. `CookingTask`) with its snake_case serde name.
+fn from_debug_name<T: DeserializeOwned>(name: &str) -> Result<T> {
+    let mut snake = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(c.to_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    serde_json::from_value::<T>(json!(snake)).map_err(|e| anyhow!("unknown name {}: {}", name, e))
+}
+
+fn intent_input_from_payload(payload: &Value) -> Result<DialogueInput> {
+    Ok(DialogueInput::Intent {
+        action: from_debug_name::<IntentAction>(payload["intent"].as_str().ok_or(anyhow!("no intent in session record"))?)?,
+        menu: payload["entities"].as_array()
+            .and_then(|entities| entities.iter().find_map(|e| e["menu_name"].as_str()))
+            .map(|m| m.to_string()),
+        failed: payload["result"].as_str() == Some("Failure"),
+    })
+}
+
+fn vision_input_from_payload(payload: &Value) -> Result<DialogueInput> {
+    let mut objects = vec![];
+    let mut markers = vec![];
+    for content in payload["contents"].as_array().unwrap_or(&vec![]) {
+        for entity in content["entities"].as_array().unwrap_or(&vec![]) {
+            if entity.get("marker").is_some() {
+                markers.push(DialogueVisionMarker {
+                    marker: serde_json::from_value::<MarkerTag>(entity["marker"].clone())
+                        .map_err(|e| anyhow!("unknown marker {}: {}", entity["marker"], e))?,
+                    presence: serde_json::from_value::<MarkerPresence>(entity["presence"].clone())
+                        .map_err(|e| anyhow!("unknown marker presence {}: {}", entity["presence"], e))?,
+                });
+                continue;
+            }
+            if entity.get("object_type").is_none() {
+                continue;
+            }
+            objects.push(DialogueVisionObject {
+                object_type: from_debug_name::<DetectableObject>(entity["object_type"].as_str().unwrap_or_default())?,
+                shape: from_debug_name::<VisionObjectShape>(entity["shape"].as_str().unwrap_or_default())?,
+                area: entity["area"].as_f64().or(entity["perimeter"].as_f64()).unwrap_or_default() as f32,
+                width: entity["width"].as_f64().unwrap_or_default() as f32,
+                height: entity["height"].as_f64().unwrap_or_default() as f32,
+            });
+        }
+    }
+    Ok(DialogueInput::Vision {
+        objects,
+        markers,
+        failed: payload["result"].as_str() == Some("Failure"),
+        unsure: payload["result"].as_str() == Some("Unsure"),
+    })
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod replay_util_tests {
+    use crate::smart_speaker::models::intent_model::IntentAction;
+    use crate::smart_speaker::models::message_model::*;
+    use crate::smart_speaker::models::recipe_model::RecipeCatalog;
+    use crate::smart_speaker::models::session_model::{SessionMediaMode, SessionRecorder};
+    use crate::smart_speaker::models::intent_model::IntentCookingMenu;
+    use crate::smart_speaker::models::vision_model::{DetectableObject, VisionObjectShape};
+    use crate::utils::config_util::LanguageTag;
+    use super::super::replay_util::*;
+
+    fn recipes() -> RecipeCatalog {
+        RecipeCatalog::load(&format!("{}/resources/recipes", env!("CARGO_MANIFEST_DIR"))).unwrap()
+    }
+
+    fn intent(action: IntentAction) -> DialogueInput {
+        DialogueInput::Intent { action, menu: None, failed: false }
+    }
+
+    fn start_carrot_salad_until_measure(replay: &mut DialogueReplay) {
+        replay.feed(&DialogueInput::Intent {
+            action: IntentAction::CookingTask,
+            menu: Some("にんじんサラダ".to_string()),
+            failed: false,
+        });
+        let mut outputs = vec![];
+        for _ in 0..4 {
+            outputs = replay.feed(&intent(IntentAction::Next));
+        }
+        assert_eq!(outputs.last(), Some(&DialogueOutput::State("Pending(Vision)".to_string())));
+    }
+
+    #[test]
+    fn replay_transcript_test() {
+        let transcript = DialogueTranscript::load(
+            &format!("{}/resources/transcripts/potato_salad_cancel.json", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        DialogueReplay::verify(&transcript, recipes()).unwrap();
+    }
+
+    #[test]
+    fn replay_transcript_mismatch_test() {
+        let mut transcript = DialogueTranscript::load(
+            &format!("{}/resources/transcripts/potato_salad_cancel.json", env!("CARGO_MANIFEST_DIR"))).unwrap();
+        transcript.turns.last_mut().unwrap().expect = vec![DialogueOutput::State("Pending(Speak)".to_string())];
+        assert!(DialogueReplay::verify(&transcript, recipes()).is_err());
+    }
+
+    #[test]
+    fn replay_unknown_menu_test() {
+        let mut replay = DialogueReplay::new(false, recipes(), LanguageTag::English, false);
+        let outputs = replay.feed(&DialogueInput::Intent {
+            action: IntentAction::CookingTask,
+            menu: Some("beef stew".to_string()),
+            failed: false,
+        });
+        assert_eq!(outputs, vec![
+            DialogueOutput::Speak("Sorry. I can't hear you very well. Please repeat your message".to_string()),
+            DialogueOutput::State("Idle".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn replay_measure_revision_test() {
+        let mut replay = DialogueReplay::new(true, recipes(), LanguageTag::Japanese, true);
+        start_carrot_salad_until_measure(&mut replay);
+        let outputs = replay.feed(&DialogueInput::Vision {
+            objects: vec![DialogueVisionObject {
+                object_type: DetectableObject::Carrot,
+                shape: VisionObjectShape::Rectangle,
+                area: 120.,
+                width: 0.,
+                height: 0.,
+            }],
+            markers: vec![],
+            failed: false,
+            unsure: false,
+        });
+        assert_eq!(outputs, vec![
+            DialogueOutput::Speak("レシピで必要な量よりも多いようです。 残りの説明のために覚えておきます。".to_string()),
+            DialogueOutput::Speak("続いて、人参を食べやすい大きさに切ってください。".to_string()),
+            DialogueOutput::State("Pending(Speak)".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn replay_vision_failed_test() {
+        let mut replay = DialogueReplay::new(true, recipes(), LanguageTag::English, true);
+        start_carrot_salad_until_measure(&mut replay);
+        let outputs = replay.feed(&DialogueInput::Vision { objects: vec![], markers: vec![], failed: true, unsure: false });
+        assert_eq!(outputs, vec![
+            DialogueOutput::Speak("Sorry. I can't see very well. Please show me again.".to_string()),
+            DialogueOutput::Speak("Sorry. I can't see very well. Please show me again.".to_string()),
+            DialogueOutput::State("Pending(Vision)".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn replay_vision_unsure_test() {
+        let mut replay = DialogueReplay::new(true, recipes(), LanguageTag::English, true);
+        start_carrot_salad_until_measure(&mut replay);
+        let outputs = replay.feed(&DialogueInput::Vision { objects: vec![], markers: vec![], failed: false, unsure: true });
+        assert_eq!(outputs, vec![
+            DialogueOutput::Speak("Sorry. I'm not sure which one you mean. Please keep looking at it.".to_string()),
+        ]);
+        // the same detection is requested again after the clarification
+        let outputs = replay.feed(&DialogueInput::SpeechFinished);
+        assert_eq!(outputs, vec![DialogueOutput::State("Pending(Vision)".to_string())]);
+    }
+
+    #[test]
+    fn replay_vision_unsure_limit_test() {
+        let mut replay = DialogueReplay::new(true, recipes(), LanguageTag::English, true);
+        start_carrot_salad_until_measure(&mut replay);
+        for _ in 0..2 {
+            replay.feed(&DialogueInput::Vision { objects: vec![], markers: vec![], failed: false, unsure: true });
+            assert_eq!(replay.feed(&DialogueInput::SpeechFinished), vec![DialogueOutput::State("Pending(Vision)".to_string())]);
+        }
+        // the clarification is not asked for forever
+        let outputs = replay.feed(&DialogueInput::Vision { objects: vec![], markers: vec![], failed: false, unsure: true });
+        assert_eq!(outputs, vec![
+            DialogueOutput::Speak("Sorry. I can't see very well. Please show me again.".to_string()),
+            DialogueOutput::Speak("Sorry. I can't see very well. Please show me again.".to_string()),
+            DialogueOutput::State("Pending(Vision)".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn transcript_from_session_test() {
+        let path = std::env::temp_dir().join("vas_replay_from_session.jsonl");
+        let path = path.to_str().unwrap();
+        let mut recorder = SessionRecorder::new(path, SessionMediaMode::Elide).unwrap();
+        recorder.record(&SmartSpeakerMessage::IntentFinalized(IntentFinalizedMessage {
+            send_from: SmartSpeakerActors::SpeechToIntentActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            result: ProcessResult::Success,
+            content: IntentContent::new(IntentAction::CookingTask, vec![Box::new(IntentCookingMenu::new("ポテトサラダ"))]),
+        })).unwrap();
+        recorder.record(&SmartSpeakerMessage::RequestTextToSpeech(TextToSpeechMessage {
+            send_from: SmartSpeakerActors::ContextActor,
+            send_to: SmartSpeakerActors::MachineSpeechActor,
+            message: TextToSpeechMessageType::Normal(SmartSpeakerI18nText::new().en("start").ja("始めます")),
+        })).unwrap();
+        recorder.record(&SmartSpeakerMessage::TextToSpeechFinished(StringMessage {
+            send_from: SmartSpeakerActors::MachineSpeechActor,
+            send_to: SmartSpeakerActors::ContextActor,
+            message: "".to_string(),
+        })).unwrap();
+        drop(recorder);
+        let transcript = DialogueTranscript::from_session(path, false, &LanguageTag::Japanese).unwrap();
+        assert_eq!(transcript.turns, vec![
+            DialogueTurn {
+                input: DialogueInput::Intent {
+                    action: IntentAction::CookingTask,
+                    menu: Some("ポテトサラダ".to_string()),
+                    failed: false,
+                },
+                expect: vec![DialogueOutput::Speak("始めます".to_string())],
+            },
+            DialogueTurn {
+                input: DialogueInput::SpeechFinished,
+                expect: vec![],
+            },
+        ]);
+        let _ = std::fs::remove_file(path);
+    }
+}
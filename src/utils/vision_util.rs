@@ -1,12 +1,13 @@
 use std::ops::Mul;
 use std::str::FromStr;
 use anyhow::{anyhow, Result};
-use opencv::core::{Point2f, in_range, Size, Point, bitwise_and, BORDER_DEFAULT, Vector, RotatedRect, Point_};
-use opencv::imgproc;
+use opencv::core::{Point2f, in_range, Size, Point, bitwise_and, BORDER_DEFAULT, Vector, RotatedRect, Mat, DECOMP_LU};
+use opencv::{calib3d, imgproc};
 use opencv::prelude::*;
 use opencv::types::{VectorOfPoint, VectorOfPoint2f, VectorOfVectorOfPoint, VectorOfVectorOfPoint2f};
-use crate::smart_speaker::models::vision_model::{CameraCaptureSource, Capture, DetectableObject, PupilCaptureSource};
-use crate::utils::camera_util::Camera;
+use crate::smart_speaker::models::marker_model::MarkerBoard;
+use crate::smart_speaker::models::vision_model::{CameraCaptureSource, Capture, DetectableObject, ImageSequenceCaptureSource, PupilCaptureSource, VideoFileCaptureSource};
+use crate::utils::camera_util::{Camera, CameraSettings, ImageSequence, VideoFile};
 use crate::utils::pupil_util::{Pupil, PupilRemote};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +15,7 @@ pub(crate) enum VisionType {
     None,
     Pupil,
     BuiltInCamera,
+    Recording,
 }
 
 impl FromStr for VisionType {
@@ -27,29 +29,14 @@ impl FromStr for VisionType {
             "built-in-camera" => Ok(VisionType::BuiltInCamera),
             "camera" => Ok(VisionType::BuiltInCamera),
             "cam" => Ok(VisionType::BuiltInCamera),
+            "recording" => Ok(VisionType::Recording),
+            "video" => Ok(VisionType::Recording),
+            "images" => Ok(VisionType::Recording),
             _ => Err(anyhow!("invalid vision type")),
         }
     }
 }
 
-pub(crate) enum MarkerPerimeter {
-    Small, // edge length: 50mm
-    Medium, // edge length: 100mm
-    Large, // edge length: 150mm
-    A4_12, // edge length: 38mm
-}
-
-impl MarkerPerimeter {
-    pub(crate) fn get_perimeter(&self) -> f32 {
-        match self {
-            MarkerPerimeter::Small => 20.,
-            MarkerPerimeter::Medium => 40.,
-            MarkerPerimeter::Large => 60.,
-            MarkerPerimeter::A4_12 => 15.2,
-        }
-    }
-}
-
 pub(crate) fn set_pupil_capture(capture: &mut Capture, zmq_endpoint: String) -> Result<()> {
     capture.source = Some(Box::new(
         PupilCaptureSource::new(
@@ -57,10 +44,24 @@ pub(crate) fn set_pupil_capture(capture: &mut Capture, zmq_endpoint: String) ->
     Ok(())
 }
 
-pub(crate) fn set_camera_capture(capture: &mut Capture) -> Result<()> {
+pub(crate) fn set_camera_capture(capture: &mut Capture, settings: &CameraSettings) -> Result<()> {
     capture.source = Some(Box::new(
         CameraCaptureSource::new(
-            Camera::new()?)));
+            Camera::new(settings)?)));
+    Ok(())
+}
+
+/// Replay a recording. A directory is read as an image sequence, otherwise as a video file.
+pub(crate) fn set_recording_capture(capture: &mut Capture, path: &str, fps: f64, looping: bool) -> Result<()> {
+    if std::path::Path::new(path).is_dir() {
+        capture.source = Some(Box::new(
+            ImageSequenceCaptureSource::new(
+                ImageSequence::new(path, fps, looping)?)));
+    } else {
+        capture.source = Some(Box::new(
+            VideoFileCaptureSource::new(
+                VideoFile::new(path, fps, looping)?)));
+    }
     Ok(())
 }
 
@@ -83,28 +84,6 @@ pub(crate) fn distance(px_x: &f32, px_y: &f32, py_x: &f32, py_y: &f32) -> f32 {
 
 // pub(crate) fn get_size_of_rotated_rect_from_midpoint
 
-pub(crate) fn aruco_perimeter_ratio(size: &[Point_<f32>; 4]) -> f32 {
-    let aruco_perimeter = distance(&size[0].x, &size[0].y, &size[1].x, &size[1].y)
-        + distance(&size[1].x, &size[1].y, &size[2].x, &size[2].y)
-        + distance(&size[2].x, &size[2].y, &size[3].x, &size[3].y)
-        + distance(&size[3].x, &size[3].y, &size[0].x, &size[0].y);
-    return aruco_perimeter / MarkerPerimeter::A4_12.get_perimeter();
-}
-
-pub(crate) fn aruco_side_ratio(side: f32) -> f32 {
-    let criteria = (MarkerPerimeter::A4_12.get_perimeter() / 4.) as f32;
-    side / criteria
-}
-
-/// get actual size of object by aruco marker
-pub(crate) fn pixel_to_metric(target: f32, ratio: &f32) -> f32 {
-    target / ratio
-}
-
-pub(crate) fn approx_to_arch_length_metric(contour: &VectorOfPoint2f, ratio: &f32) -> f32 {
-    imgproc::contour_area(&contour, false).unwrap() as f32 / ratio
-}
-
 pub(crate) fn get_min_rect(contour: &VectorOfPoint) -> RotatedRect {
     imgproc::min_area_rect(&contour).expect("TODO: panic message")
 }
@@ -199,21 +178,51 @@ pub(crate) fn get_object_contours(frame: &Mat) -> Result<VectorOfVectorOfPoint>
     Ok(selected_contours)
 }
 
-pub(crate) fn get_measure_criteria_from_aruco(aruco_corners: &VectorOfVectorOfPoint2f) -> Result<(Vec<f32>, Vec<f32>)> {
-    let mut width_ratios: Vec<f32> = Vec::new();
-    let mut height_ratios: Vec<f32> = Vec::new();
-    for i in 0..aruco_corners.len() {
-        let square = get_min_rect2f(&aruco_corners.get(i).unwrap());
-        let mut points = [Point2f::default(); 4];
-        square.points(&mut points).unwrap();
-        let width = distance(&points[1].x, &points[1].y, &points[2].x, &points[2].y);
-        let height = distance(&points[0].x, &points[0].y, &points[1].x, &points[1].y);
-        let width_ratio = aruco_side_ratio(width);
-        let height_ratio = aruco_side_ratio(height);
-        width_ratios.push(width_ratio);
-        height_ratios.push(height_ratio);
+/// Corners of a marker on the board in cm, clockwise from the top left like the detected corners.
+fn marker_square(x: f32, y: f32, side: f32) -> [Point2f; 4] {
+    [
+        Point2f::new(x, y),
+        Point2f::new(x + side, y),
+        Point2f::new(x + side, y + side),
+        Point2f::new(x, y + side),
+    ]
+}
+
+/// Homographies from the frame to the board plane, in cm. Lengths on the plane do not depend on the angle the plane is seen at.
+/// If two or more detected markers have a board position, one homography is fitted to all of their corners.
+/// Otherwise each untagged marker gives a plane with the marker at the origin. Tagged markers move with their tray or tool
+/// and may be tilted, so they are not used as planes.
+pub(crate) fn get_marker_planes(aruco_corners: &VectorOfVectorOfPoint2f, aruco_ids: &Vector<i32>, board: &MarkerBoard) -> Result<Vec<Mat>> {
+    let mut frame_points = VectorOfPoint2f::new();
+    let mut board_points = VectorOfPoint2f::new();
+    for (corners, id) in aruco_corners.iter().zip(aruco_ids.iter()) {
+        if let Some([x, y]) = board.marker(id).and_then(|marker| marker.position_mm) {
+            frame_points.extend(corners.iter());
+            board_points.extend(marker_square(x / 10., y / 10., board.marker_mm(id) / 10.));
+        }
+    }
+    if frame_points.len() >= 8 {
+        let homography = calib3d::find_homography(&frame_points, &board_points, &mut Mat::default(), 0, 3.)?;
+        if !homography.empty() {
+            return Ok(vec![homography]);
+        }
+    }
+    aruco_corners.iter().zip(aruco_ids.iter())
+        .filter(|(_, id)| board.tag(*id).is_none())
+        .map(|(corners, id)| {
+            let square = VectorOfPoint2f::from_iter(marker_square(0., 0., board.marker_mm(id) / 10.));
+            Ok(imgproc::get_perspective_transform(&corners, &square, DECOMP_LU)?)
+        })
+        .collect()
+}
+
+/// Points of the frame on the plane of `homography`.
+pub(crate) fn project_to_plane(points: &VectorOfPoint2f, homography: &Mat) -> Result<VectorOfPoint2f> {
+    let mut projected = VectorOfPoint2f::new();
+    if !points.is_empty() {
+        opencv::core::perspective_transform(points, &mut projected, homography)?;
     }
-    Ok((width_ratios, height_ratios))
+    Ok(projected)
 }
 
 pub(crate) fn gaze_to_px(gaze: &(f32, f32), frame_size: &(i32, i32)) -> (i32, i32) {